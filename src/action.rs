@@ -1,27 +1,62 @@
+use crate::clean_level::CleanLevel;
 use crate::equipment::Equipment;
+use crate::package_format::PackageFormat;
 
 #[derive(Debug, PartialEq)]
 pub enum Action {
     Process(Equipment),
-    Clean(Equipment),
-    Transfer(Equipment, Equipment),
+    Clean(Equipment, CleanLevel, Vec<Equipment>),
+    Transfer(Equipment, Box<Equipment>, Vec<Equipment>),
+    Maintain(Equipment, String),
+    Package(Equipment, PackageFormat, u32),
+    Propagate(String),
+    Pitch(String, usize, u32),
 }
 
 impl Action {
     pub fn lookup(&self) -> String {
         match self {
             Action::Process(equipment) => format!("Process ({})", equipment.name),
-            Action::Clean(equipment) => format!("Clean ({})", equipment.name),
-            Action::Transfer(from, to) => format!("Transfer (from {} to {})", from.name, to.name),
+            Action::Clean(equipment, level, _shared) => {
+                format!("Clean ({}: {})", equipment.name, level.lookup())
+            }
+            Action::Transfer(from, to, _shared) => {
+                format!("Transfer (from {} to {})", from.name, to.name)
+            }
+            Action::Maintain(equipment, task) => format!("Maintain ({}: {})", equipment.name, task),
+            Action::Package(line, format, units) => {
+                format!("Package ({}: {} x {})", line.name, units, format.lookup())
+            }
+            Action::Propagate(yeast) => format!("Propagate ({})", yeast),
+            Action::Pitch(yeast, from, generation) => {
+                format!(
+                    "Pitch ({}: generation {} from batch {})",
+                    yeast, generation, from
+                )
+            }
         }
     }
     pub fn resources(&self) -> Vec<String> {
         match self {
             Action::Process(equipment) => vec![equipment.name.clone()],
-            Action::Clean(equipment) => vec!["Cleaner".to_string(), equipment.name.clone()],
-            Action::Transfer(from, to) => {
-                vec!["Pumper".to_string(), from.name.clone(), to.name.clone()]
+            Action::Clean(equipment, _level, shared) => {
+                let mut resources = vec!["Cleaner".to_string(), equipment.name.clone()];
+                resources.extend(shared.iter().map(|equipment| equipment.name.clone()));
+                resources
+            }
+            Action::Transfer(from, to, shared) => {
+                let mut resources = vec!["Pumper".to_string(), from.name.clone(), to.name.clone()];
+                resources.extend(shared.iter().map(|equipment| equipment.name.clone()));
+                resources
             }
+            Action::Maintain(equipment, _task) => {
+                vec!["Maintainer".to_string(), equipment.name.clone()]
+            }
+            Action::Package(line, _format, _units) => {
+                vec!["Packager".to_string(), line.name.clone()]
+            }
+            Action::Propagate(_yeast) => vec!["Propagator".to_string()],
+            Action::Pitch(_yeast, _from, _generation) => vec!["Pitcher".to_string()],
         }
     }
 }
@@ -36,11 +71,23 @@ pub mod mock {
     }
 
     pub fn mock_clean(equipment: Equipment) -> Action {
-        Action::Clean(equipment)
+        Action::Clean(equipment, CleanLevel::FullCip, vec![])
     }
 
     pub fn mock_transfer(equipment: Equipment, other: Equipment) -> Action {
-        Action::Transfer(equipment, other)
+        Action::Transfer(equipment, Box::new(other), vec![])
+    }
+
+    pub fn mock_maintain(equipment: Equipment) -> Action {
+        Action::Maintain(equipment, "Screen service".to_string())
+    }
+
+    pub fn mock_package(line: Equipment) -> Action {
+        Action::Package(line, PackageFormat::Can16oz, 600)
+    }
+
+    pub fn mock_pitch() -> Action {
+        Action::Pitch("US-05".to_string(), 1, 2)
     }
 }
 
@@ -55,14 +102,17 @@ mod tests {
     fn test_action_new() {
         let equipment = equipment::mock::mock_equipment();
         let action = mock::mock_clean(equipment.clone());
-        assert_eq!(action, Action::Clean(equipment));
+        assert_eq!(
+            action,
+            Action::Clean(equipment, CleanLevel::FullCip, vec![])
+        );
     }
 
     #[test]
     fn test_action_lookup() {
         let equipment_1 = equipment::mock::mock_equipment();
         let action_1 = mock::mock_clean(equipment_1.clone());
-        assert_eq!(&action_1.lookup(), "Clean (Foobar 2000)");
+        assert_eq!(&action_1.lookup(), "Clean (Foobar 2000: Full CIP)");
 
         let action_2 = mock::mock_process(equipment_1.clone());
         assert_eq!(&action_2.lookup(), "Process (Foobar 2000)");
@@ -77,6 +127,18 @@ mod tests {
             &action_3.lookup(),
             "Transfer (from Foobar 2000 to Foobar 2001)"
         );
+
+        let action_4 = mock::mock_maintain(equipment_1.clone());
+        assert_eq!(&action_4.lookup(), "Maintain (Foobar 2000: Screen service)");
+
+        let action_5 = mock::mock_package(equipment_1.clone());
+        assert_eq!(&action_5.lookup(), "Package (Foobar 2000: 600 x 16oz Can)");
+
+        let action_6 = mock::mock_pitch();
+        assert_eq!(
+            &action_6.lookup(),
+            "Pitch (US-05: generation 2 from batch 1)"
+        );
     }
 
     #[test]
@@ -105,5 +167,31 @@ mod tests {
                 "Foobar 2001".to_string()
             ]
         );
+
+        let action_4 = mock::mock_maintain(equipment_1.clone());
+        assert_eq!(
+            action_4.resources(),
+            vec!["Maintainer".to_string(), "Foobar 2000".to_string()]
+        );
+
+        let pump = Equipment::new(
+            "Pump 1".to_string(),
+            capacity::mock::mock_bbl5(),
+            equipment_group::mock::mock_pump(),
+        );
+        let action_5 = Action::Transfer(
+            equipment_1.clone(),
+            Box::new(equipment_1.clone()),
+            vec![pump],
+        );
+        assert_eq!(
+            action_5.resources(),
+            vec![
+                "Pumper".to_string(),
+                "Foobar 2000".to_string(),
+                "Foobar 2000".to_string(),
+                "Pump 1".to_string()
+            ]
+        );
     }
 }
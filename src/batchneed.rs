@@ -1,6 +1,10 @@
+use chrono::{DateTime, Utc};
+
 use crate::beer::Beer;
+use crate::capability::Capability;
 use crate::capacity::Capacity;
 use crate::interval::Interval;
+use crate::package_format::PackageFormat;
 use crate::step_group::StepGroup;
 use crate::volume::Volume;
 
@@ -10,6 +14,8 @@ pub struct BatchNeed<'a> {
     pub beer: &'a Beer,
     pub system: Capacity,
     pub volume: Volume,
+    pub packaging: Vec<(PackageFormat, u32)>,
+    pub due: Option<DateTime<Utc>>,
 }
 
 impl<'a> BatchNeed<'a> {
@@ -19,6 +25,41 @@ impl<'a> BatchNeed<'a> {
             beer,
             system,
             volume,
+            packaging: Vec::new(),
+            due: None,
+        }
+    }
+
+    /// Package some of the batch in a format.
+    pub fn with_packaging(mut self, format: PackageFormat, units: u32) -> Self {
+        self.packaging.push((format, units));
+        self
+    }
+
+    /// The batch has to be finished, and packaged, by then.
+    pub fn with_due(mut self, due: DateTime<Utc>) -> Self {
+        self.due = Some(due);
+        self
+    }
+
+    /// The latest the batch can be brewed and still be finished when it is due, when every
+    /// step takes as long as it may.
+    pub fn brew_by(&self) -> Option<DateTime<Utc>> {
+        let due = self.due?;
+        let (_max_volume, steps) = self.beer.recipe.get(&self.system)?;
+        Some(due - steps.range().1)
+    }
+
+    /// What is left of the batch, that is not allocated to a package format yet.
+    pub fn unpackaged(&self) -> Volume {
+        let packaged = self
+            .packaging
+            .iter()
+            .map(|(format, units)| format.volume_of(*units).liters())
+            .sum::<f32>();
+        match self.volume.to_liter() {
+            Volume::Liter(liter) => Volume::Liter((liter - packaged).max(0.0)),
+            _ => panic!("Should not happen"),
         }
     }
 
@@ -29,6 +70,34 @@ impl<'a> BatchNeed<'a> {
         }
         panic!("Should not happen");
     }
+
+    /// The step the yeast is pitched at the start of, and harvested at the end of: the first
+    /// fermentation step.
+    pub fn pitch_step(&self) -> Option<StepGroup> {
+        self.steps()
+            .into_iter()
+            .map(|(step_group, _interval)| step_group)
+            .find(|step_group| step_group.capability() == Capability::Fermentation)
+    }
+
+    /// Where the step is in the sequence of the recipe for this batch.
+    pub fn step_position(&self, step_group: &StepGroup) -> usize {
+        if let Some((_max_volume, steps)) = self.beer.recipe.get(&self.system) {
+            // The packaging is not a step of the recipe, it comes after all of them
+            if step_group == &StepGroup::Packaging {
+                return steps.iter().count();
+            }
+            if let Some(pos) = steps.position(step_group) {
+                return pos;
+            }
+        }
+        panic!(
+            "Beer {} has no step {} for system {:?}",
+            self.beer.name,
+            step_group.lookup(),
+            self.system
+        );
+    }
 }
 
 #[cfg(test)]
@@ -47,6 +116,7 @@ mod tests {
     use crate::beer;
     use crate::capacity;
     use crate::volume;
+    use chrono::TimeZone;
 
     #[test]
     fn test_batchneed_new() {
@@ -58,4 +128,42 @@ mod tests {
         assert_eq!(batchneed.system, system);
         assert_eq!(batchneed.volume, volume::mock::mock_gallon_us());
     }
+
+    #[test]
+    fn test_batchneed_step_position() {
+        let beer = beer::mock::mock_beer();
+        let batchneed = mock::mock_batchneed(&beer, capacity::mock::mock_g5());
+        assert_eq!(batchneed.step_position(&StepGroup::Brewing), 0);
+        assert_eq!(batchneed.step_position(&StepGroup::Carbonation), 4);
+        assert_eq!(batchneed.step_position(&StepGroup::Packaging), 5);
+    }
+
+    #[test]
+    fn test_batchneed_pitch_step() {
+        let beer = beer::mock::mock_brew_day_beer();
+        let batchneed =
+            BatchNeed::new(1, &beer, capacity::mock::mock_g10(), Volume::GallonUS(10.0));
+        assert_eq!(batchneed.pitch_step(), Some(StepGroup::PrimaryFermentation));
+    }
+
+    #[test]
+    fn test_batchneed_brew_by() {
+        let beer = beer::mock::mock_brew_day_beer();
+        let batchneed = mock::mock_batchneed(&beer, capacity::mock::mock_g10());
+        assert_eq!(batchneed.brew_by(), None);
+        let due = Utc.with_ymd_and_hms(2021, 9, 15, 0, 0, 0).unwrap();
+        let (_volume, steps) = beer.recipe.get(&capacity::mock::mock_g10()).unwrap();
+        assert_eq!(
+            batchneed.with_due(due).brew_by(),
+            Some(due - steps.range().1)
+        );
+    }
+
+    #[test]
+    fn test_batchneed_unpackaged() {
+        let beer = beer::mock::mock_beer();
+        let batchneed = BatchNeed::new(1, &beer, capacity::mock::mock_g5(), Volume::Liter(10.0))
+            .with_packaging(PackageFormat::Bottle750ml, 8);
+        assert_eq!(batchneed.unpackaged(), Volume::Liter(4.0));
+    }
 }
@@ -1,4 +1,9 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+
 use crate::config::{FactoryConfig, RecipeConfig};
+use crate::interval::Interval;
 use crate::recipe::Recipe;
 use crate::style::Style;
 
@@ -7,6 +12,12 @@ pub struct Beer {
     pub name: String,
     pub style: Style,
     pub recipe: Recipe,
+    pub allergens: Vec<String>,
+    pub tags: Vec<String>,
+    pub max_age: Option<Duration>,
+    pub yeast: Option<String>,
+    pub version: Option<u32>,
+    pub effective: Option<DateTime<Utc>>,
 }
 
 impl Beer {
@@ -21,8 +32,64 @@ impl Beer {
             name,
             style,
             recipe,
+            allergens: Vec::new(),
+            tags: Vec::new(),
+            max_age: None,
+            yeast: None,
+            version: None,
+            effective: None,
+        }
+    }
+
+    /// Allergens, like lactose or gluten, that may not be left behind for the next beer.
+    pub fn with_allergens(mut self, allergens: Vec<String>) -> Self {
+        self.allergens = allergens;
+        self
+    }
+
+    /// Tags, like "sour" or "gluten-free", that the segregation rules of the factory use.
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// How old the beer may be when it is due, instead of what is usual for its style.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// The yeast strain that is pitched, see `Factory::yeasts`.
+    pub fn with_yeast(mut self, yeast: String) -> Self {
+        self.yeast = Some(yeast);
+        self
+    }
+
+    /// The version of the recipe, see `Factory::recipe_history`.
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// The moment from which new batches are brewed with this version of the recipe. Without
+    /// it, the version has always been in use.
+    pub fn with_effective(mut self, effective: DateTime<Utc>) -> Self {
+        self.effective = Some(effective);
+        self
+    }
+
+    /// The name of the beer, with the version of the recipe when it has one, like "Bier v2".
+    pub fn label(&self) -> String {
+        match self.version {
+            Some(version) => format!("{} v{}", self.name, version),
+            None => self.name.clone(),
         }
     }
+
+    /// How old the beer may be when it is due, counted from the moment it is packaged.
+    pub fn shelf_life(&self) -> Option<Duration> {
+        self.max_age.or_else(|| self.style.max_age())
+    }
 }
 
 #[cfg(test)]
@@ -38,17 +105,59 @@ pub mod mock {
             recipe::mock::mock_recipe(),
         )
     }
+
+    pub fn mock_brew_day_beer() -> Beer {
+        Beer::new(
+            "foobeer 3000".to_string(),
+            style::mock::mock_blonde_ale(),
+            recipe::mock::mock_brew_day_recipe(),
+        )
+    }
 }
 
 impl From<(&FactoryConfig, &RecipeConfig)> for Beer {
     fn from(config: (&FactoryConfig, &RecipeConfig)) -> Self {
         let (factory_config, recipe_config) = config;
         let cloned_str = String::from(&recipe_config.name);
-        Beer::new(
+        let style = match &recipe_config.style {
+            Some(style) => match Style::from_str(style) {
+                Ok(x) => x,
+                Err(_e) => panic!(
+                    "{} does not appear to be a valid style for {}",
+                    style, recipe_config.name
+                ),
+            },
+            None => Style::BlondeAle,
+        };
+        let beer = Beer::new(
             cloned_str,
-            Style::BlondeAle,
+            style,
             Recipe::from((factory_config, recipe_config)),
         )
+        .with_allergens(recipe_config.allergens.clone())
+        .with_tags(recipe_config.tags.clone());
+        let beer = match &recipe_config.yeast {
+            Some(yeast) => beer.with_yeast(yeast.clone()),
+            None => beer,
+        };
+        let beer = match recipe_config.version {
+            Some(version) => beer.with_version(version),
+            None => beer,
+        };
+        let beer = match recipe_config.effective {
+            Some(effective) => beer.with_effective(effective),
+            None => beer,
+        };
+        match &recipe_config.max_age {
+            Some(max_age) => match Interval::from_str(max_age) {
+                Ok(x) => beer.with_max_age(x.duration()),
+                Err(_e) => panic!(
+                    "{} does not appear to be a valid max age for {}",
+                    max_age, recipe_config.name
+                ),
+            },
+            None => beer,
+        }
     }
 }
 
@@ -64,5 +173,29 @@ mod tests {
         assert_eq!(&beer.name, "foobeer 2000");
         assert_eq!(beer.style, style::mock::mock_blonde_ale());
         assert_eq!(beer.recipe, recipe::mock::mock_recipe());
+        assert!(beer.allergens.is_empty());
+        assert!(beer.tags.is_empty());
+    }
+
+    #[test]
+    fn test_beer_label() {
+        let beer = mock::mock_beer();
+        assert_eq!(beer.label(), "foobeer 2000");
+        let beer = beer.with_version(2);
+        assert_eq!(beer.label(), "foobeer 2000 v2");
+    }
+
+    #[test]
+    fn test_beer_shelf_life() {
+        let beer = mock::mock_beer();
+        assert_eq!(beer.shelf_life(), None);
+        let beer = beer.with_max_age(Duration::weeks(8));
+        assert_eq!(beer.shelf_life(), Some(Duration::weeks(8)));
+        let ipa = Beer::new(
+            "Anti-Scurvy Elixir".to_string(),
+            style::mock::mock_ipa(),
+            recipe::mock::mock_recipe(),
+        );
+        assert_eq!(ipa.shelf_life(), Some(Duration::weeks(2)));
     }
 }
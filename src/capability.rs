@@ -0,0 +1,105 @@
+/**
+ * What a piece of equipment can be used for. Steps ask for a capability instead of a specific
+ * `EquipmentGroup`, so that a unitank can do fermentation as well as carbonation and serving.
+ */
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum Capability {
+    HeatingWater,
+    Mashing,
+    Lautering,
+    Boiling,
+    Fermentation,
+    Conditioning,
+    Carbonation,
+    Serving,
+    Packaging,
+}
+
+impl Capability {
+    pub fn lookup(&self) -> &'static str {
+        match self {
+            Capability::HeatingWater => "Heating Water",
+            Capability::Mashing => "Mashing",
+            Capability::Lautering => "Lautering",
+            Capability::Boiling => "Boiling",
+            Capability::Fermentation => "Fermentation",
+            Capability::Conditioning => "Conditioning",
+            Capability::Carbonation => "Carbonation",
+            Capability::Serving => "Serving",
+            Capability::Packaging => "Packaging",
+        }
+    }
+}
+
+impl std::str::FromStr for Capability {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Capability, ()> {
+        match s {
+            "Heating Water" => Ok(Capability::HeatingWater),
+            "heating" => Ok(Capability::HeatingWater),
+            "Mashing" => Ok(Capability::Mashing),
+            "mashing" => Ok(Capability::Mashing),
+            "Lautering" => Ok(Capability::Lautering),
+            "lautering" => Ok(Capability::Lautering),
+            "Boiling" => Ok(Capability::Boiling),
+            "boiling" => Ok(Capability::Boiling),
+            "Fermentation" => Ok(Capability::Fermentation),
+            "fermentation" => Ok(Capability::Fermentation),
+            "Conditioning" => Ok(Capability::Conditioning),
+            "conditioning" => Ok(Capability::Conditioning),
+            "Carbonation" => Ok(Capability::Carbonation),
+            "carbonation" => Ok(Capability::Carbonation),
+            "Serving" => Ok(Capability::Serving),
+            "serving" => Ok(Capability::Serving),
+            "Packaging" => Ok(Capability::Packaging),
+            "packaging" => Ok(Capability::Packaging),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_fermentation() -> Capability {
+        Capability::Fermentation
+    }
+
+    pub fn mock_carbonation() -> Capability {
+        Capability::Carbonation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capability_lookup() {
+        assert_eq!(Capability::HeatingWater.lookup(), "Heating Water");
+        assert_eq!(Capability::Mashing.lookup(), "Mashing");
+        assert_eq!(Capability::Lautering.lookup(), "Lautering");
+        assert_eq!(Capability::Boiling.lookup(), "Boiling");
+        assert_eq!(Capability::Fermentation.lookup(), "Fermentation");
+        assert_eq!(Capability::Conditioning.lookup(), "Conditioning");
+        assert_eq!(Capability::Carbonation.lookup(), "Carbonation");
+        assert_eq!(Capability::Serving.lookup(), "Serving");
+        assert_eq!(Capability::Packaging.lookup(), "Packaging");
+    }
+
+    #[test]
+    fn test_capability_parse() {
+        assert_eq!("Heating Water".parse(), Ok(Capability::HeatingWater));
+        assert_eq!("mashing".parse(), Ok(Capability::Mashing));
+        assert_eq!("Lautering".parse(), Ok(Capability::Lautering));
+        assert_eq!("boiling".parse(), Ok(Capability::Boiling));
+        assert_eq!("Fermentation".parse(), Ok(Capability::Fermentation));
+        assert_eq!("conditioning".parse(), Ok(Capability::Conditioning));
+        assert_eq!("Carbonation".parse(), Ok(Capability::Carbonation));
+        assert_eq!("serving".parse(), Ok(Capability::Serving));
+        assert_eq!("packaging".parse(), Ok(Capability::Packaging));
+        assert_eq!("brewing".parse::<Capability>(), Err(()));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::interval::Interval;
+use crate::step_group::StepGroup;
+use crate::volume::Volume;
+
+/**
+ * How a recipe carbonates its beer. Each method comes with its own step, and thereby with the
+ * vessel it needs: forced carbonation is done in a pressure-rated brite tank (or unitank), a
+ * spunded beer keeps its own CO2 while it finishes fermenting in the fermentor, and a
+ * keg-conditioned beer referments in the keg.
+ *
+ * CO2 is not a vessel that is booked, but gas that is used up. Only the gas that is pushed into
+ * the beer, or that is used to purge the kegs, is taken from the cylinders.
+ */
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum CarbonationMethod {
+    Forced,
+    Spunding,
+    KegConditioned,
+}
+
+impl CarbonationMethod {
+    pub fn lookup(&self) -> &'static str {
+        match self {
+            CarbonationMethod::Forced => "Forced",
+            CarbonationMethod::Spunding => "Spunding",
+            CarbonationMethod::KegConditioned => "Keg Conditioned",
+        }
+    }
+
+    pub fn step_group(&self) -> StepGroup {
+        match self {
+            CarbonationMethod::Forced => StepGroup::Carbonation,
+            CarbonationMethod::Spunding => StepGroup::Spunding,
+            CarbonationMethod::KegConditioned => StepGroup::KegConditioning,
+        }
+    }
+
+    /// How long the carbonation takes, when the recipe does not say otherwise.
+    pub fn interval(&self) -> Interval {
+        match self {
+            CarbonationMethod::Forced => Interval::Days(2),
+            CarbonationMethod::Spunding => Interval::Days(5),
+            CarbonationMethod::KegConditioned => Interval::Weeks(2),
+        }
+    }
+
+    /// The CO2 that is taken from the cylinders, in pounds per barrel of beer.
+    pub fn co2_per_bbl(&self) -> f32 {
+        // @TODO: these are rules of thumb, the actual usage depends on the wanted volumes of CO2
+        match self {
+            CarbonationMethod::Forced => 1.5,
+            CarbonationMethod::Spunding => 0.0,
+            CarbonationMethod::KegConditioned => 0.1,
+        }
+    }
+
+    pub fn co2_usage(&self, volume: &Volume) -> Volume {
+        match volume.to_bbl() {
+            Volume::BeerBarrel(bbl) => Volume::Lb(self.co2_per_bbl() * bbl),
+            _ => panic!("Should not happen"),
+        }
+    }
+}
+
+impl std::str::FromStr for CarbonationMethod {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<CarbonationMethod, ()> {
+        match s {
+            "Forced" => Ok(CarbonationMethod::Forced),
+            "forced" => Ok(CarbonationMethod::Forced),
+            "Spunding" => Ok(CarbonationMethod::Spunding),
+            "spunding" => Ok(CarbonationMethod::Spunding),
+            "Keg Conditioned" => Ok(CarbonationMethod::KegConditioned),
+            "keg" => Ok(CarbonationMethod::KegConditioned),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_forced() -> CarbonationMethod {
+        CarbonationMethod::Forced
+    }
+
+    pub fn mock_spunding() -> CarbonationMethod {
+        CarbonationMethod::Spunding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_carbonation_method_lookup() {
+        assert_eq!(CarbonationMethod::Forced.lookup(), "Forced");
+        assert_eq!(CarbonationMethod::Spunding.lookup(), "Spunding");
+        assert_eq!(
+            CarbonationMethod::KegConditioned.lookup(),
+            "Keg Conditioned"
+        );
+    }
+
+    #[test]
+    fn test_carbonation_method_step_group() {
+        assert_eq!(
+            CarbonationMethod::Forced.step_group(),
+            StepGroup::Carbonation
+        );
+        assert_eq!(
+            CarbonationMethod::Spunding.step_group(),
+            StepGroup::Spunding
+        );
+        assert_eq!(
+            CarbonationMethod::KegConditioned.step_group(),
+            StepGroup::KegConditioning
+        );
+        assert_eq!(
+            StepGroup::Spunding.carbonation_method(),
+            Some(CarbonationMethod::Spunding)
+        );
+        assert_eq!(StepGroup::Aging.carbonation_method(), None);
+    }
+
+    #[test]
+    fn test_carbonation_method_interval() {
+        assert_eq!(mock::mock_forced().interval(), Interval::Days(2));
+        assert_eq!(mock::mock_spunding().interval(), Interval::Days(5));
+        assert_eq!(
+            CarbonationMethod::KegConditioned.interval(),
+            Interval::Weeks(2)
+        );
+    }
+
+    #[test]
+    fn test_carbonation_method_co2_usage() {
+        assert_eq!(
+            mock::mock_forced().co2_usage(&Volume::BeerBarrel(10.0)),
+            Volume::Lb(15.0)
+        );
+        assert_eq!(
+            mock::mock_spunding().co2_usage(&Volume::BeerBarrel(10.0)),
+            Volume::Lb(0.0)
+        );
+    }
+
+    #[test]
+    fn test_carbonation_method_parse() {
+        assert_eq!("Forced".parse(), Ok(CarbonationMethod::Forced));
+        assert_eq!("spunding".parse(), Ok(CarbonationMethod::Spunding));
+        assert_eq!("keg".parse(), Ok(CarbonationMethod::KegConditioned));
+        assert_eq!("bottle".parse::<CarbonationMethod>(), Err(()));
+    }
+}
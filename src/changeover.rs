@@ -0,0 +1,165 @@
+use std::str::FromStr;
+
+use crate::beer::Beer;
+use crate::clean_level::CleanLevel;
+use crate::config::ChangeoverConfig;
+
+/// A rule of the changeover matrix. `from` and `to` match a beer name, a style, or anything ("*").
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangeoverRule {
+    pub from: String,
+    pub to: String,
+    pub clean: CleanLevel,
+}
+
+impl ChangeoverRule {
+    pub fn new(from: String, to: String, clean: CleanLevel) -> Self {
+        Self { from, to, clean }
+    }
+
+    fn matches(pattern: &str, beer: &Beer) -> bool {
+        pattern == "*" || pattern == beer.name || pattern == beer.style.lookup()
+    }
+
+    pub fn applies(&self, from: &Beer, to: &Beer) -> bool {
+        ChangeoverRule::matches(&self.from, from) && ChangeoverRule::matches(&self.to, to)
+    }
+}
+
+impl std::convert::From<&ChangeoverConfig> for ChangeoverRule {
+    fn from(config: &ChangeoverConfig) -> Self {
+        let clean = match CleanLevel::from_str(&config.clean) {
+            Ok(x) => x,
+            Err(_e) => panic!(
+                "{} does not appear to be a valid clean level for the changeover from {} to {}",
+                config.clean, config.from, config.to
+            ),
+        };
+        ChangeoverRule::new(String::from(&config.from), String::from(&config.to), clean)
+    }
+}
+
+/**
+ * The changeover matrix decides how a vessel is cleaned, given the beer that was in it and the
+ * beer that goes in next. The rules of the factory go first, in the order they are given.
+ * Otherwise an allergen that is left behind, or a darker beer followed by a lighter one, needs a
+ * full clean in place. A rebrew of the same beer gets a rinse, and anything else is sanitized.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Changeover {
+    pub rules: Vec<ChangeoverRule>,
+}
+
+impl Changeover {
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    pub fn clean_level(&self, from: &Beer, to: &Beer) -> CleanLevel {
+        if let Some(rule) = self.rules.iter().find(|rule| rule.applies(from, to)) {
+            return rule.clean.clone();
+        }
+        if from
+            .allergens
+            .iter()
+            .any(|allergen| !to.allergens.contains(allergen))
+        {
+            return CleanLevel::FullCip;
+        }
+        if from.name == to.name {
+            return CleanLevel::Rinse;
+        }
+        if from.style.color() > to.style.color() {
+            return CleanLevel::FullCip;
+        }
+
+        CleanLevel::Sanitize
+    }
+}
+
+impl Default for Changeover {
+    fn default() -> Self {
+        Changeover::new()
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_changeover() -> Changeover {
+        let mut changeover = Changeover::new();
+        changeover.rules.push(ChangeoverRule::new(
+            "Fruit Beer".to_string(),
+            "*".to_string(),
+            CleanLevel::FullCip,
+        ));
+        changeover
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipe;
+    use crate::style::Style;
+
+    fn beer(name: &str, style: Style) -> Beer {
+        Beer::new(name.to_string(), style, recipe::mock::mock_recipe())
+    }
+
+    #[test]
+    fn test_changeover_defaults() {
+        let changeover = Changeover::new();
+        let stout = beer("Dobroy Nochi", Style::SpecialtyStout);
+        let blonde = beer("Autumn's Early Arrival Blonde", Style::BlondeAle);
+        assert_eq!(changeover.clean_level(&stout, &blonde), CleanLevel::FullCip);
+        assert_eq!(
+            changeover.clean_level(&blonde, &stout),
+            CleanLevel::Sanitize
+        );
+        assert_eq!(changeover.clean_level(&blonde, &blonde), CleanLevel::Rinse);
+    }
+
+    #[test]
+    fn test_changeover_allergens() {
+        let changeover = Changeover::new();
+        let milk_stout =
+            beer("Milk Stout", Style::SpecialtyStout).with_allergens(vec!["lactose".to_string()]);
+        let stout = beer("Dobroy Nochi", Style::SpecialtyStout);
+        assert_eq!(
+            changeover.clean_level(&milk_stout, &stout),
+            CleanLevel::FullCip
+        );
+        assert_eq!(
+            changeover.clean_level(&stout, &milk_stout),
+            CleanLevel::Sanitize
+        );
+    }
+
+    #[test]
+    fn test_changeover_rules() {
+        let changeover = mock::mock_changeover();
+        let fruit = beer("Blues Don't Bother Me", Style::FruitBeer);
+        let blonde = beer("Autumn's Early Arrival Blonde", Style::BlondeAle);
+        assert_eq!(changeover.clean_level(&fruit, &fruit), CleanLevel::FullCip);
+        assert_eq!(
+            changeover.clean_level(&blonde, &fruit),
+            CleanLevel::Sanitize
+        );
+    }
+
+    #[test]
+    fn test_changeover_rule_from_config() {
+        let config: ChangeoverConfig =
+            serde_json::from_str(r#"{"from": "IPA", "to": "Pilsner", "clean": "cip"}"#).unwrap();
+        assert_eq!(
+            ChangeoverRule::from(&config),
+            ChangeoverRule::new(
+                "IPA".to_string(),
+                "Pilsner".to_string(),
+                CleanLevel::FullCip
+            )
+        );
+    }
+}
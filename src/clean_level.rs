@@ -0,0 +1,112 @@
+use chrono::Duration;
+
+/**
+ * How thoroughly a vessel is cleaned between two batches. A rebrew of the same beer only needs a
+ * rinse, while going from a stout to a pilsner needs the full caustic and acid clean in place.
+ * A vessel that held a segregated beer, like a sour, is decontaminated before other beers may
+ * use it again.
+ */
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum CleanLevel {
+    Rinse,
+    Sanitize,
+    FullCip,
+    Decontamination,
+}
+
+impl CleanLevel {
+    pub fn lookup(&self) -> &'static str {
+        match self {
+            CleanLevel::Rinse => "Rinse",
+            CleanLevel::Sanitize => "Sanitize",
+            CleanLevel::FullCip => "Full CIP",
+            CleanLevel::Decontamination => "Decontamination",
+        }
+    }
+
+    pub fn all() -> Vec<CleanLevel> {
+        vec![
+            CleanLevel::Rinse,
+            CleanLevel::Sanitize,
+            CleanLevel::FullCip,
+            CleanLevel::Decontamination,
+        ]
+    }
+
+    /// How long this clean takes, given the time of a full clean in place of the vessel.
+    pub fn duration(&self, full_cip: Duration) -> Duration {
+        // @TODO: rules of thumb, these could become configurable per vessel
+        match self {
+            CleanLevel::Rinse => full_cip / 4,
+            CleanLevel::Sanitize => full_cip / 2,
+            CleanLevel::FullCip => full_cip,
+            CleanLevel::Decontamination => full_cip * 2,
+        }
+    }
+}
+
+impl std::str::FromStr for CleanLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<CleanLevel, ()> {
+        match s {
+            "Rinse" => Ok(CleanLevel::Rinse),
+            "rinse" => Ok(CleanLevel::Rinse),
+            "Sanitize" => Ok(CleanLevel::Sanitize),
+            "sanitize" => Ok(CleanLevel::Sanitize),
+            "Full CIP" => Ok(CleanLevel::FullCip),
+            "cip" => Ok(CleanLevel::FullCip),
+            "Decontamination" => Ok(CleanLevel::Decontamination),
+            "decontamination" => Ok(CleanLevel::Decontamination),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_full_cip() -> CleanLevel {
+        CleanLevel::FullCip
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_level_lookup() {
+        assert_eq!(CleanLevel::Rinse.lookup(), "Rinse");
+        assert_eq!(CleanLevel::Sanitize.lookup(), "Sanitize");
+        assert_eq!(CleanLevel::FullCip.lookup(), "Full CIP");
+        assert_eq!(CleanLevel::Decontamination.lookup(), "Decontamination");
+    }
+
+    #[test]
+    fn test_clean_level_duration() {
+        let full_cip = Duration::hours(2);
+        assert_eq!(CleanLevel::Rinse.duration(full_cip), Duration::minutes(30));
+        assert_eq!(CleanLevel::Sanitize.duration(full_cip), Duration::hours(1));
+        assert_eq!(CleanLevel::FullCip.duration(full_cip), Duration::hours(2));
+        assert_eq!(
+            CleanLevel::Decontamination.duration(full_cip),
+            Duration::hours(4)
+        );
+    }
+
+    #[test]
+    fn test_clean_level_order() {
+        assert!(CleanLevel::Rinse < CleanLevel::Sanitize);
+        assert!(CleanLevel::Sanitize < CleanLevel::FullCip);
+    }
+
+    #[test]
+    fn test_clean_level_parse() {
+        assert_eq!("rinse".parse(), Ok(CleanLevel::Rinse));
+        assert_eq!("Full CIP".parse(), Ok(CleanLevel::FullCip));
+        assert_eq!("cip".parse(), Ok(CleanLevel::FullCip));
+        assert_eq!("scrub".parse::<CleanLevel>(), Err(()));
+    }
+}
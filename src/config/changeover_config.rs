@@ -0,0 +1,7 @@
+/// The clean that is needed when a vessel goes `from` one beer or style `to` another.
+#[derive(serde::Deserialize, Debug)]
+pub struct ChangeoverConfig {
+    pub from: String,
+    pub to: String,
+    pub clean: String,
+}
@@ -0,0 +1,9 @@
+use chrono::{DateTime, Utc};
+
+#[derive(serde::Deserialize, Debug)]
+pub struct DowntimeConfig {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    #[serde(default)]
+    pub reason: String,
+}
@@ -1,7 +1,36 @@
+use super::downtime_config::DowntimeConfig;
+use super::maintenance_config::MaintenanceConfig;
+use super::packaging_config::PackagingRateConfig;
+use super::visit_config::VisitConfig;
+
 #[derive(serde::Deserialize, Debug)]
 pub struct EquipmentConfig {
     pub id: u32,
     pub name: String,
     pub equipment_type: String,
     pub capacity: String,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub contents: Option<String>,
+    #[serde(default)]
+    pub downtime: Vec<DowntimeConfig>,
+    #[serde(default)]
+    pub maintenance: Vec<MaintenanceConfig>,
+    #[serde(default)]
+    pub pump_rate: Option<String>,
+    #[serde(default)]
+    pub cip_time: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub quarantine: Vec<String>,
+    #[serde(default)]
+    pub formats: Vec<PackagingRateConfig>,
+    #[serde(default)]
+    pub changeover_time: Option<String>,
+    #[serde(default)]
+    pub crew: u32,
+    #[serde(default)]
+    pub visits: Vec<VisitConfig>,
 }
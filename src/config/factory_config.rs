@@ -1,11 +1,28 @@
+use std::collections::HashMap;
 use std::str::FromStr;
 
 use crate::capacity::Capacity;
 use crate::equipment::Equipment;
 use crate::equipment_group::EquipmentGroup;
+use crate::step_definition::StepDefinition;
 
+pub use super::changeover_config::ChangeoverConfig;
 pub use super::equipment_config::EquipmentConfig;
+pub use super::forecast_config::ForecastConfig;
+pub use super::handling_config::HandlingConfig;
+pub use super::keg_fleet_config::KegFleetConfig;
+pub use super::material_config::MaterialConfig;
+pub use super::order_config::CustomerOrderConfig;
+pub use super::packaging_config::PackagingOrderConfig;
 pub use super::recipe_config::RecipeConfig;
+pub use super::release_config::ReleaseConfig;
+pub use super::resource_config::ResourceConfig;
+pub use super::scaling_config::ScalingConfig;
+pub use super::segregation_config::SegregationConfig;
+pub use super::step_config::StepConfig;
+pub use super::stock_config::StockConfig;
+pub use super::tap_list_config::TapListConfig;
+pub use super::yeast_config::YeastConfig;
 
 #[derive(serde::Deserialize, Debug)]
 pub struct FactoryConfig {
@@ -13,9 +30,50 @@ pub struct FactoryConfig {
     pub equipment: Vec<EquipmentConfig>,
     pub recipes: Vec<RecipeConfig>,
     pub capacity: String,
+    #[serde(default)]
+    pub steps: Vec<StepConfig>,
+    #[serde(default)]
+    pub handling: Vec<HandlingConfig>,
+    #[serde(default)]
+    pub changeovers: Vec<ChangeoverConfig>,
+    #[serde(default)]
+    pub segregation: Vec<SegregationConfig>,
+    #[serde(default)]
+    pub resources: Vec<ResourceConfig>,
+    #[serde(default)]
+    pub packaging_crew: Option<u32>,
+    #[serde(default)]
+    pub packaging_orders: Vec<PackagingOrderConfig>,
+    #[serde(default)]
+    pub keg_fleet: Vec<KegFleetConfig>,
+    #[serde(default)]
+    pub inventory: Vec<StockConfig>,
+    #[serde(default)]
+    pub tap_list: Option<TapListConfig>,
+    #[serde(default)]
+    pub releases: Vec<ReleaseConfig>,
+    #[serde(default)]
+    pub forecast: Option<ForecastConfig>,
+    #[serde(default)]
+    pub orders: Vec<CustomerOrderConfig>,
+    #[serde(default)]
+    pub yeasts: Vec<YeastConfig>,
+    #[serde(default)]
+    pub materials: Vec<MaterialConfig>,
+    #[serde(default)]
+    pub scaling: Option<ScalingConfig>,
 }
 
 impl FactoryConfig {
+    /// The steps that the factory declares, by name.
+    pub fn step_definitions(&self) -> HashMap<String, StepDefinition> {
+        self.steps
+            .iter()
+            .map(StepDefinition::from)
+            .map(|definition| (definition.name.clone(), definition))
+            .collect()
+    }
+
     pub fn max_mash_tun_capacity(&self) -> Option<Capacity> {
         let mut mash_tuns: Vec<Equipment> = vec![];
         for equipment_config in &self.equipment {
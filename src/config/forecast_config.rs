@@ -0,0 +1,8 @@
+/// How the demand is projected from the sales history.
+#[derive(serde::Deserialize, Debug)]
+pub struct ForecastConfig {
+    pub method: String,
+    pub weeks: u32,
+    #[serde(default)]
+    pub window: Option<u32>,
+}
@@ -0,0 +1,11 @@
+/// Transfer and cleaning times for all the equipment of a group, or only the ones of a size.
+#[derive(serde::Deserialize, Debug)]
+pub struct HandlingConfig {
+    pub equipment_type: String,
+    #[serde(default)]
+    pub capacity: Option<String>,
+    #[serde(default)]
+    pub pump_rate: Option<String>,
+    #[serde(default)]
+    pub cip_time: Option<String>,
+}
@@ -0,0 +1,15 @@
+/// The kegs of one size that the brewery owns, and where they are.
+#[derive(serde::Deserialize, Debug)]
+pub struct KegFleetConfig {
+    pub format: String,
+    #[serde(default)]
+    pub on_hand: u32,
+    #[serde(default)]
+    pub at_accounts: u32,
+    #[serde(default)]
+    pub dirty: u32,
+    #[serde(default)]
+    pub return_rate: f32,
+    #[serde(default)]
+    pub wash_time: Option<String>,
+}
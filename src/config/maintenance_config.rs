@@ -0,0 +1,13 @@
+#[derive(serde::Deserialize, Debug)]
+pub struct MaintenanceConfig {
+    pub name: String,
+    #[serde(default)]
+    pub every_uses: Option<u32>,
+    #[serde(default)]
+    pub every_days: Option<u32>,
+    pub duration: String,
+    #[serde(default)]
+    pub uses: u32,
+    #[serde(default)]
+    pub days_occupied: u32,
+}
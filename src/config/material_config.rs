@@ -0,0 +1,18 @@
+/// How much of a material goes in a batch of the recipe.
+#[derive(serde::Deserialize, Debug)]
+pub struct IngredientConfig {
+    pub name: String,
+    pub amount: f32,
+}
+
+/// A raw material in the store, and who supplies it.
+#[derive(serde::Deserialize, Debug)]
+pub struct MaterialConfig {
+    pub name: String,
+    pub unit: String,
+    #[serde(default)]
+    pub on_hand: f32,
+    #[serde(default)]
+    pub supplier: String,
+    pub lead_time: String,
+}
@@ -1,10 +1,46 @@
+mod changeover_config;
+mod downtime_config;
 mod equipment_config;
 mod factory_config;
+mod forecast_config;
+mod handling_config;
+mod keg_fleet_config;
+mod maintenance_config;
+mod material_config;
+mod order_config;
+mod packaging_config;
 mod recipe_config;
+mod release_config;
+mod resource_config;
+mod scaling_config;
+mod segregation_config;
+mod step_config;
+mod stock_config;
+mod tap_list_config;
+mod visit_config;
+mod yeast_config;
 
+pub use changeover_config::ChangeoverConfig;
+pub use downtime_config::DowntimeConfig;
 pub use equipment_config::EquipmentConfig;
 pub use factory_config::FactoryConfig;
-pub use recipe_config::RecipeConfig;
+pub use forecast_config::ForecastConfig;
+pub use handling_config::HandlingConfig;
+pub use keg_fleet_config::KegFleetConfig;
+pub use maintenance_config::MaintenanceConfig;
+pub use material_config::{IngredientConfig, MaterialConfig};
+pub use order_config::CustomerOrderConfig;
+pub use packaging_config::{PackagingOrderConfig, PackagingRateConfig};
+pub use recipe_config::{PhaseConfig, RecipeConfig};
+pub use release_config::ReleaseConfig;
+pub use resource_config::{ConsumptionConfig, ResourceConfig};
+pub use scaling_config::ScalingConfig;
+pub use segregation_config::SegregationConfig;
+pub use step_config::StepConfig;
+pub use stock_config::{DepletionConfig, StockConfig};
+pub use tap_list_config::{TapListConfig, TapRequirementConfig};
+pub use visit_config::VisitConfig;
+pub use yeast_config::{SlurryConfig, YeastConfig};
 
 use serde::{Deserialize, Serialize};
 
@@ -24,11 +60,26 @@ impl Config {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     use std::path::PathBuf;
 
+    use chrono::{TimeZone, Utc};
+
+    use crate::batchneed::BatchNeed;
     use crate::beer::Beer;
+    use crate::capability::Capability;
     use crate::capacity::Capacity;
+    use crate::clean_level::CleanLevel;
     use crate::equipment::Equipment;
+    use crate::equipment_group::EquipmentGroup;
+    use crate::factory::Factory;
+    use crate::forecast::{ForecastMethod, Sale};
+    use crate::interval::Interval;
+    use crate::package_format::PackageFormat;
+    use crate::packaging::PackagingOrder;
+    use crate::scaling::Scaling;
+    use crate::step_group::StepGroup;
+    use crate::style::Style;
     use crate::volume::Volume;
 
     fn load_configuration_from_json() -> Config {
@@ -42,7 +93,7 @@ mod tests {
     fn it_can_load_a_configuration_from_json() {
         let config = load_configuration_from_json();
         assert_eq!("Loons Landing Brewery", config.factory.name);
-        assert_eq!(8, config.factory.equipment.len());
+        assert_eq!(11, config.factory.equipment.len());
 
         // Check to make sure that we have at least one fermentor named "Big Bertha"
         for equip_config in &config.factory.equipment {
@@ -62,6 +113,336 @@ mod tests {
         }
     }
 
+    #[test]
+    fn it_can_load_custom_steps_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        assert_eq!(factory.equipments.len(), 11);
+        let dry_hopping = factory.step_group("Dry Hopping").unwrap();
+        let beer = factory.beers.get("Damned Squirrel Mk. II").unwrap();
+        let (batch_size, steps) = beer.recipe.get(&Capacity::G15).unwrap();
+        assert_eq!(batch_size, &Volume::GallonUS(10.0));
+        assert_eq!(
+            steps.iter().collect::<Vec<(StepGroup, Interval)>>(),
+            vec![
+                (StepGroup::Brewing, Interval::Days(1)),
+                (StepGroup::PrimaryFermentation, Interval::Days(33)),
+                (StepGroup::SecondaryFermentation, Interval::Days(64)),
+                (dry_hopping, Interval::Days(3)),
+                (StepGroup::Carbonation, Interval::Days(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_can_load_a_carbonation_method_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let beer = factory.beers.get("Loon Call Bitter").unwrap();
+        let (_batch_size, steps) = beer.recipe.get(&Capacity::G15).unwrap();
+        // Conditioned in the keg, for as long as that usually takes
+        assert_eq!(
+            steps.get(&StepGroup::KegConditioning),
+            Some(&Interval::Weeks(2))
+        );
+        assert_eq!(steps.get(&StepGroup::Carbonation), None);
+    }
+
+    #[test]
+    fn it_can_load_the_co2_inventory_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        assert_eq!(factory.co2_inventory(), Volume::Lb(20.0));
+    }
+
+    #[test]
+    fn it_can_load_transfer_and_cleaning_times_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let big_bertha = factory.equipments.get("Big Bertha").unwrap();
+        assert_eq!(big_bertha.pump_rate, Some(Volume::GallonUS(2.0)));
+        assert_eq!(big_bertha.cip_time, Some(chrono::Duration::hours(1)));
+        let small_fermentor = factory.equipments.get("FV-001").unwrap();
+        assert_eq!(
+            small_fermentor.cip_time,
+            Some(chrono::Duration::minutes(45))
+        );
+        let kettle = factory.equipments.get("Large Kettle").unwrap();
+        assert_eq!(kettle.cip_time, None);
+    }
+
+    #[test]
+    fn it_can_load_changeovers_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let beer = factory.beers.get("Damned Squirrel Mk. II").unwrap();
+        assert_eq!(beer.style, Style::BrownAle);
+        assert_eq!(beer.allergens, vec!["lactose".to_string()]);
+        assert_eq!(beer.shelf_life(), Some(chrono::Duration::weeks(8)));
+        assert_eq!(
+            factory.changeover.clean_level(beer, beer),
+            CleanLevel::FullCip
+        );
+    }
+
+    #[test]
+    fn it_can_load_segregation_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let beer = factory.beers.get("Damned Squirrel Mk. II").unwrap();
+        let brite_tank = factory.equipments.get("BT-001").unwrap();
+        let gluten_free_brite_tank = factory.equipments.get("BT-002").unwrap();
+        assert_eq!(gluten_free_brite_tank.tags, vec!["gluten-free".to_string()]);
+        assert!(factory.segregation.allows(beer, brite_tank));
+        assert!(!factory.segregation.allows(beer, gluten_free_brite_tank));
+    }
+
+    #[test]
+    fn it_can_load_shared_equipment_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let shared = factory.shared_equipment();
+        assert_eq!(shared[&EquipmentGroup::Pump][0].name, "Transfer Pump");
+        assert_eq!(
+            shared[&EquipmentGroup::HeatExchanger][0].name,
+            "Plate Chiller"
+        );
+        assert!(!shared.contains_key(&EquipmentGroup::CipSkid));
+    }
+
+    #[test]
+    fn it_can_load_resources_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let beer = factory.beers.get("Loon Call Bitter").unwrap();
+        let batch = BatchNeed::new(1, beer, Capacity::G15, Volume::GallonUS(10.0));
+        let crash = factory.step_group("Cold Crash").unwrap();
+        let glycol = &factory.resources[0];
+        assert_eq!(glycol.limit, 2);
+        assert_eq!(glycol.usage(&crash, &batch), 1);
+        assert_eq!(glycol.usage(&StepGroup::PrimaryFermentation, &batch), 0);
+        let cooler = &factory.resources[1];
+        assert_eq!(cooler.limit, 40);
+        assert_eq!(cooler.usage(&StepGroup::KegConditioning, &batch), 2);
+    }
+
+    #[test]
+    fn it_can_load_packaging_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let line = factory.equipments.get("Canning Line").unwrap();
+        assert!(line.can(&Capability::Packaging));
+        let cans = line.packaging_rate(&PackageFormat::Can16oz).unwrap();
+        assert_eq!(cans.rate, 1200);
+        assert_eq!(cans.minimum_run, 24);
+        assert!(line.packaging_rate(&PackageFormat::Bottle750ml).is_none());
+        assert_eq!(line.changeover_time, Some(chrono::Duration::minutes(30)));
+        assert_eq!(line.crew, 2);
+        assert_eq!(factory.packaging_crew, Some(2));
+        assert_eq!(
+            factory.packaging_orders,
+            vec![PackagingOrder::new(
+                "Damned Squirrel Mk. II".to_string(),
+                PackageFormat::HalfBarrel,
+                2
+            )]
+        );
+    }
+
+    #[test]
+    fn it_can_load_the_keg_fleet_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let fleet = &factory.keg_fleets[0];
+        assert_eq!(fleet.format, PackageFormat::HalfBarrel);
+        assert_eq!(fleet.size(), 16);
+        assert_eq!(fleet.wash_time, chrono::Duration::days(1));
+    }
+
+    #[test]
+    fn it_can_load_the_inventory_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let stock = &factory.inventory[0];
+        assert_eq!(stock.beer, "Damned Squirrel Mk. II");
+        assert_eq!(stock.depletion.len(), 2);
+        // Two weeks of cover is six kegs, more than the reorder point
+        assert_eq!(stock.safety_stock(), 6);
+        assert_eq!(
+            factory.replenishment(chrono::Duration::weeks(1), &HashMap::new()),
+            vec![PackagingOrder::new(
+                "Damned Squirrel Mk. II".to_string(),
+                PackageFormat::HalfBarrel,
+                6
+            )]
+        );
+    }
+
+    #[test]
+    fn it_can_load_the_tap_list_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let tap_list = factory.tap_list.unwrap();
+        assert_eq!(tap_list.minimum, 6);
+        assert_eq!(tap_list.window, chrono::Duration::weeks(3));
+        let beer = factory.beers.get("Damned Squirrel Mk. II").unwrap();
+        // A brown ale is dark, and no lager
+        assert_eq!(
+            tap_list.shortfalls(&[beer]),
+            vec![
+                "1 of 6 beers on tap".to_string(),
+                "0 of 1 lager on tap".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn it_can_load_the_releases_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let release = &factory.releases[0];
+        assert_eq!(release.beer, "Damned Squirrel Mk. II");
+        assert_eq!(release.volume, Volume::GallonUS(10.0));
+        let now = Utc.with_ymd_and_hms(2021, 1, 4, 8, 0, 0).unwrap();
+        assert_eq!(
+            release.next_date(now),
+            Utc.with_ymd_and_hms(2021, 10, 31, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_can_load_the_forecast_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let forecast = factory.forecast.as_ref().unwrap();
+        assert_eq!(forecast.method, ForecastMethod::SeasonalNaive);
+        assert_eq!(forecast.weeks, 4);
+
+        let mut sales_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        sales_path.push("contrib/LoonsLandingSales.csv");
+        let sales = Sale::read_csv(sales_path.to_str().unwrap().to_string()).unwrap();
+        assert_eq!(sales.len(), 9);
+        // The first weeks of the year before, the guest tap isn't brewed here
+        let now = Utc.with_ymd_and_hms(2021, 1, 4, 8, 0, 0).unwrap();
+        let wishlist = forecast.wishlist(&sales, &factory.beers, now);
+        assert_eq!(wishlist.len(), 1);
+        let (_, volume) = &wishlist["Damned Squirrel Mk. II"];
+        match volume.to_gallon_us() {
+            Volume::GallonUS(gallons) => assert!((gallons - 54.7).abs() < 0.01),
+            _ => panic!("Should not happen"),
+        }
+    }
+
+    #[test]
+    fn it_can_load_the_order_book_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        assert_eq!(factory.order_book.len(), 1);
+        let order = &factory.order_book[0];
+        assert_eq!(order.customer, "Lakeside Tavern");
+        assert_eq!(order.format, PackageFormat::HalfBarrel);
+        assert_eq!(order.units, 1);
+        assert_eq!(
+            order.delivery,
+            Utc.with_ymd_and_hms(2021, 11, 15, 10, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_can_load_the_yeasts_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let beer = factory.beers.get("Damned Squirrel Mk. II").unwrap();
+        assert_eq!(beer.yeast, Some("Nottingham".to_string()));
+        let strain = &factory.yeasts["Nottingham"];
+        assert_eq!(strain.max_generations, 5);
+        assert_eq!(strain.viability, chrono::Duration::days(5));
+        assert_eq!(strain.propagation, chrono::Duration::days(3));
+    }
+
+    #[test]
+    fn it_can_load_the_materials_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let beer = factory.beers.get("Damned Squirrel Mk. II").unwrap();
+        let ingredients = beer.recipe.ingredients(&Capacity::G15);
+        assert_eq!(ingredients.len(), 3);
+        assert_eq!(ingredients[0].name, "Maris Otter");
+        assert_eq!(ingredients[0].amount, 9.5);
+        // The crystal malt is not kept track of in the store
+        assert_eq!(factory.materials.len(), 2);
+        let hops = &factory.materials["Fuggle"];
+        assert_eq!(hops.supplier, "Hops Direct");
+        assert_eq!(hops.lead_time, chrono::Duration::days(3));
+    }
+
+    #[test]
+    fn it_can_load_recipe_versions_from_json() {
+        let config = load_configuration_from_json();
+        let factory = Factory::from(&config.factory);
+        let beer = &factory.beers["Damned Squirrel Mk. II"];
+        assert_eq!(beer.label(), "Damned Squirrel Mk. II v2");
+        let first = factory.beer_version("Damned Squirrel Mk. II", 1).unwrap();
+        assert_eq!(first.effective, None);
+        let (_batch_size, steps) = first.recipe.get(&Capacity::G15).unwrap();
+        assert_eq!(
+            steps.get(&StepGroup::SecondaryFermentation),
+            Some(&Interval::Days(56))
+        );
+        let brewed = Utc.with_ymd_and_hms(2021, 2, 15, 8, 0, 0).unwrap();
+        assert_eq!(
+            factory.beer_at("Damned Squirrel Mk. II", brewed),
+            Some(first)
+        );
+        let brewed = Utc.with_ymd_and_hms(2021, 3, 1, 8, 0, 0).unwrap();
+        assert_eq!(
+            factory.beer_at("Damned Squirrel Mk. II", brewed),
+            Some(beer)
+        );
+    }
+
+    #[test]
+    fn it_can_scale_the_recipes_from_json() {
+        let config = load_configuration_from_json();
+        let mut factory = Factory::from(&config.factory);
+        // The recipes are for the one brewhouse there is
+        assert_eq!(factory.brew_systems(), vec![Capacity::G15]);
+        assert!(factory.scaled_recipes().is_empty());
+
+        let mash_tun = Equipment::new(
+            "Pilot Mash Tun".to_string(),
+            Capacity::G5,
+            EquipmentGroup::MashTun,
+        );
+        factory.equipments.insert(mash_tun.name.clone(), mash_tun);
+        factory.scale_recipes(&Scaling::from(config.factory.scaling.as_ref().unwrap()));
+        // 70% of 5 gallons, less half a gallon, for every recipe. The brew day is shorter in the
+        // smaller tun.
+        assert_eq!(
+            factory.scaled_recipes(),
+            vec![
+                "Damned Squirrel Mk. II v1 on 5G, scaled from 15G: 3.0G, Brewing 17h, \
+                 Primary Fermentation 33d, Secondary Fermentation 56d, Carbonation 2d"
+                    .to_string(),
+                "Damned Squirrel Mk. II v2 on 5G, scaled from 15G: 3.0G, Brewing 17h, \
+                 Primary Fermentation 33d, Secondary Fermentation 64d, Dry Hopping 3d, \
+                 Carbonation 2d"
+                    .to_string(),
+                "Loon Call Bitter on 5G, scaled from 15G: 3.0G, Brewing 17h, \
+                 Primary Fermentation 10d, Cold Crash 2d, Keg Conditioning 2w"
+                    .to_string(),
+            ]
+        );
+        // FV-001 and the brite tanks of 5 gallons take it from there, the kegs are not booked
+        let beer = &factory.beers["Loon Call Bitter"];
+        assert!(factory.can_brew(beer, &Capacity::G5));
+        let beer = &factory.beers["Damned Squirrel Mk. II"];
+        assert!(factory.can_brew(beer, &Capacity::G5));
+        factory.equipments.remove("BT-001");
+        factory.equipments.remove("BT-002");
+        assert!(!factory.can_brew(beer, &Capacity::G5));
+    }
+
     #[test]
     fn max_mash_tun_capacity_should_be_15g() {
         let config = load_configuration_from_json();
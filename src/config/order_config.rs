@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+
+/// The packages of a beer that a customer ordered, and when they are delivered.
+#[derive(serde::Deserialize, Debug)]
+pub struct CustomerOrderConfig {
+    pub customer: String,
+    pub beer: String,
+    pub format: String,
+    pub units: u32,
+    pub delivery: DateTime<Utc>,
+}
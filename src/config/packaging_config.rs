@@ -0,0 +1,17 @@
+/// How many packages of a format a packaging line fills per hour, and the smallest run that is
+/// worth setting the line up for.
+#[derive(serde::Deserialize, Debug)]
+pub struct PackagingRateConfig {
+    pub format: String,
+    pub rate: u32,
+    #[serde(default)]
+    pub minimum_run: u32,
+}
+
+/// The packages of a beer that are ordered.
+#[derive(serde::Deserialize, Debug)]
+pub struct PackagingOrderConfig {
+    pub beer: String,
+    pub format: String,
+    pub units: u32,
+}
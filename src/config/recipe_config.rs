@@ -1,5 +1,43 @@
+use chrono::{DateTime, Utc};
+
+use super::material_config::IngredientConfig;
+use super::resource_config::ConsumptionConfig;
+
 #[derive(serde::Deserialize, Debug)]
 pub struct RecipeConfig {
     pub name: String,
     pub batch_size: String,
+    #[serde(default)]
+    pub style: Option<String>,
+    #[serde(default)]
+    pub allergens: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub max_age: Option<String>,
+    #[serde(default)]
+    pub yeast: Option<String>,
+    #[serde(default)]
+    pub version: Option<u32>,
+    #[serde(default)]
+    pub effective: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub ingredients: Vec<IngredientConfig>,
+    #[serde(default)]
+    pub phases: Vec<PhaseConfig>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct PhaseConfig {
+    pub name: String,
+    #[serde(default)]
+    pub duration: String,
+    #[serde(default)]
+    pub method: Option<String>,
+    /// The operations of a brew day, to plan them on the vessels of the brewhouse one by one.
+    #[serde(default)]
+    pub operations: Vec<PhaseConfig>,
+    /// What the phase takes of the resources of the factory, like the glycol for a crash.
+    #[serde(default)]
+    pub consumes: Vec<ConsumptionConfig>,
 }
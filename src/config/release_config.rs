@@ -0,0 +1,8 @@
+/// A beer that is released every year on the same day.
+#[derive(serde::Deserialize, Debug)]
+pub struct ReleaseConfig {
+    pub beer: String,
+    pub month: u32,
+    pub day: u32,
+    pub volume: String,
+}
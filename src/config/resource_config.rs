@@ -0,0 +1,16 @@
+/// A resource of the factory, like the glycol chiller or the walk-in cooler, that only so many
+/// steps can use at the same time.
+#[derive(serde::Deserialize, Debug)]
+pub struct ResourceConfig {
+    pub name: String,
+    pub limit: u32,
+}
+
+/// How much of a resource a phase of a recipe takes, for the whole batch or `per` volume of it.
+#[derive(serde::Deserialize, Debug)]
+pub struct ConsumptionConfig {
+    pub resource: String,
+    pub amount: u32,
+    #[serde(default)]
+    pub per: Option<String>,
+}
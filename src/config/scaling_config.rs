@@ -0,0 +1,10 @@
+/// How the recipes are scaled to the other brew systems of the factory.
+#[derive(serde::Deserialize, Debug)]
+pub struct ScalingConfig {
+    #[serde(default)]
+    pub efficiency: Option<f32>,
+    #[serde(default)]
+    pub loss: Option<String>,
+    #[serde(default)]
+    pub duration_exponent: Option<f32>,
+}
@@ -0,0 +1,10 @@
+/// Beers with the `tag` may only use equipment with the same tag, optionally only for one type
+/// of equipment. Other beers may use that equipment after a `decontamination`, or never.
+#[derive(serde::Deserialize, Debug)]
+pub struct SegregationConfig {
+    pub tag: String,
+    #[serde(default)]
+    pub equipment_type: Option<String>,
+    #[serde(default)]
+    pub decontamination: bool,
+}
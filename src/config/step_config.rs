@@ -0,0 +1,8 @@
+#[derive(serde::Deserialize, Debug)]
+pub struct StepConfig {
+    pub name: String,
+    #[serde(alias = "equipment_type")]
+    pub capability: String,
+    pub order: u32,
+    pub post_process_time: String,
+}
@@ -0,0 +1,21 @@
+/// A sales channel of the stock, with the packages it sells per week.
+#[derive(serde::Deserialize, Debug)]
+pub struct DepletionConfig {
+    pub channel: String,
+    pub per_week: f32,
+}
+
+/// The finished goods of a beer in a package format.
+#[derive(serde::Deserialize, Debug)]
+pub struct StockConfig {
+    pub beer: String,
+    pub format: String,
+    #[serde(default)]
+    pub on_hand: u32,
+    #[serde(default)]
+    pub depletion: Vec<DepletionConfig>,
+    #[serde(default)]
+    pub reorder_point: u32,
+    #[serde(default)]
+    pub days_of_cover: Option<u32>,
+}
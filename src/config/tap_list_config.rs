@@ -0,0 +1,16 @@
+/// A kind of beer the taproom should pour, and how many of them.
+#[derive(serde::Deserialize, Debug)]
+pub struct TapRequirementConfig {
+    pub kind: String,
+    pub count: u32,
+}
+
+/// What the taproom should always have on tap.
+#[derive(serde::Deserialize, Debug)]
+pub struct TapListConfig {
+    pub minimum: u32,
+    pub window: String,
+    pub horizon: String,
+    #[serde(default)]
+    pub requirements: Vec<TapRequirementConfig>,
+}
@@ -0,0 +1,8 @@
+use chrono::{DateTime, Utc};
+
+#[derive(serde::Deserialize, Debug)]
+pub struct VisitConfig {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub cases: u32,
+}
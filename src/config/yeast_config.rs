@@ -0,0 +1,27 @@
+use chrono::{DateTime, Utc};
+
+/// A yeast strain, how long its harvested slurry stays viable, how many generations it may be
+/// repitched and how long it takes to propagate.
+///
+/// The planner repitches the slurry whenever it can, even when the batches are done later for
+/// it. With `propagate_if_sooner` it only counts the propagations after the time it takes to
+/// brew all the batches, so that fresh yeast is propagated when that is done sooner.
+#[derive(serde::Deserialize, Debug)]
+pub struct YeastConfig {
+    pub name: String,
+    pub max_generations: u32,
+    pub viability: String,
+    pub propagation: String,
+    #[serde(default)]
+    pub propagate_if_sooner: bool,
+    #[serde(default)]
+    pub slurry: Vec<SlurryConfig>,
+}
+
+/// The slurry that is harvested from a batch in progress, and its generation.
+#[derive(serde::Deserialize, Debug)]
+pub struct SlurryConfig {
+    pub batch: usize,
+    pub harvested: DateTime<Utc>,
+    pub generation: u32,
+}
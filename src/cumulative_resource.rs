@@ -0,0 +1,143 @@
+use std::str::FromStr;
+
+use crate::batchneed::BatchNeed;
+use crate::config::{ConsumptionConfig, ResourceConfig};
+use crate::step_group::StepGroup;
+use crate::volume::Volume;
+
+/// How much of a resource a step of a recipe takes while it runs. Without a `per` volume the
+/// amount is for the whole batch, like one slot of the glycol chiller for a fermentor that is
+/// crashed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Consumption {
+    pub resource: String,
+    pub amount: u32,
+    pub per: Option<Volume>,
+}
+
+impl Consumption {
+    pub fn new(resource: String, amount: u32, per: Option<Volume>) -> Self {
+        Self {
+            resource,
+            amount,
+            per,
+        }
+    }
+
+    pub fn usage(&self, batch: &BatchNeed) -> u32 {
+        match &self.per {
+            None => self.amount,
+            Some(per) => {
+                if let Volume::Liter(per) = per.to_liter() {
+                    if let Volume::Liter(volume) = batch.volume.to_liter() {
+                        return self.amount * (volume / per).ceil() as u32;
+                    }
+                }
+
+                panic!("Something went wonky when trying to convert volumes");
+            }
+        }
+    }
+}
+
+impl std::convert::From<&ConsumptionConfig> for Consumption {
+    fn from(config: &ConsumptionConfig) -> Self {
+        let per = config.per.as_ref().map(|per| match Volume::from_str(per) {
+            Ok(x) => x,
+            Err(_e) => panic!(
+                "{} does not appear to be a valid volume for resource {}",
+                per, config.resource
+            ),
+        });
+        Consumption::new(String::from(&config.resource), config.amount, per)
+    }
+}
+
+/**
+ * A resource that is not booked by one batch at a time, but that has room for a number of
+ * them, like a glycol chiller that can crash two fermentors at once, or a walk-in cooler with
+ * room for 40 kegs. The steps of the recipes say how much of it they take, and are planned so
+ * that together they stay within the limit.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct CumulativeResource {
+    pub name: String,
+    pub limit: u32,
+}
+
+impl CumulativeResource {
+    pub fn new(name: String, limit: u32) -> Self {
+        Self { name, limit }
+    }
+
+    /// How much the step of the batch takes, if anything.
+    pub fn usage(&self, step_group: &StepGroup, batch: &BatchNeed) -> u32 {
+        batch
+            .beer
+            .recipe
+            .consumption(step_group)
+            .iter()
+            .filter(|consumption| consumption.resource == self.name)
+            .map(|consumption| consumption.usage(batch))
+            .sum()
+    }
+}
+
+impl std::convert::From<&ResourceConfig> for CumulativeResource {
+    fn from(config: &ResourceConfig) -> Self {
+        CumulativeResource::new(String::from(&config.name), config.limit)
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_glycol() -> CumulativeResource {
+        CumulativeResource::new("Glycol".to_string(), 2)
+    }
+
+    pub fn mock_cold_storage() -> CumulativeResource {
+        CumulativeResource::new("Cold Storage".to_string(), 40)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batchneed;
+    use crate::beer;
+    use crate::capacity::Capacity;
+
+    #[test]
+    fn test_cumulative_resource_usage() {
+        let mut beer = beer::mock::mock_beer();
+        beer.recipe = beer
+            .recipe
+            .with_consumption(
+                StepGroup::SecondaryFermentation,
+                Consumption::new("Glycol".to_string(), 1, None),
+            )
+            .with_consumption(
+                StepGroup::Aging,
+                Consumption::new("Cold Storage".to_string(), 1, Some(Volume::GallonUS(5.0))),
+            );
+        let batch = batchneed::BatchNeed::new(1, &beer, Capacity::G10, Volume::GallonUS(10.0));
+        let glycol = mock::mock_glycol();
+        assert_eq!(glycol.usage(&StepGroup::SecondaryFermentation, &batch), 1);
+        assert_eq!(glycol.usage(&StepGroup::Aging, &batch), 0);
+        let cold_storage = mock::mock_cold_storage();
+        assert_eq!(cold_storage.usage(&StepGroup::Aging, &batch), 2);
+    }
+
+    #[test]
+    fn test_consumption_from_config() {
+        let config: ConsumptionConfig =
+            serde_json::from_str(r#"{"resource": "Walk-in Cooler", "amount": 1, "per": "5g"}"#)
+                .unwrap();
+        assert_eq!(
+            Consumption::from(&config),
+            Consumption::new("Walk-in Cooler".to_string(), 1, Some(Volume::GallonUS(5.0)))
+        );
+    }
+}
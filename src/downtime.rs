@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+
+use crate::config::DowntimeConfig;
+
+/**
+ * A window in which a piece of equipment can not be used: a gasket that is replaced, a tank
+ * that is passivated, or a fermentor that is lent out to a collab. The planner keeps the
+ * equipment free from `start` till `end`.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Downtime {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub reason: String,
+}
+
+impl Downtime {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>, reason: String) -> Self {
+        assert!(start < end);
+        Self { start, end, reason }
+    }
+
+    /// Whether something that occupies the equipment from `start` till `end` runs into this window.
+    pub fn overlaps(&self, start: &DateTime<Utc>, end: &DateTime<Utc>) -> bool {
+        start < &self.end && &self.start < end
+    }
+}
+
+impl std::convert::From<&DowntimeConfig> for Downtime {
+    fn from(config: &DowntimeConfig) -> Self {
+        if config.start >= config.end {
+            panic!(
+                "{} does not appear to be a valid end of the downtime starting at {}",
+                config.end, config.start
+            );
+        }
+        Downtime::new(config.start, config.end, String::from(&config.reason))
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use chrono::TimeZone;
+
+    pub fn mock_downtime() -> Downtime {
+        Downtime::new(
+            Utc.with_ymd_and_hms(2021, 1, 4, 8, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 1, 6, 17, 0, 0).unwrap(),
+            "Gasket replacement".to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_downtime_overlaps() {
+        let downtime = mock::mock_downtime();
+        let before = Utc.with_ymd_and_hms(2021, 1, 1, 8, 0, 0).unwrap();
+        let during = Utc.with_ymd_and_hms(2021, 1, 5, 8, 0, 0).unwrap();
+        let after = Utc.with_ymd_and_hms(2021, 1, 7, 8, 0, 0).unwrap();
+        assert!(!downtime.overlaps(&before, &downtime.start));
+        assert!(downtime.overlaps(&before, &during));
+        assert!(downtime.overlaps(&during, &after));
+        assert!(downtime.overlaps(&before, &after));
+        assert!(!downtime.overlaps(&downtime.end, &after));
+    }
+
+    #[test]
+    fn test_downtime_from_config() {
+        let config: DowntimeConfig = serde_json::from_str(
+            r#"{"start": "2021-01-04T08:00:00Z", "end": "2021-01-06T17:00:00Z",
+                "reason": "Gasket replacement"}"#,
+        )
+        .unwrap();
+        assert_eq!(Downtime::from(&config), mock::mock_downtime());
+    }
+}
@@ -1,7 +1,15 @@
+use crate::capability::Capability;
 use crate::capacity::Capacity;
 use crate::config::EquipmentConfig;
+use crate::downtime::Downtime;
 use crate::equipment_group::EquipmentGroup;
+use crate::interval::Interval;
+use crate::maintenance::MaintenanceRule;
+use crate::package_format::PackageFormat;
+use crate::packaging::PackagingRate;
+use crate::visit::Visit;
 use crate::volume::Volume;
+use chrono::Duration;
 use std::str::FromStr;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -9,17 +17,134 @@ pub struct Equipment {
     pub name: String,
     pub capacity: Capacity,
     pub equipment_group: EquipmentGroup,
+    pub capabilities: Vec<Capability>,
+    pub contents: Option<Volume>,
+    pub downtime: Vec<Downtime>,
+    pub maintenance: Vec<MaintenanceRule>,
+    pub pump_rate: Option<Volume>,
+    pub cip_time: Option<Duration>,
+    pub tags: Vec<String>,
+    pub quarantine: Vec<String>,
+    pub packaging: Vec<PackagingRate>,
+    pub changeover_time: Option<Duration>,
+    pub crew: u32,
+    pub visits: Vec<Visit>,
 }
 
 impl Equipment {
     pub fn new(name: String, capacity: Capacity, equipment_group: EquipmentGroup) -> Self {
+        let capabilities = equipment_group.capabilities();
         Self {
             name,
             capacity,
             equipment_group,
+            capabilities,
+            contents: None,
+            downtime: Vec::new(),
+            maintenance: Vec::new(),
+            pump_rate: None,
+            cip_time: None,
+            tags: Vec::new(),
+            quarantine: Vec::new(),
+            packaging: Vec::new(),
+            changeover_time: None,
+            crew: 0,
+            visits: Vec::new(),
         }
     }
 
+    /// What is in the equipment, like the pounds of CO2 in a gas cylinder.
+    pub fn with_contents(mut self, contents: Volume) -> Self {
+        self.contents = Some(contents);
+        self
+    }
+
+    /// Equipment that can do more, or less, than the others of its group.
+    pub fn with_capabilities(mut self, capabilities: Vec<Capability>) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// A window in which the equipment is not available, for maintenance for example.
+    pub fn with_downtime(mut self, downtime: Downtime) -> Self {
+        self.downtime.push(downtime);
+        self
+    }
+
+    pub fn with_maintenance(mut self, rule: MaintenanceRule) -> Self {
+        self.maintenance.push(rule);
+        self
+    }
+
+    /// How much can be pumped out of the equipment per minute.
+    pub fn with_pump_rate(mut self, pump_rate: Volume) -> Self {
+        self.pump_rate = Some(pump_rate);
+        self
+    }
+
+    /// How long a clean in place takes.
+    pub fn with_cip_time(mut self, cip_time: Duration) -> Self {
+        self.cip_time = Some(cip_time);
+        self
+    }
+
+    /// Tags, like "sour", that the segregation rules of the factory use to dedicate equipment.
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// The equipment held a segregated beer with this tag, and is not decontaminated yet.
+    pub fn with_quarantine(mut self, tag: String) -> Self {
+        self.quarantine.push(tag);
+        self
+    }
+
+    /// A format that a packaging line can fill.
+    pub fn with_packaging_rate(mut self, rate: PackagingRate) -> Self {
+        self.packaging.push(rate);
+        self
+    }
+
+    /// How long it takes to set a packaging line up for another beer or format.
+    pub fn with_changeover_time(mut self, changeover_time: Duration) -> Self {
+        self.changeover_time = Some(changeover_time);
+        self
+    }
+
+    /// How many people it takes to run the equipment.
+    pub fn with_crew(mut self, crew: u32) -> Self {
+        self.crew = crew;
+        self
+    }
+
+    /// A visit of an external service, like a mobile canner. Equipment with visits can only
+    /// be used during one of them.
+    pub fn with_visit(mut self, visit: Visit) -> Self {
+        self.visits.push(visit);
+        self
+    }
+
+    pub fn packaging_rate(&self, format: &PackageFormat) -> Option<&PackagingRate> {
+        self.packaging.iter().find(|rate| &rate.format == format)
+    }
+
+    /// How long it takes to pump the volume out of the equipment, when its pump rate is known.
+    pub fn transfer_time(&self, volume: &Volume) -> Option<Duration> {
+        let pump_rate = self.pump_rate.as_ref()?;
+        if let Volume::Liter(rate) = pump_rate.to_liter() {
+            if let Volume::Liter(amount) = volume.to_liter() {
+                return Some(Duration::seconds((amount / rate * 60.0).ceil() as i64));
+            }
+        }
+
+        panic!("Something went wonky when trying to convert volumes");
+    }
+
+    pub fn can(&self, capability: &Capability) -> bool {
+        self.capabilities.contains(capability)
+    }
+
     pub fn can_hold(&self, volume: &Volume) -> bool {
         if let Volume::Liter(this) = self.capacity.volume().to_liter() {
             if let Volume::Liter(that) = volume.to_liter() {
@@ -43,11 +168,80 @@ impl std::convert::From<&EquipmentConfig> for Equipment {
         //     Err(_e) => panic!("{} does not appear to be a valid volume for capacity", &config.capacity),
         // };
 
-        Equipment::new(
+        let mut equipment = Equipment::new(
             String::from(&config.name),
             Capacity::from_str(&config.capacity).unwrap(),
             equipment_type,
-        )
+        );
+        if let Some(contents) = &config.contents {
+            equipment = match Volume::from_str(contents) {
+                Ok(x) => equipment.with_contents(x),
+                Err(_e) => panic!(
+                    "{} does not appear to be a valid volume for the contents of {}",
+                    contents, config.name
+                ),
+            };
+        }
+        for downtime_config in &config.downtime {
+            equipment = equipment.with_downtime(Downtime::from(downtime_config));
+        }
+        if let Some(pump_rate) = &config.pump_rate {
+            equipment = match Volume::from_str(pump_rate) {
+                Ok(x) => equipment.with_pump_rate(x),
+                Err(_e) => panic!(
+                    "{} does not appear to be a valid pump rate (per minute) for {}",
+                    pump_rate, config.name
+                ),
+            };
+        }
+        if let Some(cip_time) = &config.cip_time {
+            equipment = match Interval::from_str(cip_time) {
+                Ok(x) => equipment.with_cip_time(x.duration()),
+                Err(_e) => panic!(
+                    "{} does not appear to be a valid cip time for {}",
+                    cip_time, config.name
+                ),
+            };
+        }
+        for rate_config in &config.formats {
+            equipment = equipment.with_packaging_rate(PackagingRate::from(rate_config));
+        }
+        if let Some(changeover_time) = &config.changeover_time {
+            equipment = match Interval::from_str(changeover_time) {
+                Ok(x) => equipment.with_changeover_time(x.duration()),
+                Err(_e) => panic!(
+                    "{} does not appear to be a valid changeover time for {}",
+                    changeover_time, config.name
+                ),
+            };
+        }
+        equipment = equipment.with_crew(config.crew);
+        for visit_config in &config.visits {
+            equipment = equipment.with_visit(Visit::from(visit_config));
+        }
+        for tag in &config.quarantine {
+            equipment = equipment.with_quarantine(String::from(tag));
+        }
+        equipment = equipment.with_tags(config.tags.clone());
+        for maintenance_config in &config.maintenance {
+            equipment = equipment.with_maintenance(MaintenanceRule::from(maintenance_config));
+        }
+        if config.capabilities.is_empty() {
+            return equipment;
+        }
+
+        let capabilities = config
+            .capabilities
+            .iter()
+            .map(|capability| match Capability::from_str(capability) {
+                Ok(x) => x,
+                Err(_e) => panic!(
+                    "{} does not appear to be a valid capability for {}",
+                    capability, config.name
+                ),
+            })
+            .collect();
+        equipment.with_capabilities(capabilities)
     }
 }
 
@@ -70,7 +264,9 @@ pub mod mock {
 mod tests {
     use super::*;
     use crate::capacity;
+    use crate::downtime;
     use crate::equipment_group;
+    use crate::visit;
 
     #[test]
     fn test_equimpment_new() {
@@ -84,6 +280,89 @@ mod tests {
         // assert_eq!(equipment.volume, volume::mock::mock_gallon_us());
     }
 
+    #[test]
+    fn test_equipment_capabilities() {
+        let equipment = mock::mock_equipment();
+        assert!(equipment.can(&Capability::Mashing));
+        assert!(!equipment.can(&Capability::Lautering));
+
+        let equipment =
+            equipment.with_capabilities(vec![Capability::Mashing, Capability::Lautering]);
+        assert!(equipment.can(&Capability::Lautering));
+    }
+
+    #[test]
+    fn test_equipment_from_config() {
+        let config: EquipmentConfig = serde_json::from_str(
+            r#"{"id": 9, "name": "UT-001", "equipment_type": "fermentor", "capacity": "10g",
+                "capabilities": ["fermentation", "carbonation"]}"#,
+        )
+        .unwrap();
+        let equipment = Equipment::from(&config);
+        assert_eq!(equipment.equipment_group, EquipmentGroup::Fermentor);
+        assert_eq!(
+            equipment.capabilities,
+            vec![Capability::Fermentation, Capability::Carbonation]
+        );
+        assert_eq!(equipment.contents, None);
+
+        let config: EquipmentConfig = serde_json::from_str(
+            r#"{"id": 10, "name": "CO2-001", "equipment_type": "gastank", "capacity": "5g",
+                "contents": "20lb"}"#,
+        )
+        .unwrap();
+        let equipment = Equipment::from(&config);
+        assert_eq!(equipment.contents, Some(Volume::Lb(20.0)));
+        assert!(equipment.capabilities.is_empty());
+        assert!(equipment.downtime.is_empty());
+
+        let config: EquipmentConfig = serde_json::from_str(
+            r#"{"id": 11, "name": "FV-002", "equipment_type": "fermentor", "capacity": "10g",
+                "downtime": [{"start": "2021-01-04T08:00:00Z", "end": "2021-01-06T17:00:00Z",
+                              "reason": "Gasket replacement"}]}"#,
+        )
+        .unwrap();
+        let equipment = Equipment::from(&config);
+        assert_eq!(equipment.downtime, vec![downtime::mock::mock_downtime()]);
+        assert!(equipment.maintenance.is_empty());
+    }
+
+    #[test]
+    fn test_equipment_visits_from_config() {
+        let config: EquipmentConfig = serde_json::from_str(
+            r#"{"id": 13, "name": "Mobile Canner", "equipment_type": "line", "capacity": "15g",
+                "formats": [{"format": "can", "rate": 1200}],
+                "visits": [{"start": "2021-02-03T08:00:00Z", "end": "2021-02-03T14:00:00Z",
+                            "cases": 200}]}"#,
+        )
+        .unwrap();
+        let equipment = Equipment::from(&config);
+        assert_eq!(equipment.visits, vec![visit::mock::mock_visit()]);
+    }
+
+    #[test]
+    fn test_equipment_transfer_time() {
+        let equipment = mock::mock_equipment();
+        assert_eq!(equipment.transfer_time(&Volume::GallonUS(10.0)), None);
+        let equipment = equipment.with_pump_rate(Volume::GallonUS(5.0));
+        assert_eq!(
+            equipment.transfer_time(&Volume::GallonUS(10.0)),
+            Some(chrono::Duration::minutes(2))
+        );
+    }
+
+    #[test]
+    fn test_equipment_handling_from_config() {
+        let config: EquipmentConfig = serde_json::from_str(
+            r#"{"id": 12, "name": "FV-003", "equipment_type": "fermentor", "capacity": "10g",
+                "pump_rate": "2.5g", "cip_time": "45min"}"#,
+        )
+        .unwrap();
+        let equipment = Equipment::from(&config);
+        assert_eq!(equipment.pump_rate, Some(Volume::GallonUS(2.5)));
+        assert_eq!(equipment.cip_time, Some(chrono::Duration::minutes(45)));
+    }
+
     #[test]
     fn test_equimpment_fits() {
         let equipment = mock::mock_equipment();
@@ -1,12 +1,20 @@
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+use crate::capability::Capability;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub enum EquipmentGroup {
     MashTun,
     LauterTun,
     HotLiquorTank,
     Kettle,
     Fermentor,
+    BriteTank,
+    Unitank,
     CO2Tank,
     Keg,
+    PackagingLine,
+    Pump,
+    HeatExchanger,
+    CipSkid,
 }
 
 impl EquipmentGroup {
@@ -17,10 +25,72 @@ impl EquipmentGroup {
             EquipmentGroup::HotLiquorTank => "Hot Liqoor Tank",
             EquipmentGroup::Kettle => "Kettle",
             EquipmentGroup::Fermentor => "Fermentor",
+            EquipmentGroup::BriteTank => "Brite Tank",
+            EquipmentGroup::Unitank => "Unitank",
             EquipmentGroup::CO2Tank => "CO2 Tank",
             EquipmentGroup::Keg => "Keg",
+            EquipmentGroup::PackagingLine => "Packaging Line",
+            EquipmentGroup::Pump => "Pump",
+            EquipmentGroup::HeatExchanger => "Heat Exchanger",
+            EquipmentGroup::CipSkid => "CIP Skid",
+        }
+    }
+
+    /// What equipment of this group can do, unless the equipment itself says otherwise.
+    pub fn capabilities(&self) -> Vec<Capability> {
+        match self {
+            EquipmentGroup::MashTun => vec![Capability::Mashing],
+            EquipmentGroup::LauterTun => vec![Capability::Lautering],
+            EquipmentGroup::HotLiquorTank => vec![Capability::HeatingWater],
+            EquipmentGroup::Kettle => vec![Capability::Boiling],
+            EquipmentGroup::Fermentor => vec![Capability::Fermentation],
+            EquipmentGroup::BriteTank => vec![
+                Capability::Conditioning,
+                Capability::Carbonation,
+                Capability::Serving,
+            ],
+            EquipmentGroup::Unitank => vec![
+                Capability::Fermentation,
+                Capability::Conditioning,
+                Capability::Carbonation,
+                Capability::Serving,
+            ],
+            // The gas is used up by the carbonation, the cylinder itself is not booked
+            EquipmentGroup::CO2Tank => vec![],
+            // Kegs are counted by the keg fleets, they leave the building when they are filled
+            EquipmentGroup::Keg => vec![],
+            EquipmentGroup::PackagingLine => vec![Capability::Packaging],
+            // Shared equipment is not booked for a step, but for the transfers and cleans
+            EquipmentGroup::Pump | EquipmentGroup::HeatExchanger | EquipmentGroup::CipSkid => {
+                vec![]
+            }
+        }
+    }
+
+    /// Equipment that is shared by all the vessels, like the pumps, chillers and CIP skids.
+    pub fn is_shared(&self) -> bool {
+        matches!(
+            self,
+            EquipmentGroup::Pump | EquipmentGroup::HeatExchanger | EquipmentGroup::CipSkid
+        )
+    }
+
+    /// Whether this shared equipment is needed to transfer a batch `from` a step `to` the next.
+    /// Every transfer needs a pump, and the knockout into the fermentor goes through the chiller.
+    pub fn used_for_transfer(&self, from: &Capability, to: &Capability) -> bool {
+        match self {
+            EquipmentGroup::Pump => true,
+            EquipmentGroup::HeatExchanger => {
+                to == &Capability::Fermentation && from != &Capability::Fermentation
+            }
+            _ => false,
         }
     }
+
+    /// Whether this shared equipment is needed to clean a vessel.
+    pub fn used_for_clean(&self) -> bool {
+        self == &EquipmentGroup::CipSkid
+    }
 }
 
 impl std::str::FromStr for EquipmentGroup {
@@ -38,10 +108,22 @@ impl std::str::FromStr for EquipmentGroup {
             "kettle" => Ok(EquipmentGroup::Kettle),
             "Fermentor" => Ok(EquipmentGroup::Fermentor),
             "fermentor" => Ok(EquipmentGroup::Fermentor),
+            "Brite Tank" => Ok(EquipmentGroup::BriteTank),
+            "brite" => Ok(EquipmentGroup::BriteTank),
+            "Unitank" => Ok(EquipmentGroup::Unitank),
+            "unitank" => Ok(EquipmentGroup::Unitank),
             "CO2 Tank" => Ok(EquipmentGroup::CO2Tank),
             "gastank" => Ok(EquipmentGroup::CO2Tank),
             "Keg" => Ok(EquipmentGroup::Keg),
             "keg" => Ok(EquipmentGroup::Keg),
+            "Packaging Line" => Ok(EquipmentGroup::PackagingLine),
+            "line" => Ok(EquipmentGroup::PackagingLine),
+            "Pump" => Ok(EquipmentGroup::Pump),
+            "pump" => Ok(EquipmentGroup::Pump),
+            "Heat Exchanger" => Ok(EquipmentGroup::HeatExchanger),
+            "chiller" => Ok(EquipmentGroup::HeatExchanger),
+            "CIP Skid" => Ok(EquipmentGroup::CipSkid),
+            "cipskid" => Ok(EquipmentGroup::CipSkid),
 
             _ => Err(()),
         }
@@ -68,6 +150,14 @@ pub mod mock {
         EquipmentGroup::Fermentor
     }
 
+    pub fn mock_brite_tank() -> EquipmentGroup {
+        EquipmentGroup::BriteTank
+    }
+
+    pub fn mock_unitank() -> EquipmentGroup {
+        EquipmentGroup::Unitank
+    }
+
     pub fn mock_co2_tank() -> EquipmentGroup {
         EquipmentGroup::CO2Tank
     }
@@ -75,6 +165,14 @@ pub mod mock {
     pub fn mock_keg() -> EquipmentGroup {
         EquipmentGroup::Keg
     }
+
+    pub fn mock_packaging_line() -> EquipmentGroup {
+        EquipmentGroup::PackagingLine
+    }
+
+    pub fn mock_pump() -> EquipmentGroup {
+        EquipmentGroup::Pump
+    }
 }
 
 #[cfg(test)]
@@ -87,8 +185,13 @@ mod tests {
         assert_eq!(EquipmentGroup::HotLiquorTank.lookup(), "Hot Liqoor Tank");
         assert_eq!(EquipmentGroup::Kettle.lookup(), "Kettle");
         assert_eq!(EquipmentGroup::Fermentor.lookup(), "Fermentor");
+        assert_eq!(EquipmentGroup::BriteTank.lookup(), "Brite Tank");
+        assert_eq!(EquipmentGroup::Unitank.lookup(), "Unitank");
         assert_eq!(EquipmentGroup::CO2Tank.lookup(), "CO2 Tank");
         assert_eq!(EquipmentGroup::Keg.lookup(), "Keg");
+        assert_eq!(EquipmentGroup::PackagingLine.lookup(), "Packaging Line");
+        assert_eq!(EquipmentGroup::HeatExchanger.lookup(), "Heat Exchanger");
+        assert_eq!(EquipmentGroup::CipSkid.lookup(), "CIP Skid");
     }
 
     #[test]
@@ -98,7 +201,49 @@ mod tests {
         assert_eq!("Hot Liquor Tank".parse(), Ok(EquipmentGroup::HotLiquorTank));
         assert_eq!("Kettle".parse(), Ok(EquipmentGroup::Kettle));
         assert_eq!("Fermentor".parse(), Ok(EquipmentGroup::Fermentor));
+        assert_eq!("Brite Tank".parse(), Ok(EquipmentGroup::BriteTank));
+        assert_eq!("unitank".parse(), Ok(EquipmentGroup::Unitank));
         assert_eq!("CO2 Tank".parse(), Ok(EquipmentGroup::CO2Tank));
         assert_eq!("Keg".parse(), Ok(EquipmentGroup::Keg));
+        assert_eq!("line".parse(), Ok(EquipmentGroup::PackagingLine));
+        assert_eq!("pump".parse(), Ok(EquipmentGroup::Pump));
+        assert_eq!("chiller".parse(), Ok(EquipmentGroup::HeatExchanger));
+        assert_eq!("cipskid".parse(), Ok(EquipmentGroup::CipSkid));
+    }
+
+    #[test]
+    fn test_equipmentgroup_capabilities() {
+        assert_eq!(
+            EquipmentGroup::Fermentor.capabilities(),
+            vec![Capability::Fermentation]
+        );
+        assert!(EquipmentGroup::BriteTank
+            .capabilities()
+            .contains(&Capability::Carbonation));
+        assert!(!EquipmentGroup::BriteTank
+            .capabilities()
+            .contains(&Capability::Fermentation));
+        assert!(EquipmentGroup::Unitank
+            .capabilities()
+            .contains(&Capability::Fermentation));
+        assert!(EquipmentGroup::Unitank
+            .capabilities()
+            .contains(&Capability::Serving));
+        assert_eq!(EquipmentGroup::CO2Tank.capabilities(), vec![]);
+        assert_eq!(EquipmentGroup::Keg.capabilities(), vec![]);
+        assert_eq!(EquipmentGroup::Pump.capabilities(), vec![]);
+    }
+
+    #[test]
+    fn test_equipmentgroup_shared_use() {
+        let (mashing, fermentation) = (Capability::Mashing, Capability::Fermentation);
+        assert!(mock::mock_pump().used_for_transfer(&fermentation, &fermentation));
+        assert!(EquipmentGroup::HeatExchanger.used_for_transfer(&mashing, &fermentation));
+        assert!(!EquipmentGroup::HeatExchanger.used_for_transfer(&fermentation, &fermentation));
+        assert!(!EquipmentGroup::CipSkid.used_for_transfer(&mashing, &fermentation));
+        assert!(EquipmentGroup::CipSkid.used_for_clean());
+        assert!(!mock::mock_pump().used_for_clean());
+        assert!(EquipmentGroup::CipSkid.is_shared());
+        assert!(!EquipmentGroup::Fermentor.is_shared());
     }
 }
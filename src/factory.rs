@@ -1,21 +1,57 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
-use chrono::Duration;
+use chrono::{DateTime, Duration, Utc};
 use z3::{ast, ast::Ast, Config, Context, Optimize, SatResult};
 
 use crate::batchneed::BatchNeed;
 use crate::beer::Beer;
+use crate::capability::Capability;
 use crate::capacity::Capacity;
+use crate::changeover::{Changeover, ChangeoverRule};
+use crate::clean_level::CleanLevel;
+use crate::config::{FactoryConfig, HandlingConfig};
+use crate::cumulative_resource::CumulativeResource;
 use crate::equipment::Equipment;
 use crate::equipment_group::EquipmentGroup;
+use crate::forecast::{Forecast, Sale};
+use crate::interval::Interval;
+use crate::inventory::Stock;
+use crate::keg_fleet::KegFleet;
+use crate::maintenance::MaintenanceRule;
+use crate::material::Material;
+use crate::order_book::{CustomerOrder, Fulfillment};
+use crate::package_format::PackageFormat;
+use crate::packaging::PackagingOrder;
+use crate::release::Release;
+use crate::scaling::Scaling;
+use crate::segregation::{Segregation, SegregationRule};
+use crate::step_definition::StepDefinition;
 use crate::step_group::StepGroup;
+use crate::tap_list::TapList;
 use crate::volume::Volume;
+use crate::yeast::YeastStrain;
 
 #[derive(Debug, PartialEq)]
 pub struct Factory {
     pub name: String,
     pub equipments: HashMap<String, Equipment>,
     pub beers: HashMap<String, Beer>,
+    pub recipe_history: HashMap<String, Vec<Beer>>,
+    pub step_definitions: HashMap<String, StepDefinition>,
+    pub changeover: Changeover,
+    pub segregation: Segregation,
+    pub resources: Vec<CumulativeResource>,
+    pub packaging_crew: Option<u32>,
+    pub packaging_orders: Vec<PackagingOrder>,
+    pub keg_fleets: Vec<KegFleet>,
+    pub inventory: Vec<Stock>,
+    pub tap_list: Option<TapList>,
+    pub releases: Vec<Release>,
+    pub forecast: Option<Forecast>,
+    pub order_book: Vec<CustomerOrder>,
+    pub yeasts: HashMap<String, YeastStrain>,
+    pub materials: HashMap<String, Material>,
 }
 
 impl Factory {
@@ -24,13 +60,198 @@ impl Factory {
             name: name.to_string(),
             equipments: HashMap::new(),
             beers: HashMap::new(),
+            recipe_history: HashMap::new(),
+            step_definitions: HashMap::new(),
+            changeover: Changeover::new(),
+            segregation: Segregation::new(),
+            resources: Vec::new(),
+            packaging_crew: None,
+            packaging_orders: Vec::new(),
+            keg_fleets: Vec::new(),
+            inventory: Vec::new(),
+            tap_list: None,
+            releases: Vec::new(),
+            forecast: None,
+            order_book: Vec::new(),
+            yeasts: HashMap::new(),
+            materials: HashMap::new(),
         }
     }
 
-    pub fn calculate_batches(
-        &self,
-        wishlist: HashMap<&'static str, (&Beer, Volume)>,
-    ) -> HashMap<usize, BatchNeed> {
+    /// The clean the equipment needs when it goes `from` one beer `to` another. Equipment that
+    /// is kept apart for a segregated beer is decontaminated, whatever the changeover matrix says.
+    pub fn clean_level(&self, equipment: &Equipment, from: &Beer, to: &Beer) -> CleanLevel {
+        if self.segregation.needs_decontamination(equipment, from, to) {
+            return CleanLevel::Decontamination;
+        }
+
+        self.changeover.clean_level(from, to)
+    }
+
+    /// Look up a step by name, either one of the built-in steps or one that is declared by
+    /// this factory.
+    pub fn step_group(&self, name: &str) -> Option<StepGroup> {
+        StepGroup::named(name, &self.step_definitions)
+    }
+
+    /// Add a beer, or another version of the recipe of a beer that is known already. The
+    /// version that takes effect last stays in `beers`, even when that is still to come. The
+    /// others go in `recipe_history`, oldest first. The new batches are brewed with the version
+    /// that is in effect, see `beer_at`.
+    pub fn add_beer(&mut self, beer: Beer) {
+        let beer = match self.beers.remove(&beer.name) {
+            Some(other) => {
+                if other.version == beer.version {
+                    panic!(
+                        "{} does not appear to be a valid version, there is one already",
+                        beer.label()
+                    );
+                }
+                let (older, newer) = if other.effective > beer.effective {
+                    (beer, other)
+                } else {
+                    (other, beer)
+                };
+                let history = self.recipe_history.entry(newer.name.clone()).or_default();
+                history.push(older);
+                history.sort_by_key(|beer| beer.effective);
+                newer
+            }
+            None => beer,
+        };
+        self.beers.insert(beer.name.clone(), beer);
+    }
+
+    /// The version of the recipe of a beer that was in effect when a batch was brewed, so
+    /// that a batch in progress keeps its version. A batch that was brewed before the first
+    /// version took effect gets the first version.
+    pub fn beer_at(&self, name: &str, brewed: DateTime<Utc>) -> Option<&Beer> {
+        let history = self.recipe_history.get(name).into_iter().flatten();
+        let mut versions = self
+            .beers
+            .get(name)
+            .into_iter()
+            .chain(history.rev())
+            .peekable();
+        versions.peek()?;
+        let mut oldest = None;
+        for beer in versions {
+            match beer.effective {
+                Some(effective) if effective > brewed => oldest = Some(beer),
+                _ => return Some(beer),
+            }
+        }
+        oldest
+    }
+
+    /// A version of the recipe of a beer.
+    pub fn beer_version(&self, name: &str, version: u32) -> Option<&Beer> {
+        let history = self.recipe_history.get(name).into_iter().flatten();
+        self.beers
+            .get(name)
+            .into_iter()
+            .chain(history)
+            .find(|beer| beer.version == Some(version))
+    }
+
+    /// The sizes of the brewhouses: the systems with equipment to mash in.
+    pub fn brew_systems(&self) -> Vec<Capacity> {
+        let mut systems = self
+            .equipments
+            .values()
+            .filter(|equipment| equipment.can(&Capability::Mashing))
+            .map(|equipment| equipment.capacity.clone())
+            .collect::<Vec<Capacity>>();
+        systems.sort();
+        systems.dedup();
+        systems
+    }
+
+    /// Whether the beer has a recipe for the system, and there is equipment of that size for
+    /// every step of it.
+    pub fn can_brew(&self, beer: &Beer, system: &Capacity) -> bool {
+        match beer.recipe.get(system) {
+            Some((_yield, steps)) => steps.iter().all(|(step_group, _interval)| {
+                self.equipments.values().any(|equipment| {
+                    &equipment.capacity == system && equipment.can(&step_group.capability())
+                })
+            }),
+            None => false,
+        }
+    }
+
+    /// Scale the recipe of every beer to the brew systems that it has no entry for.
+    pub fn scale_recipes(&mut self, scaling: &Scaling) {
+        let systems = self.brew_systems();
+        let history = self.recipe_history.values_mut().flatten();
+        for beer in self.beers.values_mut().chain(history) {
+            for system in systems.iter() {
+                beer.recipe.scale(system, scaling);
+            }
+        }
+    }
+
+    /// The entries of the recipes that were scaled from another system, to check them.
+    pub fn scaled_recipes(&self) -> Vec<String> {
+        let mut scaled = Vec::new();
+        let history = self.recipe_history.values().flatten();
+        for beer in self.beers.values().chain(history) {
+            for (system, base_system) in beer.recipe.scaled_from.iter() {
+                let (r#yield, steps) = beer.recipe.get(system).unwrap();
+                let gallons = match r#yield.to_gallon_us() {
+                    Volume::GallonUS(gallons) => gallons,
+                    _ => panic!("Should not happen"),
+                };
+                let steps = steps
+                    .iter()
+                    .map(|(step_group, interval)| {
+                        format!("{} {}", step_group.lookup(), interval.lookup())
+                    })
+                    .collect::<Vec<String>>();
+                scaled.push(format!(
+                    "{} on {}, scaled from {}: {:.1}G, {}",
+                    beer.label(),
+                    system.lookup(),
+                    base_system.lookup(),
+                    gallons,
+                    steps.join(", ")
+                ));
+            }
+        }
+        scaled.sort();
+        scaled
+    }
+
+    /// The beers of the wishlist that no system can brew (see `can_brew`), with the volume
+    /// that is wished for. `calculate_batches` leaves them out.
+    pub fn unbrewable(&self, wishlist: &HashMap<&str, (&Beer, Volume)>) -> Vec<String> {
+        let mut unbrewable = wishlist
+            .iter()
+            .filter(|(_name, (beer, _volume))| {
+                !self
+                    .equipments
+                    .values()
+                    .any(|equipment| self.can_brew(beer, &equipment.capacity))
+            })
+            .map(|(name, (_beer, volume))| {
+                format!(
+                    "{} of {} is wished for, there is no recipe for a system that can brew it",
+                    volume.to_gallon_us().lookup(),
+                    name
+                )
+            })
+            .collect::<Vec<String>>();
+        unbrewable.sort();
+        unbrewable
+    }
+
+    /// The batches to brew for the wishlist, in as few batches as possible. Only the systems
+    /// that can brew a beer (see `can_brew`) are used for it, with the yield of its recipe. A
+    /// beer that no system can brew is left out, see `unbrewable`.
+    pub fn calculate_batches<'a>(
+        &'a self,
+        wishlist: HashMap<&str, (&'a Beer, Volume)>,
+    ) -> HashMap<usize, BatchNeed<'a>> {
         let mut batches_needed = HashMap::with_capacity(wishlist.len());
         let mut cfg = Config::new();
         cfg.set_proof_generation(false);
@@ -66,27 +287,40 @@ impl Factory {
         let total_batches = ast::Int::new_const(&ctx, "total batches");
         let mut all_beer_batches = Vec::with_capacity(wishlist.len());
         let mut all_beer_system_batches = HashMap::new();
-        for (name, (_beer, volume)) in &wishlist {
+        for (name, (beer, volume)) in &wishlist {
+            let beer_systems = systems
+                .keys()
+                .filter(|system| self.can_brew(beer, system))
+                .collect::<Vec<&&Capacity>>();
+            if beer_systems.is_empty() {
+                continue;
+            }
             if let Volume::Liter(needed_liters) = volume.to_liter() {
                 let beer_need = ast::Int::new_const(&ctx, format!("beer need {}", name));
                 //@fixme: also here from i64 to real
                 solver.assert(&beer_need._eq(&ast::Int::from_i64(&ctx, needed_liters as i64)));
                 let beer_total = ast::Int::new_const(&ctx, format!("beer total {}", name));
                 let mut beer_system_volumes = Vec::with_capacity(systems.len());
-                for (system, r#_yield) in &systems {
+                for system in beer_systems {
                     let beer_system_batches = ast::Int::new_const(
                         &ctx,
                         format!("beer {} system {} batches", name, system.lookup()),
                     );
                     all_beer_batches.push(beer_system_batches.clone());
                     all_beer_system_batches.insert((name, system), beer_system_batches.clone());
-                    let beer_system_volume =
-                        ast::Int::new_const(&ctx, format!("beer systems volume {}", name));
+                    let beer_system_volume = ast::Int::new_const(
+                        &ctx,
+                        format!("beer {} system {} volume", name, system.lookup()),
+                    );
                     solver.assert(&beer_system_batches.ge(&ast::Int::from_i64(&ctx, 0)));
-                    let r#yield = systems.get(system).unwrap();
+                    let (recipe_yield, _steps) = beer.recipe.get(system).unwrap();
+                    let r#yield = match recipe_yield.to_liter() {
+                        Volume::Liter(liters) => ast::Int::from_i64(&ctx, (liters as i64).max(1)),
+                        _ => panic!("should not happen"),
+                    };
                     solver.assert(
                         &beer_system_volume
-                            ._eq(&ast::Int::mul(&ctx, &[&beer_system_batches, r#yield])),
+                            ._eq(&ast::Int::mul(&ctx, &[&beer_system_batches, &r#yield])),
                     );
                     beer_system_volumes.push(beer_system_volume);
                 }
@@ -103,6 +337,9 @@ impl Factory {
                 solver.assert(&beer_total.ge(&beer_need));
             }
         }
+        if all_beer_batches.is_empty() {
+            return batches_needed;
+        }
 
         solver.assert(
             &total_batches._eq(&ast::Int::add(
@@ -121,7 +358,7 @@ impl Factory {
                 let mut id = 1;
                 for ((name, system), batch_count_int) in all_beer_system_batches.iter() {
                     let batch_count = model.eval(batch_count_int, true).unwrap().as_i64().unwrap();
-                    let beer = self.beers.get(&name.to_string()).unwrap();
+                    let (beer, _volume) = &wishlist[*name];
                     for i in 0..batch_count {
                         let (r#yield, _steps) = beer.recipe.get(&system).unwrap();
                         let mut vol = r#yield.clone();
@@ -189,28 +426,708 @@ impl Factory {
             .collect()
     }
 
+    /// Carry the usage of the equipment over to the next planning, see `Plan::maintenance_counters`.
+    pub fn update_maintenance(&mut self, counters: HashMap<String, Vec<MaintenanceRule>>) {
+        for (name, rules) in counters {
+            if let Some(equipment) = self.equipments.get_mut(&name) {
+                equipment.maintenance = rules;
+            }
+        }
+    }
+
+    /// The pumps, chillers and CIP skids that are shared by all the vessels, by group and name.
+    pub fn shared_equipment(&self) -> HashMap<EquipmentGroup, Vec<&Equipment>> {
+        let mut shared: HashMap<EquipmentGroup, Vec<&Equipment>> = HashMap::new();
+        for equipment in self.equipments.values() {
+            if equipment.equipment_group.is_shared() {
+                shared
+                    .entry(equipment.equipment_group.clone())
+                    .or_default()
+                    .push(equipment);
+            }
+        }
+        for equipments in shared.values_mut() {
+            equipments.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        shared
+    }
+
+    /// The pounds of CO2 that are left in the gas cylinders.
+    pub fn co2_inventory(&self) -> Volume {
+        let pounds = self
+            .equipments
+            .values()
+            .filter(|equipment| equipment.equipment_group == EquipmentGroup::CO2Tank)
+            .map(|equipment| match &equipment.contents {
+                Some(Volume::Lb(x)) => *x,
+                _ => 0.0,
+            })
+            .sum();
+        Volume::Lb(pounds)
+    }
+
+    /// The pounds of CO2 that the carbonation of these batches uses up.
+    pub fn co2_needed(&self, batches_needed: &HashMap<usize, BatchNeed>) -> Volume {
+        let mut pounds = 0.0;
+        for batch in batches_needed.values() {
+            for (step_group, _interval) in batch.steps() {
+                if let Some(method) = step_group.carbonation_method() {
+                    if let Volume::Lb(x) = method.co2_usage(&batch.volume) {
+                        pounds += x;
+                    }
+                }
+            }
+        }
+        Volume::Lb(pounds)
+    }
+
+    /// How much CO2 needs to be bought before these batches can be carbonated, if any.
+    pub fn co2_shortfall(&self, batches_needed: &HashMap<usize, BatchNeed>) -> Option<Volume> {
+        match (self.co2_needed(batches_needed), self.co2_inventory()) {
+            (Volume::Lb(needed), Volume::Lb(available)) if needed > available => {
+                Some(Volume::Lb(needed - available))
+            }
+            _ => None,
+        }
+    }
+
+    /// The steps that take more of a resource than there is, like a batch too large for the
+    /// walk-in cooler. The planner gives them all of it, but they won't fit as planned.
+    pub fn resource_overloads(&self, batches_needed: &HashMap<usize, BatchNeed>) -> Vec<String> {
+        let mut ids = batches_needed.keys().cloned().collect::<Vec<usize>>();
+        ids.sort();
+        let mut overloads = Vec::new();
+        for id in ids {
+            let batch = &batches_needed[&id];
+            for (step_group, _interval) in batch.steps() {
+                for resource in &self.resources {
+                    let usage = resource.usage(&step_group, batch);
+                    if usage > resource.limit {
+                        overloads.push(format!(
+                            "{} of batch {} takes {} of the {}, there is only {}",
+                            step_group.lookup(),
+                            batch.id,
+                            usage,
+                            resource.name,
+                            resource.limit
+                        ));
+                    }
+                }
+            }
+        }
+        overloads
+    }
+
+    /// The kegs that are ordered of a size, more than its fleet is projected to ever have clean
+    /// in the building.
+    pub fn keg_shortages(
+        &self,
+        batches_needed: &HashMap<usize, BatchNeed>,
+        now: DateTime<Utc>,
+    ) -> Vec<String> {
+        let mut shortages = Vec::new();
+        for fleet in self.keg_fleets.iter() {
+            let ordered = batches_needed
+                .values()
+                .flat_map(|batch| batch.packaging.iter())
+                .filter(|(format, _units)| format == &fleet.format)
+                .map(|(_format, units)| units)
+                .sum::<u32>();
+            let most = fleet.most(now);
+            if ordered > most {
+                shortages.push(format!(
+                    "{} x {} are ordered, the fleet only has {} kegs to fill",
+                    ordered,
+                    fleet.format.lookup(),
+                    most
+                ));
+            }
+        }
+        shortages
+    }
+
+    /// The fewest packages of the format that a packaging line is willing to run, if any line
+    /// can fill it.
+    pub fn minimum_run(&self, format: &PackageFormat) -> Option<u32> {
+        self.equipments
+            .values()
+            .filter_map(|equipment| equipment.packaging_rate(format))
+            .map(|rate| rate.minimum_run.max(1))
+            .min()
+    }
+
+    /// Allocate the ordered packages to the batches of the beer, in the order they are asked
+    /// for. A batch only gets a part of an order that a packaging line is willing to run. The
+    /// packages that don't fit in any of the batches, or that no line can fill, are given back.
+    pub fn allocate_packaging(
+        &self,
+        batches_needed: &mut HashMap<usize, BatchNeed>,
+        orders: &[PackagingOrder],
+    ) -> Vec<PackagingOrder> {
+        let mut ids = batches_needed.keys().cloned().collect::<Vec<usize>>();
+        ids.sort();
+        let mut unallocated = Vec::new();
+        for order in orders {
+            let mut units = order.units;
+            let minimum_run = self.minimum_run(&order.format);
+            for id in ids.iter() {
+                let batch = batches_needed.get_mut(id).unwrap();
+                if batch.beer.name != order.beer || units == 0 {
+                    continue;
+                }
+                let fits = units.min(order.format.units(&batch.unpackaged()));
+                if minimum_run.is_some_and(|minimum_run| fits >= minimum_run) {
+                    batch.packaging.push((order.format.clone(), fits));
+                    units -= fits;
+                }
+            }
+            if units > 0 {
+                unallocated.push(PackagingOrder::new(
+                    order.beer.clone(),
+                    order.format.clone(),
+                    units,
+                ));
+            }
+        }
+        unallocated
+    }
+
+    /// The beers that pour from the stock on hand, and till when. A stock that is not sold pours
+    /// for good.
+    pub fn stock_on_tap(&self, now: DateTime<Utc>) -> Vec<(&Beer, Option<DateTime<Utc>>)> {
+        let mut on_tap: Vec<(&Beer, Option<DateTime<Utc>>)> = Vec::new();
+        for stock in self.inventory.iter().filter(|stock| stock.on_hand > 0) {
+            let beer = match self.beers.get(&stock.beer) {
+                Some(beer) => beer,
+                None => continue,
+            };
+            let till = stock.lasts().map(|lasts| now + lasts);
+            match on_tap
+                .iter_mut()
+                .find(|(other, _till)| other.name == beer.name)
+            {
+                Some((_beer, other)) => {
+                    *other = match (*other, till) {
+                        (Some(other), Some(till)) => Some(other.max(till)),
+                        _ => None,
+                    }
+                }
+                None => on_tap.push((beer, till)),
+            }
+        }
+        on_tap.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+        on_tap
+    }
+
+    /// The packages of every stock that are in progress. A batch in progress counts with the
+    /// packages it is allocated to, what is left of it is shared by the stocks of its beer, in
+    /// proportion to the volume they are short by the end of the horizon.
+    fn incoming(&self, horizon: Duration, in_progress: &HashMap<usize, BatchNeed>) -> Vec<u32> {
+        let mut incoming = self
+            .inventory
+            .iter()
+            .map(|stock| {
+                in_progress
+                    .values()
+                    .filter(|batch| batch.beer.name == stock.beer)
+                    .flat_map(|batch| batch.packaging.iter())
+                    .filter(|(format, _units)| format == &stock.format)
+                    .map(|(_format, units)| units)
+                    .sum::<u32>()
+            })
+            .collect::<Vec<u32>>();
+        let short = self
+            .inventory
+            .iter()
+            .zip(incoming.iter())
+            .map(|(stock, incoming)| {
+                stock
+                    .format
+                    .volume_of(stock.shortfall(horizon, *incoming))
+                    .liters()
+            })
+            .collect::<Vec<f32>>();
+        let mut unpackaged: HashMap<&str, f32> = HashMap::new();
+        for batch in in_progress.values() {
+            if let Volume::Liter(liter) = batch.unpackaged() {
+                *unpackaged.entry(batch.beer.name.as_str()).or_default() += liter;
+            }
+        }
+        for (beer, liter) in unpackaged {
+            let stocks = (0..self.inventory.len())
+                .filter(|pos| self.inventory[*pos].beer == beer)
+                .collect::<Vec<usize>>();
+            let total = stocks.iter().map(|pos| short[*pos]).sum::<f32>();
+            if total <= 0.0 {
+                continue;
+            }
+            for pos in stocks {
+                let share = liter * short[pos] / total;
+                incoming[pos] += self.inventory[pos].format.units(&Volume::Liter(share));
+            }
+        }
+        incoming
+    }
+
+    /// The packages to make so that the stock stays above its safety stock till the end of the
+    /// horizon, on top of the ones in progress.
+    pub fn replenishment(
+        &self,
+        horizon: Duration,
+        in_progress: &HashMap<usize, BatchNeed>,
+    ) -> Vec<PackagingOrder> {
+        let incoming = self.incoming(horizon, in_progress);
+        let mut orders = Vec::new();
+        for (stock, incoming) in self.inventory.iter().zip(incoming) {
+            let units = stock.shortfall(horizon, incoming);
+            if units > 0 {
+                orders.push(PackagingOrder::new(
+                    stock.beer.clone(),
+                    stock.format.clone(),
+                    units,
+                ));
+            }
+        }
+        orders
+    }
+
+    /// The batches to brew so that no beer in the inventory runs dry over the horizon, on top
+    /// of the batches in progress. The new batches are numbered after those in progress, and
+    /// are allocated to the packages they are brewed for. Each of them is due when its stock
+    /// would drop below the safety stock without it. The packages that can't be allocated are
+    /// given back, and a batch without any is left out.
+    pub fn make_to_stock(
+        &self,
+        horizon: Duration,
+        in_progress: &HashMap<usize, BatchNeed>,
+        now: DateTime<Utc>,
+    ) -> (HashMap<usize, BatchNeed<'_>>, Vec<PackagingOrder>) {
+        let orders = self.replenishment(horizon, in_progress);
+        let mut wishlist: HashMap<&str, (&Beer, Volume)> = HashMap::new();
+        for order in orders.iter() {
+            let beer = match self.beer_at(&order.beer, now) {
+                Some(beer) => beer,
+                None => panic!("{} does not appear to be a valid beer to stock", order.beer),
+            };
+            let liter = order.format.volume_of(order.units).liters();
+            let entry = wishlist
+                .entry(beer.name.as_str())
+                .or_insert((beer, Volume::Liter(0.0)));
+            if let Volume::Liter(total) = entry.1 {
+                entry.1 = Volume::Liter(total + liter);
+            }
+        }
+        if wishlist.is_empty() {
+            return (HashMap::new(), Vec::new());
+        }
+        let offset = in_progress.keys().max().cloned().unwrap_or(0);
+        let mut batches_needed = self
+            .calculate_batches(wishlist)
+            .into_values()
+            .map(|mut batch| {
+                batch.id += offset;
+                (batch.id, batch)
+            })
+            .collect::<HashMap<usize, BatchNeed>>();
+        let unallocated = self.allocate_packaging(&mut batches_needed, &orders);
+        // A batch that none of the packages could be allocated to is not brewed for the stock
+        batches_needed.retain(|_id, batch| !batch.packaging.is_empty());
+        let mut ids = batches_needed.keys().cloned().collect::<Vec<usize>>();
+        ids.sort();
+        let incoming = self.incoming(horizon, in_progress);
+        for (stock, mut incoming) in self.inventory.iter().zip(incoming) {
+            for id in ids.iter() {
+                let batch = batches_needed.get_mut(id).unwrap();
+                let units = batch
+                    .packaging
+                    .iter()
+                    .filter(|(format, _units)| format == &stock.format)
+                    .map(|(_format, units)| units)
+                    .sum::<u32>();
+                if batch.beer.name != stock.beer || units == 0 {
+                    continue;
+                }
+                if let Some(runs_out) = stock.runs_out(incoming) {
+                    let due = now + runs_out;
+                    batch.due = Some(batch.due.map_or(due, |other| other.min(due)));
+                }
+                incoming += units;
+            }
+        }
+        (batches_needed, unallocated)
+    }
+
+    /// The batches for the next release of every seasonal beer, due on the release date. The
+    /// new batches are numbered after those in progress, and brewed with the version of the
+    /// recipe that is in effect now.
+    pub fn release_batches(
+        &self,
+        in_progress: &HashMap<usize, BatchNeed>,
+        now: DateTime<Utc>,
+    ) -> HashMap<usize, BatchNeed<'_>> {
+        let offset = in_progress.keys().max().cloned().unwrap_or(0);
+        let mut batches_needed = HashMap::new();
+        for release in self.releases.iter() {
+            let beer = match self.beer_at(&release.beer, now) {
+                Some(beer) => beer,
+                None => panic!(
+                    "{} does not appear to be a valid beer to release",
+                    release.beer
+                ),
+            };
+            let mut wishlist = HashMap::with_capacity(1);
+            wishlist.insert(beer.name.as_str(), (beer, release.volume.clone()));
+            let due = release.next_date(now);
+            let mut batches = self
+                .calculate_batches(wishlist)
+                .into_values()
+                .collect::<Vec<BatchNeed>>();
+            batches.sort_by_key(|batch| batch.id);
+            for mut batch in batches {
+                batch.id = offset + batches_needed.len() + 1;
+                batches_needed.insert(batch.id, batch.with_due(due));
+            }
+        }
+        batches_needed
+    }
+
+    /// The batches to brew for the demand that is forecasted from the sales history.
+    pub fn forecast_batches(
+        &self,
+        sales: &[Sale],
+        now: DateTime<Utc>,
+    ) -> HashMap<usize, BatchNeed<'_>> {
+        match &self.forecast {
+            Some(forecast) => {
+                let wishlist = forecast
+                    .wishlist(sales, &self.beers, now)
+                    .into_iter()
+                    .filter_map(|(name, (_beer, volume))| {
+                        Some((name, (self.beer_at(name, now)?, volume)))
+                    })
+                    .collect();
+                self.calculate_batches(wishlist)
+            }
+            None => HashMap::new(),
+        }
+    }
+
+    /// The batches to brew for the order book, on top of the batches in progress. The orders
+    /// are served in the order they are delivered, first from the batches in progress, then
+    /// from the new batches. The new batches are as large as the beer can be brewed, and there
+    /// are as many as it takes to fit the packages; a batch that serves no order is left out.
+    /// The packages that don't fit in any batch are short. The new batches are brewed with the
+    /// version of the recipe that is in effect now.
+    pub fn order_batches(
+        &self,
+        in_progress: &mut HashMap<usize, BatchNeed>,
+        now: DateTime<Utc>,
+    ) -> (HashMap<usize, BatchNeed<'_>>, Vec<Fulfillment>) {
+        let mut orders = self.order_book.iter().collect::<Vec<&CustomerOrder>>();
+        orders.sort_by_key(|order| (order.delivery, order.customer.clone()));
+        let mut fulfillments = Vec::new();
+        let mut left = Vec::with_capacity(orders.len());
+        for order in orders {
+            let units = self.serve(order, order.units, in_progress, &mut fulfillments);
+            left.push((order, units));
+        }
+        // The room that is left in every new batch, when the packages are fitted in them the
+        // way they are served below
+        let mut rooms: HashMap<&str, (&Beer, f32, Vec<f32>)> = HashMap::new();
+        for (order, units) in left.iter() {
+            let beer = match self.beer_at(&order.beer, now) {
+                Some(beer) => beer,
+                None => panic!(
+                    "{} does not appear to be a valid beer for the order of {}",
+                    order.beer, order.customer
+                ),
+            };
+            let largest = self
+                .brew_systems()
+                .iter()
+                .filter(|system| self.can_brew(beer, system))
+                .filter_map(|system| Some(beer.recipe.get(system)?.0.liters()))
+                .fold(0.0, f32::max);
+            let minimum_run = self.minimum_run(&order.format);
+            let fills = |room: f32, units: u32| {
+                let fits = units.min(order.format.units(&Volume::Liter(room)));
+                match order.format.volume_of(fits) {
+                    Volume::Liter(liter) if minimum_run.is_some_and(|run| fits >= run) => {
+                        Some((fits, liter))
+                    }
+                    _ => None,
+                }
+            };
+            let (_beer, _largest, batches) =
+                rooms
+                    .entry(beer.name.as_str())
+                    .or_insert((beer, largest, Vec::new()));
+            let mut units = *units;
+            for room in batches.iter_mut() {
+                if units == 0 {
+                    break;
+                }
+                if let Some((fits, liter)) = fills(*room, units) {
+                    *room -= liter;
+                    units -= fits;
+                }
+            }
+            while units > 0 {
+                match fills(largest, units) {
+                    Some((fits, liter)) => {
+                        batches.push(largest - liter);
+                        units -= fits;
+                    }
+                    None => break,
+                }
+            }
+        }
+        let mut wishlist: HashMap<&str, (&Beer, Volume)> = HashMap::new();
+        for (name, (beer, largest, batches)) in rooms {
+            if !batches.is_empty() {
+                let liter = largest * batches.len() as f32;
+                wishlist.insert(name, (beer, Volume::Liter(liter)));
+            }
+        }
+        let offset = in_progress.keys().max().cloned().unwrap_or(0);
+        let mut batches_needed = match wishlist.is_empty() {
+            true => HashMap::new(),
+            false => self
+                .calculate_batches(wishlist)
+                .into_values()
+                .map(|mut batch| {
+                    batch.id += offset;
+                    (batch.id, batch)
+                })
+                .collect::<HashMap<usize, BatchNeed>>(),
+        };
+        for (order, units) in left {
+            let units = self.serve(order, units, &mut batches_needed, &mut fulfillments);
+            if units > 0 {
+                fulfillments.push(Fulfillment::new(order.clone(), None, units));
+            }
+        }
+        batches_needed.retain(|_id, batch| !batch.packaging.is_empty());
+        (batches_needed, fulfillments)
+    }
+
+    /// Serve the packages of an order from the first batches of the beer that have room for
+    /// them. A batch only gets a part of the order that a packaging line is willing to run, and
+    /// it is due when the first of its orders is delivered. The packages that are left are
+    /// given back.
+    fn serve(
+        &self,
+        order: &CustomerOrder,
+        mut units: u32,
+        batches_needed: &mut HashMap<usize, BatchNeed>,
+        fulfillments: &mut Vec<Fulfillment>,
+    ) -> u32 {
+        let minimum_run = self.minimum_run(&order.format);
+        let mut ids = batches_needed.keys().cloned().collect::<Vec<usize>>();
+        ids.sort();
+        for id in ids {
+            let batch = batches_needed.get_mut(&id).unwrap();
+            if batch.beer.name != order.beer || units == 0 {
+                continue;
+            }
+            let fits = units.min(order.format.units(&batch.unpackaged()));
+            if fits > 0 && minimum_run.is_some_and(|minimum_run| fits >= minimum_run) {
+                batch.packaging.push((order.format.clone(), fits));
+                batch.due = Some(
+                    batch
+                        .due
+                        .map_or(order.delivery, |due| due.min(order.delivery)),
+                );
+                fulfillments.push(Fulfillment::new(order.clone(), Some(id), fits));
+                units -= fits;
+            }
+        }
+        units
+    }
+
+    /// Which order each batch serves, followed by the packages that are short, and those that
+    /// will be late because their batch had to be brewed already.
+    pub fn fulfillment_report(
+        &self,
+        batches_needed: &HashMap<usize, BatchNeed>,
+        fulfillments: &[Fulfillment],
+        now: DateTime<Utc>,
+    ) -> Vec<String> {
+        let mut served = fulfillments
+            .iter()
+            .filter(|fulfillment| fulfillment.batch.is_some())
+            .collect::<Vec<&Fulfillment>>();
+        served.sort_by_key(|fulfillment| (fulfillment.batch, fulfillment.order.delivery));
+        let mut report = served
+            .iter()
+            .map(|fulfillment| {
+                format!(
+                    "Batch {} of {} serves {} x {} for {}, delivered on {}",
+                    fulfillment.batch.unwrap(),
+                    batches_needed
+                        .get(&fulfillment.batch.unwrap())
+                        .map(|batch| batch.beer.label())
+                        .unwrap_or_else(|| fulfillment.order.beer.clone()),
+                    fulfillment.units,
+                    fulfillment.order.format.lookup(),
+                    fulfillment.order.customer,
+                    fulfillment.order.delivery.format("%Y-%m-%d"),
+                )
+            })
+            .collect::<Vec<String>>();
+        for fulfillment in fulfillments.iter() {
+            let order = &fulfillment.order;
+            match fulfillment.batch {
+                None => report.push(format!(
+                    "{} will be short {} x {} of {} on {}",
+                    order.customer,
+                    fulfillment.units,
+                    order.format.lookup(),
+                    order.beer,
+                    order.delivery.format("%Y-%m-%d"),
+                )),
+                Some(id) => {
+                    let brew_by = batches_needed.get(&id).and_then(|batch| batch.brew_by());
+                    if let Some(brew_by) = brew_by.filter(|brew_by| *brew_by < now) {
+                        report.push(format!(
+                            "{} will get {} x {} of {} late, batch {} had to be brewed by {}",
+                            order.customer,
+                            fulfillment.units,
+                            order.format.lookup(),
+                            order.beer,
+                            id,
+                            brew_by.format("%Y-%m-%d %H:%M"),
+                        ));
+                    }
+                }
+            }
+        }
+        report
+    }
+
+    /// The dates the batches that are due have to be brewed by, in order.
+    pub fn brew_by_dates(&self, batches_needed: &HashMap<usize, BatchNeed>) -> Vec<String> {
+        let mut batches = batches_needed
+            .values()
+            .filter(|batch| batch.due.is_some())
+            .collect::<Vec<&BatchNeed>>();
+        batches.sort_by_key(|batch| (batch.brew_by(), batch.id));
+        batches
+            .iter()
+            .filter_map(|batch| {
+                Some(format!(
+                    "Brew batch {} of {} by {}, it is due on {}",
+                    batch.id,
+                    batch.beer.label(),
+                    batch.brew_by()?.format("%Y-%m-%d %H:%M"),
+                    batch.due?.format("%Y-%m-%d"),
+                ))
+            })
+            .collect()
+    }
+
+    /// Flag the batches that are due, but can't be brewed in time anymore, and the moments
+    /// that more of them need a fermentor than there are. A batch that is brewed on its
+    /// brew-by date keeps a fermentor busy from the start of its first fermentation step
+    /// till the end of its last one.
+    pub fn release_conflicts(
+        &self,
+        batches_needed: &HashMap<usize, BatchNeed>,
+        now: DateTime<Utc>,
+    ) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        let mut batches = batches_needed
+            .values()
+            .filter(|batch| batch.brew_by().is_some())
+            .collect::<Vec<&BatchNeed>>();
+        batches.sort_by_key(|batch| batch.id);
+        let mut windows = Vec::new();
+        for batch in batches {
+            let brew_by = batch.brew_by().unwrap();
+            if brew_by < now {
+                conflicts.push(format!(
+                    "Batch {} of {} had to be brewed by {} to be ready on {}",
+                    batch.id,
+                    batch.beer.label(),
+                    brew_by.format("%Y-%m-%d %H:%M"),
+                    batch.due.unwrap().format("%Y-%m-%d"),
+                ));
+            }
+            let mut offset = Duration::zero();
+            let mut fermenting: Option<(Duration, Duration)> = None;
+            for (step_group, interval) in batch.steps() {
+                let end = offset + interval.range().1;
+                if step_group.capability() == Capability::Fermentation {
+                    fermenting = Some(match fermenting {
+                        Some((start, _end)) => (start, end),
+                        None => (offset, end),
+                    });
+                }
+                offset = end;
+            }
+            if let Some((start, end)) = fermenting {
+                windows.push((batch, brew_by + start, brew_by + end));
+            }
+        }
+        for (batch, start, _end) in windows.iter() {
+            let fermentors = self
+                .equipments
+                .values()
+                .filter(|equipment| {
+                    equipment.capacity == batch.system && equipment.can(&Capability::Fermentation)
+                })
+                .count();
+            let competing = windows
+                .iter()
+                .filter(|(other, other_start, other_end)| {
+                    other.system == batch.system && other_start <= start && start < other_end
+                })
+                .collect::<Vec<_>>();
+            if competing.len() > fermentors {
+                let mut beers = competing
+                    .iter()
+                    .map(|(other, _start, _end)| other.beer.name.clone())
+                    .collect::<Vec<String>>();
+                beers.sort();
+                beers.dedup();
+                let conflict = format!(
+                    "{} need {} fermentors of {} on {}, there are {}",
+                    beers.join(" and "),
+                    competing.len(),
+                    batch.system.lookup(),
+                    start.format("%Y-%m-%d %H:%M"),
+                    fermentors
+                );
+                if !conflicts.contains(&conflict) {
+                    conflicts.push(conflict);
+                }
+            }
+        }
+        conflicts
+    }
+
     pub fn calculate_bottleneck_equipment(
         &self,
         acc_batches: &[(Capacity, StepGroup, Duration)],
-    ) -> Vec<(Capacity, EquipmentGroup, Duration)> {
+    ) -> Vec<(Capacity, Capability, Duration)> {
         // @TODO merge this with calculate_bottleneck_step to save time on building hashmaps and sorting them into vectors
-        let mut temp: HashMap<(Capacity, EquipmentGroup), Duration> = HashMap::new();
+        let mut temp: HashMap<(Capacity, Capability), Duration> = HashMap::new();
         for (system, step_group, duration) in acc_batches {
-            let equipment_group = step_group.equipment_group();
-            match temp.get_mut(&(system.clone(), equipment_group.clone())) {
+            let capability = step_group.capability();
+            match temp.get_mut(&(system.clone(), capability.clone())) {
                 None => {
-                    temp.insert((system.clone(), equipment_group.clone()), duration.clone());
+                    temp.insert((system.clone(), capability.clone()), *duration);
                 }
-                Some(dur) => *dur = *dur + duration.clone(),
+                Some(dur) => *dur += *duration,
             };
         }
         // sort, descending on usage
-        let mut temp_vec: Vec<(&(Capacity, EquipmentGroup), &Duration)> = temp.iter().collect();
+        let mut temp_vec: Vec<(&(Capacity, Capability), &Duration)> = temp.iter().collect();
         temp_vec.sort_by(|a, b| b.1.cmp(&a.1));
         temp_vec
             .iter()
-            .map(|((system, equipment_group), duration)| {
-                (system.clone(), equipment_group.clone(), *duration.clone())
+            .map(|((system, capability), duration)| {
+                (system.clone(), capability.clone(), **duration)
             })
             .collect()
     }
@@ -218,13 +1135,11 @@ impl Factory {
     pub fn list_suited_equipment(
         &self,
         capacity: &Capacity,
-        equipment_group: &EquipmentGroup,
+        capability: &Capability,
     ) -> Vec<&Equipment> {
         let mut out = Vec::new();
         for equipment in self.equipments.values() {
-            if equipment.capacity.volume().ge(&capacity.volume())
-                && &equipment.equipment_group == equipment_group
-            {
+            if equipment.capacity.volume().ge(&capacity.volume()) && equipment.can(capability) {
                 out.push(equipment)
             }
         }
@@ -234,33 +1149,154 @@ impl Factory {
 
     pub fn calculate_bottleneck(
         &self,
-        acc_equipment: &[(Capacity, EquipmentGroup, Duration)],
-    ) -> Vec<(Capacity, EquipmentGroup, Duration)> {
+        acc_equipment: &[(Capacity, Capability, Duration)],
+    ) -> Vec<(Capacity, Capability, Duration)> {
         let mut temp = HashMap::with_capacity(acc_equipment.len());
-        for (system, equipment_group, duration) in acc_equipment {
-            let suited = self.list_suited_equipment(system, equipment_group);
-            assert!(suited.len() > 0);
+        for (system, capability, duration) in acc_equipment {
+            let suited = self.list_suited_equipment(system, capability);
+            assert!(!suited.is_empty());
             let avg_duration = *duration / (suited.len() as i32);
-            temp.insert((system.clone(), equipment_group.clone()), avg_duration);
+            temp.insert((system.clone(), capability.clone()), avg_duration);
         }
 
-        let mut temp_vec: Vec<(&(Capacity, EquipmentGroup), &Duration)> = temp.iter().collect();
+        let mut temp_vec: Vec<(&(Capacity, Capability), &Duration)> = temp.iter().collect();
         temp_vec.sort_by(|a, b| b.1.cmp(&a.1));
 
         temp_vec
             .iter()
-            .map(|((system, equipment_group), duration)| {
-                (system.clone(), equipment_group.clone(), *duration.clone())
+            .map(|((system, capability), duration)| {
+                (system.clone(), capability.clone(), **duration)
             })
             .collect()
     }
 }
 
+/// Fill in the transfer and cleaning times that the equipment does not declare itself.
+fn apply_handling(equipment: &mut Equipment, handling: &HandlingConfig) {
+    let equipment_group = match EquipmentGroup::from_str(&handling.equipment_type) {
+        Ok(x) => x,
+        Err(_e) => panic!(
+            "{} does not appear to be a valid equipment group",
+            handling.equipment_type
+        ),
+    };
+    if equipment.equipment_group != equipment_group {
+        return;
+    }
+    if let Some(capacity) = &handling.capacity {
+        match Capacity::from_str(capacity) {
+            Ok(x) if x == equipment.capacity => {}
+            Ok(_) => return,
+            Err(_e) => panic!("{} does not appear to be a valid capacity", capacity),
+        }
+    }
+    if let (None, Some(pump_rate)) = (&equipment.pump_rate, &handling.pump_rate) {
+        match Volume::from_str(pump_rate) {
+            Ok(x) => equipment.pump_rate = Some(x),
+            Err(_e) => panic!(
+                "{} does not appear to be a valid pump rate (per minute)",
+                pump_rate
+            ),
+        }
+    }
+    if let (None, Some(cip_time)) = (&equipment.cip_time, &handling.cip_time) {
+        match Interval::from_str(cip_time) {
+            Ok(x) => equipment.cip_time = Some(x.duration()),
+            Err(_e) => panic!("{} does not appear to be a valid cip time", cip_time),
+        }
+    }
+}
+
+impl std::convert::From<&FactoryConfig> for Factory {
+    fn from(config: &FactoryConfig) -> Self {
+        let mut factory = Factory::new(&config.name);
+        for equipment_config in &config.equipment {
+            let mut equipment = Equipment::from(equipment_config);
+            // The times for a specific size go before the ones for the whole group
+            let (sized, general): (Vec<&HandlingConfig>, Vec<&HandlingConfig>) = config
+                .handling
+                .iter()
+                .partition(|handling| handling.capacity.is_some());
+            for handling in sized.into_iter().chain(general) {
+                apply_handling(&mut equipment, handling);
+            }
+            factory.equipments.insert(equipment.name.clone(), equipment);
+        }
+        for changeover_config in &config.changeovers {
+            factory
+                .changeover
+                .rules
+                .push(ChangeoverRule::from(changeover_config));
+        }
+        for segregation_config in &config.segregation {
+            factory
+                .segregation
+                .rules
+                .push(SegregationRule::from(segregation_config));
+        }
+        factory.step_definitions = config.step_definitions();
+        factory.packaging_crew = config.packaging_crew;
+        for order_config in &config.packaging_orders {
+            factory
+                .packaging_orders
+                .push(PackagingOrder::from(order_config));
+        }
+        for fleet_config in &config.keg_fleet {
+            factory.keg_fleets.push(KegFleet::from(fleet_config));
+        }
+        for stock_config in &config.inventory {
+            factory.inventory.push(Stock::from(stock_config));
+        }
+        factory.tap_list = config.tap_list.as_ref().map(TapList::from);
+        factory.forecast = config.forecast.as_ref().map(Forecast::from);
+        for order_config in &config.orders {
+            factory.order_book.push(CustomerOrder::from(order_config));
+        }
+        for release_config in &config.releases {
+            factory.releases.push(Release::from(release_config));
+        }
+        for resource_config in &config.resources {
+            factory
+                .resources
+                .push(CumulativeResource::from(resource_config));
+        }
+        for material_config in &config.materials {
+            let material = Material::from(material_config);
+            factory.materials.insert(material.name.clone(), material);
+        }
+        for yeast_config in &config.yeasts {
+            let yeast = YeastStrain::from(yeast_config);
+            factory.yeasts.insert(yeast.name.clone(), yeast);
+        }
+        for recipe_config in &config.recipes {
+            let beer = Beer::from((config, recipe_config));
+            if let Some(yeast) = &beer.yeast {
+                if !factory.yeasts.contains_key(yeast) {
+                    panic!(
+                        "{} does not appear to be a valid yeast strain for {}",
+                        yeast, beer.name
+                    );
+                }
+            }
+            factory.add_beer(beer);
+        }
+        let scaling = config
+            .scaling
+            .as_ref()
+            .map(Scaling::from)
+            .unwrap_or_default();
+        factory.scale_recipes(&scaling);
+
+        factory
+    }
+}
+
 #[cfg(test)]
 pub mod mock {
     use super::*;
     use crate::beer;
     use crate::equipment;
+    use crate::step_definition;
 
     pub fn mock_factory() -> Factory {
         let mut factory = Factory::new("loonslanding");
@@ -273,6 +1309,31 @@ pub mod mock {
         let beer = beer::mock::mock_beer();
         factory.beers.insert(beer.name.to_string(), beer);
 
+        let definition = step_definition::mock::mock_dry_hopping();
+        factory
+            .step_definitions
+            .insert(definition.name.to_string(), definition);
+
+        factory
+    }
+
+    pub fn mock_brewhouse_factory() -> Factory {
+        let mut factory = Factory::new("brewhouse");
+        let equipments = vec![
+            ("HLT", EquipmentGroup::HotLiquorTank),
+            ("Mash Tun", EquipmentGroup::MashTun),
+            ("Lauter Tun", EquipmentGroup::LauterTun),
+            ("Kettle", EquipmentGroup::Kettle),
+            ("Fermentor 1", EquipmentGroup::Fermentor),
+            ("Fermentor 2", EquipmentGroup::Fermentor),
+        ];
+        for (name, equipment_group) in equipments {
+            factory.equipments.insert(
+                name.to_string(),
+                Equipment::new(name.to_string(), Capacity::G10, equipment_group),
+            );
+        }
+
         factory
     }
 }
@@ -280,9 +1341,25 @@ pub mod mock {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::beer;
     use crate::capacity;
+    use crate::cumulative_resource::Consumption;
     use crate::equipment;
     use crate::equipment_group;
+    use crate::forecast;
+    use crate::forecast::ForecastMethod;
+    use crate::inventory;
+    use crate::maintenance;
+    use crate::maintenance::Usage;
+    use crate::order_book;
+    use crate::packaging::PackagingRate;
+    use crate::recipe;
+    use crate::recipe::Recipe;
+    use crate::release;
+    use crate::step_definition;
+    use crate::steps;
+    use crate::style;
+    use chrono::TimeZone;
 
     #[test]
     fn test_factory_new() {
@@ -290,6 +1367,21 @@ mod tests {
         assert_eq!(&factory.name, "loonslanding");
         assert_eq!(factory.equipments.len(), 1);
         assert_eq!(factory.beers.len(), 1);
+        assert_eq!(factory.step_definitions.len(), 1);
+    }
+
+    #[test]
+    fn test_factory_step_group() {
+        let factory = mock::mock_factory();
+        assert_eq!(
+            factory.step_group("primary"),
+            Some(StepGroup::PrimaryFermentation)
+        );
+        assert_eq!(
+            factory.step_group("Dry Hopping"),
+            Some(StepGroup::Custom(step_definition::mock::mock_dry_hopping()))
+        );
+        assert_eq!(factory.step_group("Cold Crash"), None);
     }
 
     #[test]
@@ -307,17 +1399,518 @@ mod tests {
             .insert(equipment_2.name.to_string(), equipment_2.clone());
         assert_eq!(factory.equipments.len(), 2);
         assert_eq!(
-            factory.list_suited_equipment(&Capacity::G10, &EquipmentGroup::CO2Tank),
+            factory.list_suited_equipment(&Capacity::G10, &Capability::Carbonation),
             Vec::<&Equipment>::new()
         );
         assert_eq!(
-            factory.list_suited_equipment(&Capacity::BBL5, &EquipmentGroup::CO2Tank),
+            factory.list_suited_equipment(&Capacity::BBL5, &Capability::Carbonation),
             Vec::<&Equipment>::new()
         );
-        let suited = factory.list_suited_equipment(&Capacity::G5, &EquipmentGroup::MashTun);
+        let suited = factory.list_suited_equipment(&Capacity::G5, &Capability::Mashing);
         assert!(
             (suited == vec![&equipment_1, &equipment_2])
                 || (suited == vec![&equipment_2, &equipment_1])
         );
     }
+
+    #[test]
+    fn test_factory_list_suited_equipment_by_capability() {
+        let mut factory = mock::mock_factory();
+        let unitank = Equipment::new(
+            "Unitank 1".to_string(),
+            capacity::mock::mock_bbl5(),
+            equipment_group::mock::mock_unitank(),
+        );
+        let brite_tank = Equipment::new(
+            "Brite Tank 1".to_string(),
+            capacity::mock::mock_bbl5(),
+            equipment_group::mock::mock_brite_tank(),
+        );
+        factory
+            .equipments
+            .insert(unitank.name.to_string(), unitank.clone());
+        factory
+            .equipments
+            .insert(brite_tank.name.to_string(), brite_tank.clone());
+        assert_eq!(
+            factory.list_suited_equipment(&Capacity::BBL5, &Capability::Fermentation),
+            vec![&unitank]
+        );
+        let suited = factory.list_suited_equipment(&Capacity::BBL5, &Capability::Carbonation);
+        assert!((suited == vec![&unitank, &brite_tank]) || (suited == vec![&brite_tank, &unitank]));
+    }
+
+    #[test]
+    fn test_factory_co2_shortfall() {
+        let mut factory = mock::mock_factory();
+        let beer = beer::mock::mock_beer();
+        let mut batches_needed = HashMap::new();
+        for id in 1..=2 {
+            batches_needed.insert(
+                id,
+                BatchNeed::new(id, &beer, Capacity::G5, Volume::BeerBarrel(2.0)),
+            );
+        }
+        assert_eq!(factory.co2_inventory(), Volume::Lb(0.0));
+        assert_eq!(factory.co2_needed(&batches_needed), Volume::Lb(6.0));
+        assert_eq!(
+            factory.co2_shortfall(&batches_needed),
+            Some(Volume::Lb(6.0))
+        );
+
+        let cylinder = Equipment::new(
+            "CO2 Tank".to_string(),
+            capacity::mock::mock_g5(),
+            equipment_group::mock::mock_co2_tank(),
+        )
+        .with_contents(Volume::Lb(5.0));
+        factory.equipments.insert(cylinder.name.clone(), cylinder);
+        assert_eq!(factory.co2_inventory(), Volume::Lb(5.0));
+        assert_eq!(
+            factory.co2_shortfall(&batches_needed),
+            Some(Volume::Lb(1.0))
+        );
+    }
+
+    #[test]
+    fn test_factory_resource_overloads() {
+        let mut factory = mock::mock_factory();
+        factory
+            .resources
+            .push(CumulativeResource::new("Walk-in Cooler".to_string(), 2));
+        let mut beer = beer::mock::mock_beer();
+        beer.recipe = beer.recipe.with_consumption(
+            StepGroup::Aging,
+            Consumption::new("Walk-in Cooler".to_string(), 1, Some(Volume::GallonUS(2.5))),
+        );
+        let mut batches_needed = HashMap::new();
+        batches_needed.insert(
+            1,
+            BatchNeed::new(1, &beer, Capacity::G5, Volume::GallonUS(5.0)),
+        );
+        assert!(factory.resource_overloads(&batches_needed).is_empty());
+        factory.resources[0].limit = 1;
+        assert_eq!(
+            factory.resource_overloads(&batches_needed),
+            vec!["Aging of batch 1 takes 2 of the Walk-in Cooler, there is only 1".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_factory_keg_shortages() {
+        let mut factory = mock::mock_factory();
+        factory
+            .keg_fleets
+            .push(KegFleet::new(PackageFormat::HalfBarrel, 1).with_dirty(1));
+        let beer = beer::mock::mock_beer();
+        let mut batch = BatchNeed::new(1, &beer, Capacity::G5, Volume::GallonUS(5.0));
+        batch.packaging.push((PackageFormat::HalfBarrel, 2));
+        let mut batches_needed = HashMap::new();
+        batches_needed.insert(1, batch);
+        let now = Utc.with_ymd_and_hms(2021, 1, 4, 8, 0, 0).unwrap();
+        assert!(factory.keg_shortages(&batches_needed, now).is_empty());
+        factory.keg_fleets[0].dirty = 0;
+        assert_eq!(
+            factory.keg_shortages(&batches_needed, now),
+            vec!["2 x 1/2 BBL are ordered, the fleet only has 1 kegs to fill".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_factory_update_maintenance() {
+        let mut factory = mock::mock_factory();
+        let name = factory.equipments.keys().next().unwrap().clone();
+        let mut rule = maintenance::mock::mock_screen_service();
+        rule.record(&Usage::new(1, Duration::hours(6)));
+        let mut counters = HashMap::new();
+        counters.insert(name.clone(), vec![rule.clone()]);
+        counters.insert("Unknown".to_string(), vec![rule.clone()]);
+        factory.update_maintenance(counters);
+        assert_eq!(
+            factory.equipments.get(&name).unwrap().maintenance,
+            vec![rule]
+        );
+    }
+
+    #[test]
+    fn test_factory_shared_equipment() {
+        let mut factory = mock::mock_brewhouse_factory();
+        for name in ["Pump 2", "Pump 1"] {
+            factory.equipments.insert(
+                name.to_string(),
+                Equipment::new(name.to_string(), Capacity::G10, EquipmentGroup::Pump),
+            );
+        }
+        let shared = factory.shared_equipment();
+        assert_eq!(shared.len(), 1);
+        let pumps = shared
+            .get(&EquipmentGroup::Pump)
+            .unwrap()
+            .iter()
+            .map(|equipment| equipment.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(pumps, vec!["Pump 1", "Pump 2"]);
+    }
+
+    #[test]
+    fn test_factory_make_to_stock() {
+        let mut factory = mock::mock_brewhouse_factory();
+        let line = Equipment::new(
+            "Canning Line".to_string(),
+            Capacity::G10,
+            equipment_group::mock::mock_packaging_line(),
+        )
+        .with_packaging_rate(PackagingRate::new(PackageFormat::Can16oz, 1200, 24));
+        factory.equipments.insert(line.name.clone(), line);
+        let beer = beer::mock::mock_brew_day_beer();
+        factory.beers.insert(beer.name.clone(), beer);
+        factory.inventory.push(inventory::mock::mock_stock());
+        let beer = factory.beers.values().next().unwrap();
+        let horizon = Duration::weeks(2);
+        let now = Utc.with_ymd_and_hms(2021, 1, 4, 8, 0, 0).unwrap();
+
+        // Two weeks of sales and the reorder point take 124 cans, or 15.5 gallons
+        let (batches_needed, unallocated) = factory.make_to_stock(horizon, &HashMap::new(), now);
+        assert!(unallocated.is_empty());
+        let mut ids = batches_needed.keys().cloned().collect::<Vec<usize>>();
+        ids.sort();
+        assert_eq!(ids, vec![1, 2]);
+        let cans = batches_needed
+            .values()
+            .flat_map(|batch| batch.packaging.iter())
+            .map(|(_format, units)| units)
+            .sum::<u32>();
+        assert_eq!(cans, 124);
+        // The 16 cans over the reorder point last a day and a half, the second batch is due
+        // when the cans of the first one are sold
+        let first = batches_needed[&1].packaging[0].1;
+        let stock = &factory.inventory[0];
+        assert_eq!(
+            batches_needed[&1].due,
+            Some(now + stock.runs_out(0).unwrap())
+        );
+        assert_eq!(
+            batches_needed[&2].due,
+            Some(now + stock.runs_out(first).unwrap())
+        );
+
+        // A batch that is in progress is counted in
+        let mut in_progress = HashMap::new();
+        in_progress.insert(
+            7,
+            BatchNeed::new(7, beer, Capacity::G10, Volume::GallonUS(10.0)),
+        );
+        assert_eq!(
+            factory.replenishment(horizon, &in_progress),
+            vec![PackagingOrder::new(
+                beer.name.clone(),
+                PackageFormat::Can16oz,
+                44
+            )]
+        );
+        let (batches_needed, _unallocated) = factory.make_to_stock(horizon, &in_progress, now);
+        assert_eq!(batches_needed.len(), 1);
+        assert_eq!(
+            batches_needed[&8].packaging,
+            vec![(PackageFormat::Can16oz, 44)]
+        );
+
+        // What is left of the batch in progress is shared by the stocks of the beer
+        factory.inventory.push(
+            Stock::new(beer.name.clone(), PackageFormat::SixthBarrel, 0).with_reorder_point(1),
+        );
+        let orders = factory.replenishment(horizon, &in_progress);
+        assert_eq!(orders.len(), 2);
+        assert!(orders[0].units > 44);
+
+        // The packages that no line fills are given back
+        factory.equipments.remove("Canning Line");
+        let (batches_needed, unallocated) = factory.make_to_stock(horizon, &in_progress, now);
+        assert!(batches_needed.is_empty());
+        assert_eq!(unallocated.len(), 2);
+    }
+
+    #[test]
+    fn test_factory_recipe_versions() {
+        let mut factory = mock::mock_brewhouse_factory();
+        let effective = Utc.with_ymd_and_hms(2021, 6, 1, 0, 0, 0).unwrap();
+        let v2 = Beer::new(
+            "foobeer 3000".to_string(),
+            style::mock::mock_blonde_ale(),
+            recipe::mock::mock_brew_day_recipe(),
+        )
+        .with_version(2)
+        .with_effective(effective);
+        factory.add_beer(v2);
+        factory.add_beer(beer::mock::mock_brew_day_beer().with_version(1));
+        factory.releases.push(release::mock::mock_release());
+        // New batches are brewed with the latest version
+        assert_eq!(factory.beers["foobeer 3000"].version, Some(2));
+        assert_eq!(factory.recipe_history["foobeer 3000"].len(), 1);
+        assert_eq!(
+            factory.beer_version("foobeer 3000", 1),
+            factory.recipe_history["foobeer 3000"].first()
+        );
+
+        // A batch in progress keeps the version it was brewed with
+        let brewed = Utc.with_ymd_and_hms(2021, 5, 20, 8, 0, 0).unwrap();
+        let beer = factory.beer_at("foobeer 3000", brewed).unwrap();
+        assert_eq!(beer.version, Some(1));
+        assert_eq!(
+            factory.beer_at("foobeer 3000", effective).unwrap().version,
+            Some(2)
+        );
+        assert_eq!(factory.beer_at("foobeer 4000", effective), None);
+        let due = Utc.with_ymd_and_hms(2021, 9, 15, 0, 0, 0).unwrap();
+        let mut in_progress = HashMap::new();
+        in_progress.insert(
+            1,
+            BatchNeed::new(1, beer, Capacity::G10, Volume::GallonUS(10.0)).with_due(due),
+        );
+        assert!(factory.brew_by_dates(&in_progress)[0]
+            .starts_with("Brew batch 1 of foobeer 3000 v1 by "));
+
+        // The new batches are brewed with the version that is in effect, not the one to come
+        for (now, version) in [(brewed, Some(1)), (effective, Some(2))] {
+            let batches_needed = factory.release_batches(&in_progress, now);
+            assert_eq!(batches_needed.len(), 2);
+            assert!(batches_needed
+                .values()
+                .all(|batch| batch.beer.version == version));
+        }
+    }
+
+    #[test]
+    fn test_factory_release_batches() {
+        let mut factory = mock::mock_brewhouse_factory();
+        let beer = beer::mock::mock_brew_day_beer();
+        factory.beers.insert(beer.name.clone(), beer);
+        factory.releases.push(release::mock::mock_release());
+        let now = Utc.with_ymd_and_hms(2021, 6, 1, 8, 0, 0).unwrap();
+        let due = Utc.with_ymd_and_hms(2021, 9, 15, 0, 0, 0).unwrap();
+
+        // Twenty gallons take two batches, and there are two fermentors
+        let batches_needed = factory.release_batches(&HashMap::new(), now);
+        assert_eq!(batches_needed.len(), 2);
+        assert!(batches_needed.values().all(|batch| batch.due == Some(due)));
+        let brew_by = batches_needed[&1].brew_by().unwrap();
+        assert_eq!(
+            factory.brew_by_dates(&batches_needed),
+            vec![
+                format!(
+                    "Brew batch 1 of foobeer 3000 by {}, it is due on 2021-09-15",
+                    brew_by.format("%Y-%m-%d %H:%M")
+                ),
+                format!(
+                    "Brew batch 2 of foobeer 3000 by {}, it is due on 2021-09-15",
+                    brew_by.format("%Y-%m-%d %H:%M")
+                ),
+            ]
+        );
+        assert!(factory.release_conflicts(&batches_needed, now).is_empty());
+
+        // A third batch doesn't fit, and it's too late for the release anyway
+        factory.releases[0].volume = Volume::GallonUS(30.0);
+        let batches_needed = factory.release_batches(&HashMap::new(), now);
+        let conflicts = factory.release_conflicts(&batches_needed, due);
+        assert_eq!(conflicts.len(), 4);
+        assert!(conflicts[0].starts_with("Batch 1 of foobeer 3000 had to be brewed by"));
+        assert!(conflicts[3].starts_with("foobeer 3000 need 3 fermentors of 10G on"));
+
+        // The batches are numbered after those in progress
+        let beer = &factory.beers["foobeer 3000"];
+        let mut in_progress = HashMap::new();
+        in_progress.insert(
+            4,
+            BatchNeed::new(4, beer, Capacity::G10, Volume::GallonUS(10.0)),
+        );
+        let mut ids = factory
+            .release_batches(&in_progress, now)
+            .into_keys()
+            .collect::<Vec<usize>>();
+        ids.sort();
+        assert_eq!(ids, vec![5, 6, 7]);
+    }
+
+    #[test]
+    fn test_factory_scale_recipes() {
+        let mut factory = mock::mock_brewhouse_factory();
+        let beer = Beer::new(
+            "foobeer 5000".to_string(),
+            style::mock::mock_blonde_ale(),
+            Recipe::new(
+                Capacity::G5,
+                Volume::GallonUS(4.0),
+                steps::mock::mock_brew_day_steps(),
+            ),
+        );
+        factory.beers.insert(beer.name.clone(), beer);
+        assert_eq!(factory.brew_systems(), vec![Capacity::G10]);
+        assert!(!factory.can_brew(&factory.beers["foobeer 5000"], &Capacity::G10));
+        let mut wishlist = HashMap::new();
+        let beer = &factory.beers["foobeer 5000"];
+        wishlist.insert(beer.name.as_str(), (beer, Volume::GallonUS(16.0)));
+        assert_eq!(
+            factory.unbrewable(&wishlist),
+            vec![
+                "16G of foobeer 5000 is wished for, there is no recipe for a system that can brew it"
+                    .to_string()
+            ]
+        );
+        assert!(factory.calculate_batches(wishlist).is_empty());
+
+        factory.scale_recipes(&Scaling::new());
+        let beer = &factory.beers["foobeer 5000"];
+        assert!(factory.can_brew(beer, &Capacity::G10));
+        assert_eq!(beer.recipe.scaled_from[&Capacity::G10], Capacity::G5);
+        let scaled = factory.scaled_recipes();
+        assert_eq!(scaled.len(), 1);
+        assert!(scaled[0].starts_with("foobeer 5000 on 10G, scaled from 5G: 8.0G, "));
+
+        // Two batches of 8 gallons on the 10 gallon system
+        let mut wishlist = HashMap::new();
+        wishlist.insert(beer.name.as_str(), (beer, Volume::GallonUS(16.0)));
+        let batches_needed = factory.calculate_batches(wishlist);
+        assert_eq!(batches_needed.len(), 2);
+        assert!(batches_needed
+            .values()
+            .all(|batch| batch.system == Capacity::G10));
+    }
+
+    #[test]
+    fn test_factory_order_batches() {
+        let mut factory = mock::mock_brewhouse_factory();
+        let beer = beer::mock::mock_brew_day_beer();
+        factory.beers.insert(beer.name.clone(), beer);
+        let corner_bar = order_book::mock::mock_customer_order();
+        let mut distributor = corner_bar.clone();
+        distributor.customer = "The Distributor".to_string();
+        distributor.format = PackageFormat::HalfBarrel;
+        distributor.units = 1;
+        distributor.delivery = Utc.with_ymd_and_hms(2021, 6, 20, 8, 0, 0).unwrap();
+        factory.order_book.push(corner_bar.clone());
+        factory.order_book.push(distributor.clone());
+        let line = Equipment::new(
+            "Kegging Line".to_string(),
+            Capacity::G10,
+            equipment_group::mock::mock_packaging_line(),
+        )
+        .with_packaging_rate(PackagingRate::new(PackageFormat::HalfBarrel, 30, 1))
+        .with_packaging_rate(PackagingRate::new(PackageFormat::SixthBarrel, 60, 1));
+        factory.equipments.insert(line.name.clone(), line);
+
+        // A half barrel doesn't fit in a batch of 10 gallons, the sixtels go in two batches
+        let now = Utc.with_ymd_and_hms(2021, 6, 2, 8, 0, 0).unwrap();
+        let (batches_needed, fulfillments) = factory.order_batches(&mut HashMap::new(), now);
+        assert_eq!(batches_needed.len(), 2);
+        assert_eq!(
+            fulfillments,
+            vec![
+                Fulfillment::new(distributor.clone(), None, 1),
+                Fulfillment::new(corner_bar.clone(), Some(1), 1),
+                Fulfillment::new(corner_bar.clone(), Some(2), 1),
+            ]
+        );
+        assert_eq!(batches_needed[&1].due, Some(corner_bar.delivery));
+        assert_eq!(
+            factory.fulfillment_report(&batches_needed, &fulfillments, now),
+            vec![
+                "Batch 1 of foobeer 3000 serves 1 x 1/6 BBL for The Corner Bar, delivered on 2021-07-01",
+                "Batch 2 of foobeer 3000 serves 1 x 1/6 BBL for The Corner Bar, delivered on 2021-07-01",
+                "The Distributor will be short 1 x 1/2 BBL of foobeer 3000 on 2021-06-20",
+            ]
+        );
+
+        // Too late to brew for the delivery
+        let report =
+            factory.fulfillment_report(&batches_needed, &fulfillments, corner_bar.delivery);
+        assert_eq!(report.len(), 5);
+        assert!(report[4].starts_with(
+            "The Corner Bar will get 1 x 1/6 BBL of foobeer 3000 late, batch 2 had to be brewed by"
+        ));
+
+        // A batch in progress serves the first sixtel, one more batch is brewed for the other
+        let beer = &factory.beers["foobeer 3000"];
+        let mut in_progress = HashMap::new();
+        in_progress.insert(
+            7,
+            BatchNeed::new(7, beer, Capacity::G10, Volume::GallonUS(10.0)),
+        );
+        let (batches_needed, fulfillments) = factory.order_batches(&mut in_progress, now);
+        assert_eq!(batches_needed.keys().collect::<Vec<&usize>>(), vec![&8]);
+        assert_eq!(
+            in_progress[&7].packaging,
+            vec![(PackageFormat::SixthBarrel, 1)]
+        );
+        assert_eq!(in_progress[&7].due, Some(corner_bar.delivery));
+        assert_eq!(
+            fulfillments,
+            vec![
+                Fulfillment::new(corner_bar.clone(), Some(7), 1),
+                Fulfillment::new(distributor, None, 1),
+                Fulfillment::new(corner_bar.clone(), Some(8), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_factory_forecast_batches() {
+        let mut factory = mock::mock_brewhouse_factory();
+        let beer = beer::mock::mock_brew_day_beer();
+        factory.beers.insert(beer.name.clone(), beer);
+        let sales = forecast::mock::mock_sales();
+        let now = Utc.with_ymd_and_hms(2021, 6, 2, 8, 0, 0).unwrap();
+        assert!(factory.forecast_batches(&sales, now).is_empty());
+
+        // 10 gallons a week, for 2 weeks
+        factory.forecast = Some(Forecast::new(ForecastMethod::MovingAverage, 2));
+        let batches_needed = factory.forecast_batches(&sales, now);
+        assert_eq!(batches_needed.len(), 2);
+        assert!(batches_needed
+            .values()
+            .all(|batch| batch.beer.name == "foobeer 3000"));
+    }
+
+    #[test]
+    fn test_factory_allocate_packaging() {
+        let mut factory = mock::mock_factory();
+        let line = Equipment::new(
+            "Kegging Line".to_string(),
+            Capacity::G5,
+            equipment_group::mock::mock_packaging_line(),
+        )
+        .with_packaging_rate(PackagingRate::new(PackageFormat::HalfBarrel, 30, 1))
+        .with_packaging_rate(PackagingRate::new(PackageFormat::SixthBarrel, 60, 4));
+        factory.equipments.insert(line.name.clone(), line);
+        let beer = factory.beers.values().next().unwrap();
+        let mut batches_needed = HashMap::new();
+        for id in 1..=2 {
+            batches_needed.insert(
+                id,
+                BatchNeed::new(id, beer, Capacity::G5, Volume::BeerBarrel(1.0)),
+            );
+        }
+        let orders = vec![
+            PackagingOrder::new(beer.name.clone(), PackageFormat::HalfBarrel, 3),
+            PackagingOrder::new(beer.name.clone(), PackageFormat::SixthBarrel, 6),
+            PackagingOrder::new("Unknown".to_string(), PackageFormat::Can16oz, 24),
+        ];
+        let unallocated = factory.allocate_packaging(&mut batches_needed, &orders);
+        assert_eq!(
+            batches_needed[&1].packaging,
+            vec![(PackageFormat::HalfBarrel, 2)]
+        );
+        // The three sixths that are left in the second batch are too few for a run
+        assert_eq!(
+            batches_needed[&2].packaging,
+            vec![(PackageFormat::HalfBarrel, 1)]
+        );
+        assert_eq!(
+            unallocated,
+            vec![
+                PackagingOrder::new(beer.name.clone(), PackageFormat::SixthBarrel, 6),
+                PackagingOrder::new("Unknown".to_string(), PackageFormat::Can16oz, 24),
+            ]
+        );
+    }
 }
@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::beer::Beer;
+use crate::config::ForecastConfig;
+use crate::package_format::PackageFormat;
+use crate::volume::Volume;
+
+/// How the sales of the past are projected on the weeks to come.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ForecastMethod {
+    /// Every week sells the average of the last weeks.
+    MovingAverage,
+    /// Every week sells what the same week sold a year ago.
+    SeasonalNaive,
+}
+
+impl ForecastMethod {
+    pub fn lookup(&self) -> &'static str {
+        match self {
+            ForecastMethod::MovingAverage => "Moving Average",
+            ForecastMethod::SeasonalNaive => "Seasonal Naive",
+        }
+    }
+}
+
+impl std::str::FromStr for ForecastMethod {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<ForecastMethod, ()> {
+        match s {
+            "Moving Average" => Ok(ForecastMethod::MovingAverage),
+            "moving average" => Ok(ForecastMethod::MovingAverage),
+            "Seasonal Naive" => Ok(ForecastMethod::SeasonalNaive),
+            "seasonal naive" => Ok(ForecastMethod::SeasonalNaive),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A line of the sales history: on a day, some volume of a beer went out in a package format.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sale {
+    pub date: NaiveDate,
+    pub beer: String,
+    pub format: PackageFormat,
+    pub volume: Volume,
+}
+
+impl Sale {
+    pub fn new(date: NaiveDate, beer: String, format: PackageFormat, volume: Volume) -> Self {
+        Self {
+            date,
+            beer,
+            format,
+            volume,
+        }
+    }
+
+    /// Reads the sales history from a csv file, see `Sale::from_csv`.
+    pub fn read_csv(file_path: String) -> std::io::Result<Vec<Sale>> {
+        let content = std::fs::read_to_string(file_path)?;
+        Sale::from_csv(&content)
+    }
+
+    /// Parses the lines `date,beer,package,volume`, like `2021-06-01,Bier,half,15.5g`. A field
+    /// with a comma in it is quoted, like `"Bier, unfiltered"`. A header line and empty lines
+    /// are skipped, a line that can't be parsed is an error.
+    pub fn from_csv(content: &str) -> std::io::Result<Vec<Sale>> {
+        let invalid =
+            |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        let mut sales = Vec::new();
+        for (number, line) in (1..).zip(content.lines()) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("date,") {
+                continue;
+            }
+            let fields = match Sale::fields(line) {
+                Some(fields) if fields.len() == 4 => fields,
+                _ => {
+                    return Err(invalid(format!(
+                        "{} on line {} does not appear to be a valid sale",
+                        line, number
+                    )))
+                }
+            };
+            let date = NaiveDate::parse_from_str(&fields[0], "%Y-%m-%d").map_err(|_e| {
+                invalid(format!(
+                    "{} on line {} does not appear to be a valid date of a sale",
+                    fields[0], number
+                ))
+            })?;
+            let format = PackageFormat::from_str(&fields[2]).map_err(|_e| {
+                invalid(format!(
+                    "{} on line {} does not appear to be a valid package format of a sale",
+                    fields[2], number
+                ))
+            })?;
+            // A weight parses as well, but there is no telling how much beer that is
+            let volume = match Volume::from_str(&fields[3]) {
+                Ok(Volume::Lb(_)) | Err(_) => {
+                    return Err(invalid(format!(
+                        "{} on line {} does not appear to be a valid volume of a sale",
+                        fields[3], number
+                    )))
+                }
+                Ok(volume) => volume,
+            };
+            sales.push(Sale::new(date, fields[1].clone(), format, volume));
+        }
+        Ok(sales)
+    }
+
+    /// Splits a csv line in its fields. A quoted field may hold commas, and a quote is written
+    /// twice inside of it. A quote that is not closed makes the line invalid.
+    fn fields(line: &str) -> Option<Vec<String>> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' if quoted => quoted = false,
+                '"' if field.trim().is_empty() => {
+                    field.clear();
+                    quoted = true;
+                }
+                ',' if !quoted => {
+                    fields.push(field.trim().to_string());
+                    field.clear();
+                }
+                c => field.push(c),
+            }
+        }
+        if quoted {
+            return None;
+        }
+        fields.push(field.trim().to_string());
+        Some(fields)
+    }
+}
+
+/**
+ * The demand for every beer over the next `weeks` weeks, projected from the sales history.
+ * The moving average looks back `window` weeks, the seasonal naive method a year (52 weeks, so
+ * that the weekdays line up).
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Forecast {
+    pub method: ForecastMethod,
+    pub weeks: u32,
+    pub window: u32,
+}
+
+impl Forecast {
+    pub fn new(method: ForecastMethod, weeks: u32) -> Self {
+        Self {
+            method,
+            weeks,
+            window: 4,
+        }
+    }
+
+    pub fn with_window(mut self, window: u32) -> Self {
+        assert!(window > 0);
+        self.window = window;
+        self
+    }
+
+    /// The liters of every beer sold in the week that starts on a day.
+    fn sold(sales: &[Sale], from: NaiveDate) -> HashMap<&str, f32> {
+        let till = from + Duration::weeks(1);
+        let mut sold = HashMap::new();
+        for sale in sales
+            .iter()
+            .filter(|sale| sale.date >= from && sale.date < till)
+        {
+            if let Volume::Liter(liter) = sale.volume.to_liter() {
+                *sold.entry(sale.beer.as_str()).or_insert(0.0) += liter;
+            }
+        }
+        sold
+    }
+
+    /// The demand of every beer that was sold, for each of the next weeks.
+    pub fn demand(&self, sales: &[Sale], now: DateTime<Utc>) -> HashMap<String, Vec<Volume>> {
+        let today = now.date_naive();
+        let mut demand: HashMap<String, Vec<Volume>> = HashMap::new();
+        for sale in sales.iter() {
+            demand
+                .entry(sale.beer.clone())
+                .or_insert_with(|| vec![Volume::Liter(0.0); self.weeks as usize]);
+        }
+        match self.method {
+            ForecastMethod::MovingAverage => {
+                let mut average: HashMap<&str, f32> = HashMap::new();
+                for week in 1..=self.window {
+                    let from = today - Duration::weeks(week as i64);
+                    for (beer, liter) in Forecast::sold(sales, from) {
+                        *average.entry(beer).or_insert(0.0) += liter / self.window as f32;
+                    }
+                }
+                for (beer, weeks) in demand.iter_mut() {
+                    let liter = average.get(beer.as_str()).cloned().unwrap_or(0.0);
+                    weeks.fill(Volume::Liter(liter));
+                }
+            }
+            ForecastMethod::SeasonalNaive => {
+                for week in 0..self.weeks {
+                    let from = today - Duration::weeks(52) + Duration::weeks(week as i64);
+                    for (beer, liter) in Forecast::sold(sales, from) {
+                        demand.get_mut(beer).unwrap()[week as usize] = Volume::Liter(liter);
+                    }
+                }
+            }
+        }
+        demand
+    }
+
+    /// The production orders for `Factory::calculate_batches`: the demand over all the weeks,
+    /// for the beers that are brewed here. A beer that was sold, but isn't brewed (anymore),
+    /// is left out.
+    pub fn wishlist<'a>(
+        &self,
+        sales: &[Sale],
+        beers: &'a HashMap<String, Beer>,
+        now: DateTime<Utc>,
+    ) -> HashMap<&'a str, (&'a Beer, Volume)> {
+        let mut wishlist = HashMap::new();
+        for (name, weeks) in self.demand(sales, now) {
+            let liter = weeks.iter().map(Volume::liters).sum::<f32>();
+            if let Some(beer) = beers.get(&name) {
+                if liter > 0.0 {
+                    wishlist.insert(beer.name.as_str(), (beer, Volume::Liter(liter)));
+                }
+            }
+        }
+        wishlist
+    }
+}
+
+impl std::convert::From<&ForecastConfig> for Forecast {
+    fn from(config: &ForecastConfig) -> Self {
+        let method = match ForecastMethod::from_str(&config.method) {
+            Ok(x) => x,
+            Err(_e) => panic!(
+                "{} does not appear to be a valid forecast method",
+                config.method
+            ),
+        };
+        let forecast = Forecast::new(method, config.weeks);
+        match config.window {
+            Some(0) => panic!("0 does not appear to be a valid window of weeks to average"),
+            Some(window) => forecast.with_window(window),
+            None => forecast,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_sales() -> Vec<Sale> {
+        Sale::from_csv(
+            "date,beer,package,volume
+            2020-06-03,foobeer 3000,half,15.5g
+            2020-06-10,foobeer 3000,half,31g
+            2021-05-12,foobeer 3000,sixtel,10g
+            2021-05-19,foobeer 3000,sixtel,20g
+            2021-05-26,foobeer 3000,sixtel,10g
+            2021-05-26,guest beer,can,2g",
+        )
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+    use chrono::TimeZone;
+
+    use crate::beer;
+
+    fn liter(volume: &Volume) -> f32 {
+        match volume.to_liter() {
+            Volume::Liter(liter) => liter,
+            _ => panic!("Should not happen"),
+        }
+    }
+
+    #[test]
+    fn test_sale_from_csv() {
+        let sales = mock::mock_sales();
+        assert_eq!(sales.len(), 6);
+        assert_eq!(
+            sales[0],
+            Sale::new(
+                NaiveDate::from_ymd_opt(2020, 6, 3).unwrap(),
+                "foobeer 3000".to_string(),
+                PackageFormat::HalfBarrel,
+                Volume::GallonUS(15.5)
+            )
+        );
+        let sales = Sale::from_csv(r#"2021-06-01,"Bier, ""unfiltered""",can,3g"#).unwrap();
+        assert_eq!(sales[0].beer, r#"Bier, "unfiltered""#);
+    }
+
+    #[test]
+    fn test_sale_from_invalid_csv() {
+        let error = Sale::from_csv("date,beer,package,volume\n2021-06-01,Bier,can").unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "2021-06-01,Bier,can on line 2 does not appear to be a valid sale"
+        );
+        let error = Sale::from_csv("2021-06-01,Bier,crate,3g").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "crate on line 1 does not appear to be a valid package format of a sale"
+        );
+        assert!(Sale::from_csv(r#"2021-06-01,"Bier,can,3g"#).is_err());
+        let error = Sale::from_csv("2021-06-01,Bier,can,5lb").unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "5lb on line 1 does not appear to be a valid volume of a sale"
+        );
+    }
+
+    #[test]
+    fn test_forecast_moving_average() {
+        let sales = mock::mock_sales();
+        let now = Utc.with_ymd_and_hms(2021, 6, 2, 8, 0, 0).unwrap();
+        let demand = Forecast::new(ForecastMethod::MovingAverage, 2).demand(&sales, now);
+        // 40 gallons over the last 4 weeks
+        assert_eq!(demand["foobeer 3000"].len(), 2);
+        for week in demand["foobeer 3000"].iter() {
+            assert_approx_eq!(liter(week), liter(&Volume::GallonUS(10.0)), 0.01);
+        }
+        assert_eq!(demand["guest beer"].len(), 2);
+    }
+
+    #[test]
+    fn test_forecast_seasonal_naive() {
+        let sales = mock::mock_sales();
+        let now = Utc.with_ymd_and_hms(2021, 6, 2, 8, 0, 0).unwrap();
+        let demand = Forecast::new(ForecastMethod::SeasonalNaive, 3).demand(&sales, now);
+        assert_eq!(
+            demand["foobeer 3000"],
+            vec![
+                Volume::GallonUS(15.5).to_liter(),
+                Volume::GallonUS(31.0).to_liter(),
+                Volume::Liter(0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forecast_wishlist() {
+        let sales = mock::mock_sales();
+        let now = Utc.with_ymd_and_hms(2021, 6, 2, 8, 0, 0).unwrap();
+        let beer = beer::mock::mock_brew_day_beer();
+        let mut beers = HashMap::new();
+        beers.insert(beer.name.clone(), beer);
+        let forecast = Forecast::new(ForecastMethod::MovingAverage, 3).with_window(2);
+        let wishlist = forecast.wishlist(&sales, &beers, now);
+        // The guest beer isn't brewed here
+        assert_eq!(wishlist.len(), 1);
+        // 30 gallons over the last 2 weeks, for 3 weeks
+        let (_, volume) = &wishlist["foobeer 3000"];
+        assert_approx_eq!(liter(volume), liter(&Volume::GallonUS(45.0)), 0.01);
+    }
+
+    #[test]
+    fn test_forecast_from_config() {
+        let config: ForecastConfig =
+            serde_json::from_str(r#"{"method": "seasonal naive", "weeks": 8, "window": 6}"#)
+                .unwrap();
+        assert_eq!(
+            Forecast::from(&config),
+            Forecast::new(ForecastMethod::SeasonalNaive, 8).with_window(6)
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use chrono::Duration;
 
+const DELTA_FOR_MINUTES_IN_MINUTES: i64 = 5;
 const DELTA_FOR_HOURS_IN_MINUTES: i64 = 30;
 const DELTA_FOR_DAYS_IN_HOURS: i64 = 8;
 const DELTA_FOR_WEEKS_IN_DAYS: i64 = 4;
@@ -7,6 +8,7 @@ const DELTA_FOR_MONTHS_IN_DAYS: i64 = 7;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Interval {
+    Minutes(u8),
     Hours(u8),
     Days(u8),
     Weeks(u8),
@@ -16,6 +18,7 @@ pub enum Interval {
 impl Interval {
     pub fn lookup(&self) -> String {
         match self {
+            Interval::Minutes(x) => format!("{}min", x),
             Interval::Hours(x) => format!("{}h", x),
             Interval::Days(x) => format!("{}d", x),
             Interval::Weeks(x) => format!("{}w", x),
@@ -24,6 +27,7 @@ impl Interval {
     }
     pub fn duration(&self) -> Duration {
         match self {
+            Interval::Minutes(x) => Duration::minutes(*x as i64),
             Interval::Hours(x) => Duration::hours(*x as i64),
             Interval::Days(x) => Duration::days(*x as i64),
             Interval::Weeks(x) => Duration::days(*x as i64 * 7),
@@ -31,9 +35,44 @@ impl Interval {
         }
     }
 
+    /// The interval that comes closest to the duration, in the smallest unit that can hold it.
+    /// A duration that doesn't fit in 255 months panics.
+    pub fn approximate(duration: Duration) -> Interval {
+        let seconds = duration.num_seconds().max(60);
+        let minutes = (seconds + 30) / 60;
+        if minutes <= u8::MAX as i64 {
+            return Interval::Minutes(minutes as u8);
+        }
+        let hours = (seconds + 1800) / 3600;
+        if hours <= u8::MAX as i64 {
+            return Interval::Hours(hours as u8);
+        }
+        let days = (seconds + 43200) / 86400;
+        if days <= u8::MAX as i64 {
+            return Interval::Days(days as u8);
+        }
+        let weeks = (days + 3) / 7;
+        if weeks <= u8::MAX as i64 {
+            return Interval::Weeks(weeks as u8);
+        }
+        let months = (days + 15) / 30;
+        if months <= u8::MAX as i64 {
+            return Interval::Months(months as u8);
+        }
+        panic!(
+            "{} days does not appear to be a valid duration for an interval",
+            days
+        );
+    }
+
     pub fn range(&self) -> (Duration, Duration) {
         let d = self.duration();
         match self {
+            Interval::Minutes(_x) => {
+                // A step of a few minutes can't take less than no time at all
+                let delta = Duration::minutes(DELTA_FOR_MINUTES_IN_MINUTES);
+                ((d - delta).max(Duration::zero()), d + delta)
+            }
             Interval::Hours(_x) => {
                 let delta = Duration::minutes(DELTA_FOR_HOURS_IN_MINUTES);
                 (d - delta, d + delta)
@@ -58,6 +97,11 @@ impl std::str::FromStr for Interval {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Interval, ()> {
+        if s.len() > 3 && s.ends_with("min") {
+            if let Ok(duration) = s[0..s.len() - 3].parse() {
+                return Ok(Interval::Minutes(duration));
+            }
+        }
         if s.len() > 1 {
             if let Ok(duration) = s[0..s.len() - 1].parse() {
                 match s.chars().last().unwrap() {
@@ -76,6 +120,10 @@ impl std::str::FromStr for Interval {
 #[cfg(test)]
 mod mock {
     use super::*;
+    pub fn minutes() -> Interval {
+        Interval::Minutes(45)
+    }
+
     pub fn hours() -> Interval {
         Interval::Hours(5)
     }
@@ -99,6 +147,7 @@ mod tests {
 
     #[test]
     fn test_interval_lookup() {
+        assert_eq!(Interval::Minutes(8).lookup(), "8min");
         assert_eq!(Interval::Hours(8).lookup(), "8h");
         assert_eq!(Interval::Days(8).lookup(), "8d");
         assert_eq!(Interval::Weeks(8).lookup(), "8w");
@@ -107,6 +156,7 @@ mod tests {
 
     #[test]
     fn test_interval_parse() {
+        assert_eq!("8min".parse(), Ok(Interval::Minutes(8)));
         assert_eq!("8h".parse(), Ok(Interval::Hours(8)));
         assert_eq!("8d".parse(), Ok(Interval::Days(8)));
         assert_eq!("8w".parse(), Ok(Interval::Weeks(8)));
@@ -115,6 +165,7 @@ mod tests {
 
     #[test]
     fn test_interval_duration() {
+        assert_eq!(Interval::Minutes(8).duration(), Duration::seconds(8 * 60));
         assert_eq!(Interval::Hours(8).duration(), Duration::seconds(8 * 3600));
         assert_eq!(
             Interval::Days(8).duration(),
@@ -130,8 +181,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_interval_approximate() {
+        assert_eq!(
+            Interval::approximate(Duration::minutes(90)),
+            Interval::Minutes(90)
+        );
+        assert_eq!(
+            Interval::approximate(Duration::minutes(400)),
+            Interval::Hours(7)
+        );
+        assert_eq!(
+            Interval::approximate(Duration::hours(300)),
+            Interval::Days(13)
+        );
+        assert_eq!(
+            Interval::approximate(Duration::days(400)),
+            Interval::Weeks(57)
+        );
+        assert_eq!(
+            Interval::approximate(Duration::weeks(300)),
+            Interval::Months(70)
+        );
+    }
+
     #[test]
     fn test_interval_range() {
+        assert_eq!(
+            Interval::Minutes(3).range(),
+            (Duration::zero(), Duration::minutes(8))
+        );
+        assert_eq!(
+            mock::minutes().range(),
+            (Duration::minutes(40), Duration::minutes(50))
+        );
         assert_eq!(
             mock::hours().range(),
             (
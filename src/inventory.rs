@@ -0,0 +1,202 @@
+use std::str::FromStr;
+
+use chrono::Duration;
+
+use crate::config::{DepletionConfig, StockConfig};
+use crate::package_format::PackageFormat;
+
+/// How many packages a sales channel, like the taproom or distribution, goes through per week.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Depletion {
+    pub channel: String,
+    pub per_week: f32,
+}
+
+impl Depletion {
+    pub fn new(channel: String, per_week: f32) -> Self {
+        Self { channel, per_week }
+    }
+}
+
+impl std::convert::From<&DepletionConfig> for Depletion {
+    fn from(config: &DepletionConfig) -> Self {
+        Depletion::new(String::from(&config.channel), config.per_week)
+    }
+}
+
+/**
+ * The finished goods of a beer in a package format. The stock goes down with the depletion of
+ * every sales channel, and should not drop below the reorder point, nor below the packages that
+ * are sold in `days_of_cover` days, whichever is more.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stock {
+    pub beer: String,
+    pub format: PackageFormat,
+    pub on_hand: u32,
+    pub depletion: Vec<Depletion>,
+    pub reorder_point: u32,
+    pub days_of_cover: Option<u32>,
+}
+
+impl Stock {
+    pub fn new(beer: String, format: PackageFormat, on_hand: u32) -> Self {
+        Self {
+            beer,
+            format,
+            on_hand,
+            depletion: Vec::new(),
+            reorder_point: 0,
+            days_of_cover: None,
+        }
+    }
+
+    pub fn with_depletion(mut self, depletion: Depletion) -> Self {
+        self.depletion.push(depletion);
+        self
+    }
+
+    pub fn with_reorder_point(mut self, reorder_point: u32) -> Self {
+        self.reorder_point = reorder_point;
+        self
+    }
+
+    pub fn with_days_of_cover(mut self, days_of_cover: u32) -> Self {
+        self.days_of_cover = Some(days_of_cover);
+        self
+    }
+
+    /// How many packages are sold per day, over all the channels.
+    pub fn per_day(&self) -> f32 {
+        self.depletion
+            .iter()
+            .map(|depletion| depletion.per_week)
+            .sum::<f32>()
+            / 7.0
+    }
+
+    /// How many packages are sold in a period.
+    pub fn sold(&self, period: Duration) -> u32 {
+        (self.per_day() * period.num_seconds() as f32 / 86400.0).ceil() as u32
+    }
+
+    /// The least that should be on hand.
+    pub fn safety_stock(&self) -> u32 {
+        match self.days_of_cover {
+            Some(days) => self
+                .reorder_point
+                .max(self.sold(Duration::days(days as i64))),
+            None => self.reorder_point,
+        }
+    }
+
+    /// How long the stock lasts, with the `incoming` packages, before it drops below the safety
+    /// stock. A stock that is not sold never runs out.
+    pub fn runs_out(&self, incoming: u32) -> Option<Duration> {
+        let per_day = self.per_day();
+        if per_day <= 0.0 {
+            return None;
+        }
+        let spare = (self.on_hand + incoming).saturating_sub(self.safety_stock());
+        Some(Duration::seconds((spare as f32 / per_day * 86400.0) as i64))
+    }
+
+    /// How long the packages on hand last. A stock that is not sold lasts for good.
+    pub fn lasts(&self) -> Option<Duration> {
+        let per_day = self.per_day();
+        if per_day <= 0.0 {
+            return None;
+        }
+        Some(Duration::seconds(
+            (self.on_hand as f32 / per_day * 86400.0) as i64,
+        ))
+    }
+
+    /// How many packages should be made, on top of the `incoming` ones that are in progress,
+    /// to stay above the safety stock till the end of the horizon.
+    pub fn shortfall(&self, horizon: Duration, incoming: u32) -> u32 {
+        (self.safety_stock() + self.sold(horizon)).saturating_sub(self.on_hand + incoming)
+    }
+}
+
+impl std::convert::From<&StockConfig> for Stock {
+    fn from(config: &StockConfig) -> Self {
+        let format = match PackageFormat::from_str(&config.format) {
+            Ok(x) => x,
+            Err(_e) => panic!(
+                "{} does not appear to be a valid package format for the stock of {}",
+                config.format, config.beer
+            ),
+        };
+        let mut stock = Stock::new(String::from(&config.beer), format, config.on_hand)
+            .with_reorder_point(config.reorder_point);
+        if let Some(days_of_cover) = config.days_of_cover {
+            stock = stock.with_days_of_cover(days_of_cover);
+        }
+        for depletion_config in &config.depletion {
+            stock = stock.with_depletion(Depletion::from(depletion_config));
+        }
+        stock
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use crate::package_format;
+
+    pub fn mock_stock() -> Stock {
+        Stock::new(
+            "foobeer 3000".to_string(),
+            package_format::mock::mock_can(),
+            40,
+        )
+        .with_depletion(Depletion::new("taproom".to_string(), 42.0))
+        .with_depletion(Depletion::new("distribution".to_string(), 28.0))
+        .with_reorder_point(24)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stock_safety_stock() {
+        let stock = mock::mock_stock();
+        assert_eq!(stock.per_day(), 10.0);
+        assert_eq!(stock.safety_stock(), 24);
+        assert_eq!(stock.with_days_of_cover(3).safety_stock(), 30);
+    }
+
+    #[test]
+    fn test_stock_shortfall() {
+        let stock = mock::mock_stock();
+        assert_eq!(stock.shortfall(Duration::weeks(2), 0), 124);
+        assert_eq!(stock.shortfall(Duration::weeks(2), 80), 44);
+        assert_eq!(stock.shortfall(Duration::days(1), 0), 0);
+    }
+
+    #[test]
+    fn test_stock_runs_out() {
+        let stock = mock::mock_stock();
+        // 16 cans over the reorder point, at 10 a day
+        assert_eq!(stock.runs_out(0), Some(Duration::seconds(138240)));
+        assert_eq!(stock.runs_out(24), Some(Duration::seconds(345600)));
+        assert_eq!(stock.lasts(), Some(Duration::seconds(345600)));
+        let unsold = Stock::new("foobeer 3000".to_string(), stock.format, 0);
+        assert_eq!(unsold.runs_out(0), None);
+        assert_eq!(unsold.lasts(), None);
+    }
+
+    #[test]
+    fn test_stock_from_config() {
+        let config: StockConfig = serde_json::from_str(
+            r#"{"beer": "foobeer 3000", "format": "can", "on_hand": 40, "reorder_point": 24,
+                "depletion": [{"channel": "taproom", "per_week": 42},
+                              {"channel": "distribution", "per_week": 28}]}"#,
+        )
+        .unwrap();
+        assert_eq!(Stock::from(&config), mock::mock_stock());
+    }
+}
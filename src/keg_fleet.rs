@@ -0,0 +1,168 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::config::KegFleetConfig;
+use crate::interval::Interval;
+use crate::package_format::PackageFormat;
+
+/**
+ * The kegs of one size that the brewery owns. Some are clean and on hand, some are dirty and
+ * waiting for the keg washer, and the rest are out at the accounts. Every week `return_rate` of
+ * the kegs at the accounts comes back, and a keg that comes back is clean `wash_time` later.
+ * A keg that is filled leaves the building, the planner doesn't count on it coming back.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct KegFleet {
+    pub format: PackageFormat,
+    pub on_hand: u32,
+    pub at_accounts: u32,
+    pub dirty: u32,
+    pub return_rate: f32,
+    pub wash_time: Duration,
+}
+
+impl KegFleet {
+    pub fn new(format: PackageFormat, on_hand: u32) -> Self {
+        assert!(format.is_keg());
+        Self {
+            format,
+            on_hand,
+            at_accounts: 0,
+            dirty: 0,
+            return_rate: 0.0,
+            wash_time: Duration::days(1),
+        }
+    }
+
+    pub fn with_dirty(mut self, dirty: u32) -> Self {
+        self.dirty = dirty;
+        self
+    }
+
+    /// The kegs at the accounts, and the share of them that comes back every week.
+    pub fn with_returns(mut self, at_accounts: u32, return_rate: f32) -> Self {
+        self.at_accounts = at_accounts;
+        self.return_rate = return_rate;
+        self
+    }
+
+    pub fn with_wash_time(mut self, wash_time: Duration) -> Self {
+        self.wash_time = wash_time;
+        self
+    }
+
+    /// How many kegs the brewery owns.
+    pub fn size(&self) -> u32 {
+        self.on_hand + self.dirty + self.at_accounts
+    }
+
+    /// The moments that the number of clean kegs in the building goes up, from `now` on,
+    /// with the number of clean kegs from then on.
+    pub fn availability(&self, now: DateTime<Utc>) -> Vec<(DateTime<Utc>, u32)> {
+        let mut availability = vec![(now, self.on_hand)];
+        let mut clean = self.on_hand;
+        if self.dirty > 0 {
+            clean += self.dirty;
+            availability.push((now + self.wash_time, clean));
+        }
+        if self.return_rate > 0.0 {
+            let per_week = self.at_accounts as f32 * self.return_rate;
+            for returned in 1..=self.at_accounts {
+                let weeks = returned as f32 / per_week;
+                let back = now + Duration::seconds((weeks * 604800.0).ceil() as i64);
+                availability.push((back + self.wash_time, clean + returned));
+            }
+        }
+        availability
+    }
+
+    /// The most clean kegs that are projected to be in the building, from `now` on.
+    pub fn most(&self, now: DateTime<Utc>) -> u32 {
+        self.availability(now)
+            .iter()
+            .map(|(_moment, kegs)| *kegs)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// How many clean kegs are projected to be in the building at a moment.
+    pub fn clean_at(&self, now: DateTime<Utc>, at: DateTime<Utc>) -> u32 {
+        self.availability(now)
+            .iter()
+            .filter(|(moment, _kegs)| moment <= &at)
+            .map(|(_moment, kegs)| *kegs)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl std::convert::From<&KegFleetConfig> for KegFleet {
+    fn from(config: &KegFleetConfig) -> Self {
+        let format = match PackageFormat::from_str(&config.format) {
+            Ok(x) if x.is_keg() => x,
+            _ => panic!("{} does not appear to be a valid keg size", config.format),
+        };
+        let mut fleet = KegFleet::new(format, config.on_hand)
+            .with_dirty(config.dirty)
+            .with_returns(config.at_accounts, config.return_rate);
+        if let Some(wash_time) = &config.wash_time {
+            fleet = match Interval::from_str(wash_time) {
+                Ok(x) => fleet.with_wash_time(x.duration()),
+                Err(_e) => panic!(
+                    "{} does not appear to be a valid wash time for the {} kegs",
+                    wash_time, config.format
+                ),
+            };
+        }
+        fleet
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use crate::package_format;
+
+    pub fn mock_keg_fleet() -> KegFleet {
+        KegFleet::new(package_format::mock::mock_half_barrel(), 4)
+            .with_dirty(2)
+            .with_returns(10, 0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_keg_fleet_availability() {
+        let fleet = mock::mock_keg_fleet();
+        assert_eq!(fleet.size(), 16);
+        let now = Utc.with_ymd_and_hms(2021, 1, 4, 8, 0, 0).unwrap();
+        let availability = fleet.availability(now);
+        assert_eq!(availability.len(), 12);
+        assert_eq!(availability[0], (now, 4));
+        assert_eq!(availability[1], (now + Duration::days(1), 6));
+        assert_eq!(availability[11], (now + Duration::days(15), 16));
+        assert_eq!(fleet.clean_at(now, now), 4);
+        assert_eq!(fleet.clean_at(now, now + Duration::days(1)), 6);
+        // Half of the ten kegs at the accounts is back after a week, and washed a day later
+        assert_eq!(fleet.clean_at(now, now + Duration::days(8)), 11);
+        assert_eq!(fleet.clean_at(now, now + Duration::weeks(10)), 16);
+        assert_eq!(fleet.most(now), 16);
+        // Without returns, the kegs at the accounts are never counted on
+        assert_eq!(fleet.with_returns(10, 0.0).most(now), 6);
+    }
+
+    #[test]
+    fn test_keg_fleet_from_config() {
+        let config: KegFleetConfig = serde_json::from_str(
+            r#"{"format": "half", "on_hand": 4, "at_accounts": 10, "dirty": 2,
+                "return_rate": 0.5, "wash_time": "1d"}"#,
+        )
+        .unwrap();
+        assert_eq!(KegFleet::from(&config), mock::mock_keg_fleet());
+    }
+}
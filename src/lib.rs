@@ -1,20 +1,41 @@
 pub mod action;
 pub mod batchneed;
 pub mod beer;
+pub mod capability;
 pub mod capacity;
+pub mod carbonation_method;
+pub mod changeover;
+pub mod clean_level;
 pub mod config;
+pub mod cumulative_resource;
+pub mod downtime;
 pub mod equipment;
 pub mod equipment_group;
 pub mod factory;
+pub mod forecast;
 pub mod interval;
+pub mod inventory;
+pub mod keg_fleet;
+pub mod maintenance;
+pub mod material;
+pub mod order_book;
+pub mod package_format;
+pub mod packaging;
 pub mod plan;
 pub mod recipe;
+pub mod release;
+pub mod scaling;
+pub mod segregation;
+pub mod step_definition;
 pub mod step_group;
 pub mod steps;
 pub mod style;
+pub mod tap_list;
 pub mod r#type;
+pub mod visit;
 pub mod volume;
 pub mod work;
+pub mod yeast;
 
 // #[cfg(test)]
 // mod tests {
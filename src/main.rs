@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
 use clap::Parser;
 
 use beertime::beer::Beer;
@@ -8,7 +9,10 @@ use beertime::config::Config;
 use beertime::equipment::Equipment;
 use beertime::equipment_group::EquipmentGroup;
 use beertime::factory::Factory;
+use beertime::forecast::Sale;
 use beertime::interval::Interval;
+use beertime::keg_fleet::KegFleet;
+use beertime::package_format::PackageFormat;
 use beertime::plan::Plan;
 use beertime::recipe::Recipe;
 use beertime::steps::Steps;
@@ -42,18 +46,34 @@ fn load_equipment(factory: &mut Factory) {
     }
     for i in 0..2 {
         eqs.push(Equipment::new(
-            format!("CO2 Tank 5Lb G10 {}", i + 1),
+            format!("Brite Tank 15G G10 {}", i + 1),
             Capacity::G10,
-            EquipmentGroup::CO2Tank,
+            EquipmentGroup::BriteTank,
         ));
     }
-    for i in 0..14 {
-        eqs.push(Equipment::new(
-            format!("Keg 5G {}", i + 1),
-            Capacity::G10,
-            EquipmentGroup::Keg,
-        ));
+    for i in 0..2 {
+        eqs.push(
+            Equipment::new(
+                format!("CO2 Tank 5Lb G10 {}", i + 1),
+                Capacity::G10,
+                EquipmentGroup::CO2Tank,
+            )
+            .with_contents(Volume::Lb(5.0)),
+        );
     }
+    eqs.push(Equipment::new(
+        "Transfer Pump".to_string(),
+        Capacity::G10,
+        EquipmentGroup::Pump,
+    ));
+    eqs.push(Equipment::new(
+        "Plate Chiller".to_string(),
+        Capacity::G10,
+        EquipmentGroup::HeatExchanger,
+    ));
+    factory
+        .keg_fleets
+        .push(KegFleet::new(PackageFormat::SixthBarrel, 14));
     for eq in eqs {
         factory.equipments.insert(eq.name.to_string(), eq);
     }
@@ -259,7 +279,7 @@ fn load_recipies(factory: &mut Factory) {
     }
 }
 
-fn wishlist(factory: &Factory) -> HashMap<&'static str, (&Beer, Volume)> {
+fn wishlist(factory: &Factory, now: DateTime<Utc>) -> HashMap<&'static str, (&Beer, Volume)> {
     let config = vec![
         ("Bier", Volume::GallonUS(30.0)),
         ("Anti-Scurvy Elixir", Volume::GallonUS(70.0)),
@@ -267,7 +287,7 @@ fn wishlist(factory: &Factory) -> HashMap<&'static str, (&Beer, Volume)> {
     ];
     let mut wishlist = HashMap::with_capacity(config.len());
     for (name, volume) in config {
-        wishlist.insert(name, (factory.beers.get(name).unwrap(), volume));
+        wishlist.insert(name, (factory.beer_at(name, now).unwrap(), volume));
     }
     wishlist
 }
@@ -283,6 +303,9 @@ struct Args {
     /// File name of the factory definition toml file
     #[clap(short)]
     factory_definition_file: String,
+    /// File name of the sales history .csv file, to forecast the demand from
+    #[clap(short)]
+    sales_file: Option<String>,
 }
 
 fn main() {
@@ -293,23 +316,105 @@ fn main() {
         Ok(conf) => {
             println!("Factory Name: {}", conf.factory.name);
             println!("Factory settings: {:?}", conf.factory);
+            let factory = Factory::from(&conf.factory);
+            for scaled in factory.scaled_recipes() {
+                println!("{}", scaled);
+            }
+            if let Some(sales_file) = args.sales_file {
+                let sales = match Sale::read_csv(sales_file) {
+                    Ok(sales) => sales,
+                    Err(e) => panic!("Unable to read the sales history: {}", e),
+                };
+                if let Some(forecast) = &factory.forecast {
+                    let now = chrono::offset::Utc::now();
+                    for (name, (_beer, volume)) in forecast.wishlist(&sales, &factory.beers, now) {
+                        println!(
+                            "Forecast for {} over {} weeks: {}",
+                            name,
+                            forecast.weeks,
+                            volume.to_gallon_us().lookup()
+                        );
+                    }
+                }
+            }
         }
         _ => panic!("Unable to read configuration file. Does it exist and in proper format?"),
     }
     if false {
         let mut factory = Factory::new("Loons Landing");
         load(&mut factory);
-        let wishlist = wishlist(&factory);
-        let batches_needed = factory.calculate_batches(wishlist);
+        let now = chrono::offset::Utc::now();
+        let wishlist = wishlist(&factory, now);
+        for beer in factory.unbrewable(&wishlist) {
+            println!("{}", beer);
+        }
+        let mut batches_needed = factory.calculate_batches(wishlist);
         assert_eq!(batches_needed.len(), 19);
+        for order in factory.allocate_packaging(&mut batches_needed, &factory.packaging_orders) {
+            println!(
+                "No batch left for {} x {} of {}",
+                order.units,
+                order.format.lookup(),
+                order.beer
+            );
+        }
+        let (stocked, unstocked) =
+            factory.make_to_stock(chrono::Duration::weeks(8), &batches_needed, now);
+        for order in unstocked {
+            println!(
+                "No batch left to stock {} x {} of {}",
+                order.units,
+                order.format.lookup(),
+                order.beer
+            );
+        }
+        batches_needed.extend(stocked);
+        if let Some(shortfall) = factory.co2_shortfall(&batches_needed) {
+            println!("Not enough CO2 in the cylinders, {} short", shortfall);
+        }
+        for overload in factory.resource_overloads(&batches_needed) {
+            println!("{}", overload);
+        }
         let most_needed_steps = factory.calculate_bottleneck_step(&batches_needed);
         let most_needed_equipment =
             factory.calculate_bottleneck_equipment(most_needed_steps.as_slice());
         let _most_bottlenecked_equipment =
             factory.calculate_bottleneck(most_needed_equipment.as_slice());
         //println!("\nbottleneck : {:?}", most_bottlenecked_equipment);
-        let now = chrono::offset::Utc::now();
+        let (ordered, fulfillments) = factory.order_batches(&mut batches_needed, now);
+        for line in factory.fulfillment_report(&ordered, &fulfillments, now) {
+            println!("{}", line);
+        }
+        batches_needed.extend(ordered);
+        let released = factory.release_batches(&batches_needed, now);
+        batches_needed.extend(released);
+        for conflict in factory.release_conflicts(&batches_needed, now) {
+            println!("{}", conflict);
+        }
+        for brew_by in factory.brew_by_dates(&batches_needed) {
+            println!("{}", brew_by);
+        }
+        for warning in Plan::missed_visits(&factory, &batches_needed, now) {
+            println!("{}", warning);
+        }
+        for shortage in factory.keg_shortages(&batches_needed, now) {
+            println!("{}", shortage);
+        }
         let solution = Plan::plan(&factory, &batches_needed, now);
+        for warning in Plan::late_warnings(solution.as_slice()) {
+            println!("{}", warning);
+        }
+        for warning in Plan::downtime_warnings(solution.as_slice(), now) {
+            println!("{}", warning);
+        }
+        if let Some(tap_list) = &factory.tap_list {
+            for warning in Plan::tap_list_warnings(solution.as_slice(), &factory, tap_list, now) {
+                println!("{}", warning);
+            }
+        }
+        for requirement in Plan::material_requirements(&factory, solution.as_slice(), now) {
+            println!("{}", requirement);
+        }
         let pla = Plan::pla_basic(solution.as_slice(), Plan::sort_by_batch);
         println!("{}", pla);
         // @TODO: Generate plan list
@@ -0,0 +1,156 @@
+use chrono::Duration;
+use std::str::FromStr;
+
+use crate::config::MaintenanceConfig;
+use crate::interval::Interval;
+
+/// When a piece of equipment needs its maintenance.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MaintenanceTrigger {
+    Uses(u32),
+    DaysOccupied(u32),
+}
+
+/// How much a piece of equipment has been used since its last maintenance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Usage {
+    pub uses: u32,
+    pub occupied: Duration,
+}
+
+impl Usage {
+    pub fn new(uses: u32, occupied: Duration) -> Self {
+        Self { uses, occupied }
+    }
+
+    pub fn none() -> Self {
+        Usage::new(0, Duration::zero())
+    }
+}
+
+/**
+ * Preventive maintenance of a piece of equipment, like servicing the mash tun screens every 20
+ * brews or the fermentor valves every 90 days of fermentation. The rule keeps track of the usage
+ * since the last time it was done, so that the counters carry over from one plan to the next.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaintenanceRule {
+    pub name: String,
+    pub trigger: MaintenanceTrigger,
+    pub duration: Duration,
+    pub usage: Usage,
+}
+
+impl MaintenanceRule {
+    pub fn new(name: String, trigger: MaintenanceTrigger, duration: Duration) -> Self {
+        Self {
+            name,
+            trigger,
+            duration,
+            usage: Usage::none(),
+        }
+    }
+
+    pub fn record(&mut self, usage: &Usage) {
+        self.usage.uses += usage.uses;
+        self.usage.occupied += usage.occupied;
+    }
+
+    pub fn is_due(&self) -> bool {
+        match self.trigger {
+            MaintenanceTrigger::Uses(x) => self.usage.uses >= x,
+            MaintenanceTrigger::DaysOccupied(x) => self.usage.occupied >= Duration::days(x as i64),
+        }
+    }
+
+    /// The maintenance is done, so the counting starts over.
+    pub fn reset(&mut self) {
+        self.usage = Usage::none();
+    }
+}
+
+impl std::convert::From<&MaintenanceConfig> for MaintenanceRule {
+    fn from(config: &MaintenanceConfig) -> Self {
+        let trigger = match (config.every_uses, config.every_days) {
+            (Some(x), None) if x > 0 => MaintenanceTrigger::Uses(x),
+            (None, Some(x)) if x > 0 => MaintenanceTrigger::DaysOccupied(x),
+            _ => panic!(
+                "{} does not appear to be a valid maintenance rule, it needs either every_uses or every_days",
+                config.name
+            ),
+        };
+        let duration = match Interval::from_str(&config.duration) {
+            Ok(x) => x.duration(),
+            Err(_e) => panic!(
+                "{} does not appear to be a valid duration for maintenance {}",
+                config.duration, config.name
+            ),
+        };
+        let mut rule = MaintenanceRule::new(String::from(&config.name), trigger, duration);
+        rule.record(&Usage::new(
+            config.uses,
+            Duration::days(config.days_occupied as i64),
+        ));
+        rule
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_screen_service() -> MaintenanceRule {
+        MaintenanceRule::new(
+            "Screen service".to_string(),
+            MaintenanceTrigger::Uses(2),
+            Duration::hours(4),
+        )
+    }
+
+    pub fn mock_valve_service() -> MaintenanceRule {
+        MaintenanceRule::new(
+            "Valve service".to_string(),
+            MaintenanceTrigger::DaysOccupied(90),
+            Duration::days(1),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance_every_uses() {
+        let mut rule = mock::mock_screen_service();
+        assert!(!rule.is_due());
+        rule.record(&Usage::new(1, Duration::hours(6)));
+        assert!(!rule.is_due());
+        rule.record(&Usage::new(1, Duration::hours(6)));
+        assert!(rule.is_due());
+        rule.reset();
+        assert!(!rule.is_due());
+        assert_eq!(rule.usage, Usage::none());
+    }
+
+    #[test]
+    fn test_maintenance_every_days_occupied() {
+        let mut rule = mock::mock_valve_service();
+        rule.record(&Usage::new(5, Duration::days(89)));
+        assert!(!rule.is_due());
+        rule.record(&Usage::new(1, Duration::days(1)));
+        assert!(rule.is_due());
+    }
+
+    #[test]
+    fn test_maintenance_from_config() {
+        let config: MaintenanceConfig = serde_json::from_str(
+            r#"{"name": "Screen service", "every_uses": 2, "duration": "4h", "uses": 1}"#,
+        )
+        .unwrap();
+        let rule = MaintenanceRule::from(&config);
+        assert_eq!(rule.trigger, MaintenanceTrigger::Uses(2));
+        assert_eq!(rule.duration, Duration::hours(4));
+        assert_eq!(rule.usage, Usage::new(1, Duration::zero()));
+    }
+}
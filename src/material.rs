@@ -0,0 +1,98 @@
+use std::str::FromStr;
+
+use chrono::Duration;
+
+use crate::config::{IngredientConfig, MaterialConfig};
+use crate::interval::Interval;
+
+/// How much of a material, like a malt, a hop or a yeast, goes in a batch. The amount is in the
+/// unit that the material is stocked in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ingredient {
+    pub name: String,
+    pub amount: f32,
+}
+
+impl Ingredient {
+    pub fn new(name: String, amount: f32) -> Self {
+        Self { name, amount }
+    }
+}
+
+impl std::convert::From<&IngredientConfig> for Ingredient {
+    fn from(config: &IngredientConfig) -> Self {
+        Ingredient::new(String::from(&config.name), config.amount)
+    }
+}
+
+/// A raw material in the store: how much is on hand, who supplies it and how long it takes
+/// them to deliver.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub unit: String,
+    pub on_hand: f32,
+    pub supplier: String,
+    pub lead_time: Duration,
+}
+
+impl Material {
+    pub fn new(name: String, unit: String, on_hand: f32) -> Self {
+        Self {
+            name,
+            unit,
+            on_hand,
+            supplier: String::new(),
+            lead_time: Duration::zero(),
+        }
+    }
+
+    pub fn with_supplier(mut self, supplier: String, lead_time: Duration) -> Self {
+        self.supplier = supplier;
+        self.lead_time = lead_time;
+        self
+    }
+}
+
+impl std::convert::From<&MaterialConfig> for Material {
+    fn from(config: &MaterialConfig) -> Self {
+        let lead_time = match Interval::from_str(&config.lead_time) {
+            Ok(x) => x.duration(),
+            Err(_e) => panic!(
+                "{} does not appear to be a valid lead time for {}",
+                config.lead_time, config.name
+            ),
+        };
+        Material::new(
+            String::from(&config.name),
+            String::from(&config.unit),
+            config.on_hand,
+        )
+        .with_supplier(String::from(&config.supplier), lead_time)
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_material() -> Material {
+        Material::new("Maris Otter".to_string(), "kg".to_string(), 25.0)
+            .with_supplier("Country Malt".to_string(), Duration::days(7))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_material_from_config() {
+        let config: MaterialConfig = serde_json::from_str(
+            r#"{"name": "Maris Otter", "unit": "kg", "on_hand": 25.0,
+                "supplier": "Country Malt", "lead_time": "7d"}"#,
+        )
+        .unwrap();
+        assert_eq!(Material::from(&config), mock::mock_material());
+    }
+}
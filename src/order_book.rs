@@ -0,0 +1,103 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+
+use crate::config::CustomerOrderConfig;
+use crate::package_format::PackageFormat;
+
+/// The packages of a beer that a distributor or an account ordered, to be delivered on a day.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomerOrder {
+    pub customer: String,
+    pub beer: String,
+    pub format: PackageFormat,
+    pub units: u32,
+    pub delivery: DateTime<Utc>,
+}
+
+impl CustomerOrder {
+    pub fn new(
+        customer: String,
+        beer: String,
+        format: PackageFormat,
+        units: u32,
+        delivery: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            customer,
+            beer,
+            format,
+            units,
+            delivery,
+        }
+    }
+}
+
+impl std::convert::From<&CustomerOrderConfig> for CustomerOrder {
+    fn from(config: &CustomerOrderConfig) -> Self {
+        let format = match PackageFormat::from_str(&config.format) {
+            Ok(x) => x,
+            Err(_e) => panic!(
+                "{} does not appear to be a valid package format for the order of {}",
+                config.format, config.customer
+            ),
+        };
+        CustomerOrder::new(
+            String::from(&config.customer),
+            String::from(&config.beer),
+            format,
+            config.units,
+            config.delivery,
+        )
+    }
+}
+
+/// The packages of an order that a batch serves. When there is no batch, the packages are
+/// short.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fulfillment {
+    pub order: CustomerOrder,
+    pub batch: Option<usize>,
+    pub units: u32,
+}
+
+impl Fulfillment {
+    pub fn new(order: CustomerOrder, batch: Option<usize>, units: u32) -> Self {
+        Self {
+            order,
+            batch,
+            units,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use chrono::TimeZone;
+
+    pub fn mock_customer_order() -> CustomerOrder {
+        CustomerOrder::new(
+            "The Corner Bar".to_string(),
+            "foobeer 3000".to_string(),
+            PackageFormat::SixthBarrel,
+            2,
+            Utc.with_ymd_and_hms(2021, 7, 1, 8, 0, 0).unwrap(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_customer_order_from_config() {
+        let config: CustomerOrderConfig = serde_json::from_str(
+            r#"{"customer": "The Corner Bar", "beer": "foobeer 3000", "format": "sixtel",
+                "units": 2, "delivery": "2021-07-01T08:00:00Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(CustomerOrder::from(&config), mock::mock_customer_order());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::volume::Volume;
+
+/// What the beer is packaged in.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum PackageFormat {
+    HalfBarrel,
+    SixthBarrel,
+    Can16oz,
+    Bottle750ml,
+}
+
+impl PackageFormat {
+    pub fn lookup(&self) -> &'static str {
+        match self {
+            PackageFormat::HalfBarrel => "1/2 BBL",
+            PackageFormat::SixthBarrel => "1/6 BBL",
+            PackageFormat::Can16oz => "16oz Can",
+            PackageFormat::Bottle750ml => "750ml Bottle",
+        }
+    }
+
+    /// What goes in one package.
+    pub fn volume(&self) -> Volume {
+        match self {
+            PackageFormat::HalfBarrel => Volume::BeerBarrel(0.5),
+            PackageFormat::SixthBarrel => Volume::BeerBarrel(1.0 / 6.0),
+            PackageFormat::Can16oz => Volume::GallonUS(0.125),
+            PackageFormat::Bottle750ml => Volume::Liter(0.75),
+        }
+    }
+
+    pub fn is_keg(&self) -> bool {
+        matches!(self, PackageFormat::HalfBarrel | PackageFormat::SixthBarrel)
+    }
+
+    /// How many packages go in a case, a keg is shipped on its own.
+    pub fn per_case(&self) -> u32 {
+        match self {
+            PackageFormat::HalfBarrel | PackageFormat::SixthBarrel => 1,
+            PackageFormat::Can16oz => 24,
+            PackageFormat::Bottle750ml => 12,
+        }
+    }
+
+    /// How many cases it takes to ship a number of packages.
+    pub fn cases(&self, units: u32) -> u32 {
+        units.div_ceil(self.per_case())
+    }
+
+    /// The volume of a number of packages.
+    pub fn volume_of(&self, units: u32) -> Volume {
+        match self.volume().to_liter() {
+            Volume::Liter(liter) => Volume::Liter(liter * units as f32),
+            _ => panic!("Should not happen"),
+        }
+    }
+
+    /// How many full packages can be filled with the volume.
+    pub fn units(&self, volume: &Volume) -> u32 {
+        if let Volume::Liter(package) = self.volume().to_liter() {
+            if let Volume::Liter(amount) = volume.to_liter() {
+                // Leave room for the rounding of the conversion to liters
+                return (amount / package + 0.0001).floor() as u32;
+            }
+        }
+
+        panic!("Something went wonky when trying to convert volumes");
+    }
+}
+
+impl std::str::FromStr for PackageFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<PackageFormat, ()> {
+        match s {
+            "1/2 BBL" => Ok(PackageFormat::HalfBarrel),
+            "half" => Ok(PackageFormat::HalfBarrel),
+            "1/6 BBL" => Ok(PackageFormat::SixthBarrel),
+            "sixtel" => Ok(PackageFormat::SixthBarrel),
+            "16oz Can" => Ok(PackageFormat::Can16oz),
+            "can" => Ok(PackageFormat::Can16oz),
+            "750ml Bottle" => Ok(PackageFormat::Bottle750ml),
+            "bottle" => Ok(PackageFormat::Bottle750ml),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_can() -> PackageFormat {
+        PackageFormat::Can16oz
+    }
+
+    pub fn mock_half_barrel() -> PackageFormat {
+        PackageFormat::HalfBarrel
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_format_lookup() {
+        assert_eq!(PackageFormat::HalfBarrel.lookup(), "1/2 BBL");
+        assert_eq!(PackageFormat::SixthBarrel.lookup(), "1/6 BBL");
+        assert_eq!(PackageFormat::Can16oz.lookup(), "16oz Can");
+        assert_eq!(PackageFormat::Bottle750ml.lookup(), "750ml Bottle");
+    }
+
+    #[test]
+    fn test_package_format_units() {
+        assert_eq!(mock::mock_can().units(&Volume::GallonUS(10.0)), 80);
+        assert_eq!(mock::mock_half_barrel().units(&Volume::BeerBarrel(7.0)), 14);
+        assert_eq!(PackageFormat::Bottle750ml.units(&Volume::Liter(10.0)), 13);
+    }
+
+    #[test]
+    fn test_package_format_cases() {
+        assert_eq!(mock::mock_can().cases(48), 2);
+        assert_eq!(mock::mock_can().cases(50), 3);
+        assert_eq!(PackageFormat::Bottle750ml.cases(12), 1);
+        assert_eq!(mock::mock_half_barrel().cases(14), 14);
+    }
+
+    #[test]
+    fn test_package_format_is_keg() {
+        assert!(mock::mock_half_barrel().is_keg());
+        assert!(PackageFormat::SixthBarrel.is_keg());
+        assert!(!mock::mock_can().is_keg());
+    }
+
+    #[test]
+    fn test_package_format_parse() {
+        assert_eq!("1/2 BBL".parse(), Ok(PackageFormat::HalfBarrel));
+        assert_eq!("sixtel".parse(), Ok(PackageFormat::SixthBarrel));
+        assert_eq!("can".parse(), Ok(PackageFormat::Can16oz));
+        assert_eq!("bottle".parse(), Ok(PackageFormat::Bottle750ml));
+        assert_eq!("growler".parse::<PackageFormat>(), Err(()));
+    }
+}
@@ -0,0 +1,121 @@
+use std::str::FromStr;
+
+use chrono::Duration;
+
+use crate::config::{PackagingOrderConfig, PackagingRateConfig};
+use crate::package_format::PackageFormat;
+
+/// How fast a packaging line fills a format, in packages per hour.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackagingRate {
+    pub format: PackageFormat,
+    pub rate: u32,
+    pub minimum_run: u32,
+}
+
+impl PackagingRate {
+    pub fn new(format: PackageFormat, rate: u32, minimum_run: u32) -> Self {
+        Self {
+            format,
+            rate,
+            minimum_run,
+        }
+    }
+
+    /// How long it takes to fill the packages.
+    pub fn run_time(&self, units: u32) -> Duration {
+        Duration::seconds((units as f32 * 3600.0 / self.rate as f32).ceil() as i64)
+    }
+}
+
+impl std::convert::From<&PackagingRateConfig> for PackagingRate {
+    fn from(config: &PackagingRateConfig) -> Self {
+        let format = match PackageFormat::from_str(&config.format) {
+            Ok(x) => x,
+            Err(_e) => panic!(
+                "{} does not appear to be a valid package format",
+                config.format
+            ),
+        };
+        if config.rate == 0 {
+            panic!(
+                "{} does not appear to be a valid packaging rate for {}",
+                config.rate, config.format
+            );
+        }
+        PackagingRate::new(format, config.rate, config.minimum_run)
+    }
+}
+
+/// The packages of a beer that are asked for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackagingOrder {
+    pub beer: String,
+    pub format: PackageFormat,
+    pub units: u32,
+}
+
+impl PackagingOrder {
+    pub fn new(beer: String, format: PackageFormat, units: u32) -> Self {
+        Self {
+            beer,
+            format,
+            units,
+        }
+    }
+}
+
+impl std::convert::From<&PackagingOrderConfig> for PackagingOrder {
+    fn from(config: &PackagingOrderConfig) -> Self {
+        let format = match PackageFormat::from_str(&config.format) {
+            Ok(x) => x,
+            Err(_e) => panic!(
+                "{} does not appear to be a valid package format for {}",
+                config.format, config.beer
+            ),
+        };
+        PackagingOrder::new(String::from(&config.beer), format, config.units)
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use crate::package_format;
+
+    pub fn mock_canning() -> PackagingRate {
+        PackagingRate::new(package_format::mock::mock_can(), 1200, 100)
+    }
+
+    pub fn mock_kegging() -> PackagingRate {
+        PackagingRate::new(package_format::mock::mock_half_barrel(), 30, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packaging_rate_run_time() {
+        assert_eq!(mock::mock_canning().run_time(600), Duration::minutes(30));
+        assert_eq!(mock::mock_kegging().run_time(1), Duration::minutes(2));
+    }
+
+    #[test]
+    fn test_packaging_rate_from_config() {
+        let config: PackagingRateConfig =
+            serde_json::from_str(r#"{"format": "can", "rate": 1200, "minimum_run": 100}"#).unwrap();
+        assert_eq!(PackagingRate::from(&config), mock::mock_canning());
+    }
+
+    #[test]
+    fn test_packaging_order_from_config() {
+        let config: PackagingOrderConfig =
+            serde_json::from_str(r#"{"beer": "Bier", "format": "half", "units": 4}"#).unwrap();
+        assert_eq!(
+            PackagingOrder::from(&config),
+            PackagingOrder::new("Bier".to_string(), PackageFormat::HalfBarrel, 4)
+        );
+    }
+}
@@ -1,21 +1,57 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use chrono::prelude::*;
 use z3::{ast, ast::Ast, Config, Context, Optimize, SatResult};
 
 use crate::action::Action;
 use crate::batchneed::BatchNeed;
+use crate::beer::Beer;
+use crate::capability::Capability;
 use crate::capacity::Capacity;
+use crate::clean_level::CleanLevel;
+use crate::downtime::Downtime;
 use crate::equipment::Equipment;
 use crate::equipment_group::EquipmentGroup;
 use crate::factory::Factory;
+use crate::maintenance::{MaintenanceRule, Usage};
+use crate::package_format::PackageFormat;
 use crate::step_group::StepGroup;
+use crate::tap_list::{TapList, TapRequirement};
+use crate::visit::Visit;
+use crate::yeast::{Slurry, YeastStrain};
 
 /*
 Premium customers coin insertion slot
 There is not much improvement after 15 iterations, it only will take much longer.
 */
 const REPEAT: usize = 15;
+/*
+Maintenance that is due moves the batches after it, which can make other maintenance due
+at another moment. Usually this settles after a couple of rounds.
+*/
+const REPEAT_MAINTENANCE: usize = 5;
+
+/// A packaging run of a batch: the format and number of packages, and its line, start and stop.
+type PackagingRun<'ctx> = (
+    usize,
+    PackageFormat,
+    u32,
+    ast::Int<'ctx>,
+    ast::Int<'ctx>,
+    ast::Int<'ctx>,
+);
+
+/// The yeast of a batch: the batch it is harvested from (0 when it is propagated, negative for a
+/// slurry on hand, see `Plan::slurries`) and its generation.
+type YeastLink<'ctx> = (usize, ast::Int<'ctx>, ast::Int<'ctx>);
+
+/// The solver variables of the steps: the machines that can be picked, the machine that is
+/// picked for every step, and the times of every step.
+struct StepVariables<'ctx> {
+    machines: HashMap<(Capability, Capacity), HashMap<usize, (ast::Int<'ctx>, Equipment)>>,
+    step_machine: HashMap<(usize, StepGroup), ast::Int<'ctx>>,
+    step_times: HashMap<(usize, StepGroup, &'static str), ast::Int<'ctx>>,
+}
 
 #[derive(Debug, PartialEq)]
 pub struct Plan<'a> {
@@ -27,6 +63,9 @@ pub struct Plan<'a> {
     end: DateTime<Utc>,
 }
 
+/// The uses of equipment after which maintenance is due, with the moment it can start.
+type MaintenanceDue<'a, 'b> = Vec<(&'b Plan<'a>, MaintenanceRule, DateTime<Utc>)>;
+
 const S1A: &'static str = "STARTED";
 const E1A: &'static str = "STOPPED";
 const S2A: &'static str = "TRANSFERED";
@@ -52,11 +91,17 @@ impl<'a> Plan<'a> {
         end: DateTime<Utc>,
     ) -> Self {
         match &action {
-            Action::Process(equipment) | Action::Clean(equipment) => {
-                assert_eq!(step_group.equipment_group(), equipment.equipment_group);
+            Action::Process(equipment)
+            | Action::Clean(equipment, _, _)
+            | Action::Package(equipment, _, _) => {
+                assert!(equipment.can(&step_group.capability()));
+            }
+            Action::Transfer(equipment, _not_relavant, _shared) => {
+                assert!(equipment.can(&step_group.capability()));
             }
-            Action::Transfer(equipment, _not_relavant) => {
-                assert_eq!(step_group.equipment_group(), equipment.equipment_group);
+            Action::Maintain(_equipment, _task) => {}
+            Action::Propagate(_yeast) | Action::Pitch(_yeast, _, _) => {
+                assert_eq!(step_group.capability(), Capability::Fermentation);
             }
         }
 
@@ -74,6 +119,164 @@ impl<'a> Plan<'a> {
         factory: &'a Factory,
         batches_needed: &'a HashMap<usize, BatchNeed<'a>>,
         earliest_start: DateTime<Utc>,
+    ) -> Vec<Plan<'a>> {
+        // The maintenance that is due is blocked in the schedule like a downtime, and
+        // the batches are planned again around it.
+        let mut maintenance = HashMap::new();
+        let mut solution = Plan::solve(factory, batches_needed, earliest_start, &maintenance);
+        let mut settled = false;
+        for _ in 0..REPEAT_MAINTENANCE {
+            let windows = Plan::maintenance_windows(solution.as_slice());
+            if windows == maintenance {
+                settled = true;
+                break;
+            }
+            maintenance = windows;
+            solution = Plan::solve(factory, batches_needed, earliest_start, &maintenance);
+        }
+        // The last solve did not plan around the maintenance it makes due, which is fine as
+        // long as that maintenance doesn't get in the way of the batches.
+        if !settled {
+            for (name, windows) in Plan::maintenance_windows(solution.as_slice()) {
+                for window in windows {
+                    if let Some(plan) = solution.iter().find(|plan| {
+                        Plan::keeps_busy(plan, &name) && window.overlaps(&plan.start, &plan.end)
+                    }) {
+                        panic!(
+                            "{} of {} does not appear to settle after {} rounds, it overlaps batch {}",
+                            window.reason, name, REPEAT_MAINTENANCE, plan.batch.id
+                        );
+                    }
+                }
+            }
+        }
+        let first_id = solution.len() + 1;
+        let tasks = Plan::maintenance_due(solution.as_slice())
+            .0
+            .into_iter()
+            .enumerate()
+            .map(|(i, (plan, rule, start))| match &plan.action {
+                Action::Process(equipment) => Plan::new(
+                    first_id + i,
+                    plan.batch,
+                    plan.step_group.clone(),
+                    Action::Maintain(equipment.clone(), rule.name.clone()),
+                    start,
+                    start + rule.duration,
+                ),
+                _ => panic!("should not happen"),
+            })
+            .collect::<Vec<Plan>>();
+        solution.extend(tasks);
+
+        solution
+    }
+
+    /// Whether the plan keeps the equipment with the name busy.
+    fn keeps_busy(plan: &Plan, name: &str) -> bool {
+        match &plan.action {
+            Action::Process(equipment)
+            | Action::Clean(equipment, _, _)
+            | Action::Maintain(equipment, _)
+            | Action::Package(equipment, _, _) => equipment.name == name,
+            Action::Transfer(from, _, _) => from.name == name,
+            Action::Propagate(_) | Action::Pitch(_, _, _) => false,
+        }
+    }
+
+    /// The usage of the equipment at the end of the planning, so that it can be counted
+    /// towards the maintenance in the next planning.
+    pub fn maintenance_counters(planning: &[Plan]) -> HashMap<String, Vec<MaintenanceRule>> {
+        Plan::maintenance_due(planning).1
+    }
+
+    /// Walk through the uses of every piece of equipment in time, and find out after which use
+    /// its maintenance is due. The maintenance starts when the equipment is cleaned.
+    fn maintenance_due<'b>(
+        planning: &'b [Plan<'a>],
+    ) -> (
+        MaintenanceDue<'a, 'b>,
+        HashMap<String, Vec<MaintenanceRule>>,
+    ) {
+        let mut uses: HashMap<String, Vec<&Plan>> = HashMap::new();
+        let mut cleaned = HashMap::new();
+        for plan in planning {
+            match &plan.action {
+                Action::Process(equipment) if !equipment.maintenance.is_empty() => {
+                    uses.entry(equipment.name.clone()).or_default().push(plan);
+                }
+                Action::Clean(equipment, _, _) => {
+                    // A decontamination before the step does not count, only the clean after it
+                    let end = cleaned
+                        .entry((
+                            equipment.name.clone(),
+                            plan.batch.id,
+                            plan.step_group.clone(),
+                        ))
+                        .or_insert(plan.end);
+                    *end = plan.end.max(*end);
+                }
+                _ => {}
+            }
+        }
+        let mut due = Vec::new();
+        let mut counters = HashMap::with_capacity(uses.len());
+        for (name, mut plans) in uses {
+            plans.sort_by_key(|plan| plan.start);
+            let mut rules = match &plans[0].action {
+                Action::Process(equipment) => equipment.maintenance.clone(),
+                _ => panic!("should not happen"),
+            };
+            // A batch that stays in a multi-role vessel for its next step uses it once
+            let mut staying = None;
+            for plan in plans {
+                let uses = if staying == Some(plan.batch.id) { 0 } else { 1 };
+                let usage = Usage::new(uses, plan.end - plan.start);
+                // When the batch stays in the vessel, the maintenance waits till it is cleaned.
+                let clean_end =
+                    cleaned.get(&(name.clone(), plan.batch.id, plan.step_group.clone()));
+                staying = match clean_end {
+                    Some(_) => None,
+                    None => Some(plan.batch.id),
+                };
+                for rule in rules.iter_mut() {
+                    rule.record(&usage);
+                    if let Some(start) = clean_end {
+                        if rule.is_due() {
+                            due.push((plan, rule.clone(), *start));
+                            rule.reset();
+                        }
+                    }
+                }
+            }
+            counters.insert(name, rules);
+        }
+
+        (due, counters)
+    }
+
+    fn maintenance_windows(planning: &[Plan]) -> HashMap<String, Vec<Downtime>> {
+        let mut windows: HashMap<String, Vec<Downtime>> = HashMap::new();
+        for (plan, rule, start) in Plan::maintenance_due(planning).0 {
+            if let Action::Process(equipment) = &plan.action {
+                windows
+                    .entry(equipment.name.clone())
+                    .or_default()
+                    .push(Downtime::new(start, start + rule.duration, rule.name));
+            }
+        }
+        for downtime in windows.values_mut() {
+            downtime.sort_by_key(|downtime| downtime.start);
+        }
+
+        windows
+    }
+
+    fn solve(
+        factory: &'a Factory,
+        batches_needed: &'a HashMap<usize, BatchNeed<'a>>,
+        earliest_start: DateTime<Utc>,
+        maintenance: &HashMap<String, Vec<Downtime>>,
     ) -> Vec<Plan<'a>> {
         // 1) we setup the solver
         let mut cfg = Config::new();
@@ -101,68 +304,98 @@ impl<'a> Plan<'a> {
         // 2) We setup some lookup tables to keep track of our variables
         let mut z3_step_times = HashMap::with_capacity(batches_needed.len() * 6 * 4);
         let mut z3_step_machine = HashMap::with_capacity(batches_needed.len() * 6);
+        let mut z3_step_clean = HashMap::with_capacity(batches_needed.len() * 6);
+        let mut z3_step_next = HashMap::with_capacity(batches_needed.len() * 6);
+        let mut z3_step_decontamination = HashMap::with_capacity(batches_needed.len() * 6);
+        let shared = factory.shared_equipment();
+        let mut shared_uses = Vec::new();
         let mut all_endings = Vec::new();
-        let step_groups = StepGroup::all();
+        // How long the beer waits, when it could have been made later
+        let mut waits = Vec::new();
+        // How late the batches that are due are finished
+        let mut late = Vec::new();
+        let mut step_groups = StepGroup::all();
+        for definition in factory.step_definitions.values() {
+            step_groups.push(StepGroup::Custom(definition.clone()));
+        }
         let systems = Capacity::all();
 
         let mut z3_machines = HashMap::with_capacity(step_groups.len());
         for step_group in step_groups {
             for system in &systems {
-                let equipment_group = step_group.equipment_group();
-                z3_machines.insert((equipment_group, system.clone()), HashMap::new());
-            }
-        }
-        let mut machine_id = 1;
-        for equipment in factory.equipments.values() {
-            if let Some(map) = z3_machines.get_mut(&(
-                equipment.equipment_group.clone(),
-                equipment.capacity.clone(),
-            )) {
-                // For some reason z3 gives a illegale exectution (don't remember)
-                // error when we use a ast::Set of ast::Sort::int(). Therefor,
-                // we use this (plain number) as a work around.
-                // In the future we could go back to that or investigate ast::Array
-                let machine = ast::Int::new_const(&ctx, format!("Equipment {}", equipment.name));
-                map.insert(machine_id, (machine.clone(), equipment.clone()));
-                //     Constraint-like: give the machine a unqiue number, that can be added to every step
-                solver.assert(&machine._eq(&ast::Int::from_i64(&ctx, machine_id as i64)));
-                machine_id += 1;
+                let capability = step_group.capability();
+                z3_machines.insert((capability, system.clone()), HashMap::new());
+            }
+        }
+        for (machine_id, equipment) in (1..).zip(factory.equipments.values()) {
+            // For some reason z3 gives a illegale exectution (don't remember)
+            // error when we use a ast::Set of ast::Sort::int(). Therefor,
+            // we use this (plain number) as a work around.
+            // In the future we could go back to that or investigate ast::Array
+            let machine = ast::Int::new_const(&ctx, format!("Equipment {}", equipment.name));
+            //     Constraint-like: give the machine a unqiue number, that can be added to every step
+            solver.assert(&machine._eq(&ast::Int::from_i64(&ctx, machine_id as i64)));
+            // A multi-role vessel, like a unitank, is suited for every step it has the capability for.
+            for capability in &equipment.capabilities {
+                if let Some(map) =
+                    z3_machines.get_mut(&(capability.clone(), equipment.capacity.clone()))
+                {
+                    map.insert(machine_id, (machine.clone(), equipment.clone()));
+                }
             }
         }
         // 3) We iterate through the batches and each of its steps
         let start_horizon = ast::Int::from_i64(&ctx, earliest_start.timestamp());
         for batch in batches_needed.values() {
-            let mut prev = None;
             let mut start = start_horizon.clone();
-            for (step_group, interval) in batch.steps() {
+            // Whether the batch stays in the vessel of the previous step
+            let mut arrived = ast::Bool::from_bool(&ctx, false);
+            let steps = batch.steps();
+            // The machines are set up front, so that a step can see on which machine its next step is done
+            let machine_steps = steps
+                .iter()
+                .map(|(step_group, _interval)| {
+                    ast::Int::new_const(
+                        &ctx,
+                        format!(
+                            "Machine for batch: {}, beer: {} step: {:?}",
+                            batch.beer.name,
+                            batch.id,
+                            step_group.clone()
+                        ),
+                    )
+                })
+                .collect::<Vec<ast::Int>>();
+            for (pos, (step_group, interval)) in steps.iter().enumerate() {
                 // Where we define some variables for the solver and add constraints
                 let (_earliest, longest) = interval.range();
-                let machine_step = ast::Int::new_const(
-                    &ctx,
-                    format!(
-                        "Machine for batch: {}, beer: {} step: {:?}",
-                        batch.beer.name,
-                        batch.id,
-                        step_group.clone()
-                    ),
-                );
+                let machine_step = &machine_steps[pos];
                 z3_step_machine.insert((batch.id, step_group.clone()), machine_step.clone());
-                let equipment_group = step_group.equipment_group();
-                let mut machine_counts = 0;
-                if let Some(suited) = z3_machines.get(&(equipment_group, batch.system.clone())) {
-                    let suited_machines = suited
-                        .values()
-                        .map(|(int, _equ)| int)
-                        .collect::<Vec<&ast::Int>>();
-                    let mut ors = Vec::with_capacity(suited_machines.len());
-                    for machine in suited_machines {
-                        let allowed = machine_step._eq(machine);
-                        ors.push(allowed);
-                        machine_counts += 1;
+                let capability = step_group.capability();
+                match z3_machines.get(&(capability.clone(), batch.system.clone())) {
+                    Some(suited) if !suited.is_empty() => {
+                        let ors = suited
+                            .values()
+                            .filter(|(_machine, equ)| factory.segregation.allows(batch.beer, equ))
+                            .map(|(machine, _equ)| machine_step._eq(machine))
+                            .collect::<Vec<ast::Bool>>();
+                        if ors.is_empty() {
+                            panic!(
+                                "{} does not appear to be allowed in any {} equipment for {}",
+                                batch.beer.name,
+                                batch.system.lookup(),
+                                capability.lookup()
+                            );
+                        }
+                        let bors = ors.iter().collect::<Vec<&ast::Bool>>();
+                        //     Constraint: only one of these machines can be used for this step
+                        solver.assert(&ast::Bool::or(&ctx, bors.as_slice()));
                     }
-                    let bors = ors.iter().map(|x| x).collect::<Vec<&ast::Bool>>();
-                    //     Constraint: only one of these machines can be used for this step
-                    solver.assert(&ast::Bool::or(&ctx, bors.as_slice()));
+                    _ => panic!(
+                        "{} does not appear to be a valid capability for any {} equipment",
+                        capability.lookup(),
+                        batch.system.lookup()
+                    ),
                 }
                 gen_z3_var!(
                     z3_step_times,
@@ -200,11 +433,14 @@ impl<'a> Plan<'a> {
                     step_group,
                     S1F
                 );
-                all_endings.push(resource_available.clone());
+                // A batch that is due is made just in time (see 8), not as soon as possible
+                if batch.due.is_none() {
+                    all_endings.push(resource_available.clone());
+                }
                 // In the future, some batches may be actually be in production,
                 // that would mean that need to skip some steps and set another
                 // start time here.
-                if step_group == StepGroup::Brewing {
+                if pos == 0 {
                     //     Constraint: if it is the brewstep, set start first step in future
                     solver.assert(&step_start.ge(&start));
                 } else {
@@ -219,105 +455,722 @@ impl<'a> Plan<'a> {
                         &ast::Int::from_i64(&ctx, longest.num_seconds()),
                     ],
                 )));
+                if let Some(due) = batch.due {
+                    if pos + 1 == steps.len() {
+                        let due = ast::Int::from_i64(&ctx, due.timestamp());
+                        // The batch is finished when it is due, or as little late as can be (see 7)
+                        late.push(Plan::lateness(&ctx, &step_stop, &due));
+                        if let Some(max_age) = batch.beer.shelf_life() {
+                            if batch.packaging.is_empty() {
+                                let max_age = ast::Int::from_i64(&ctx, max_age.num_seconds());
+                                //     Constraint: and it is still fresh by then
+                                solver
+                                    .assert(&ast::Int::add(&ctx, &[&step_stop, &max_age]).ge(&due));
+                            }
+                        }
+                        waits.push(Plan::lateness(&ctx, &due, &step_stop));
+                    }
+                }
+                //     When the next step stays in this vessel, there is nothing to transfer
+                //     or clean: the vessel is released by the next step.
+                let stays = match steps.get(pos + 1) {
+                    Some((next_step_group, _)) => {
+                        let next_machine_step = &machine_steps[pos + 1];
+                        if next_step_group.stays_in_vessel_after(step_group) {
+                            //     Constraint: the batch stays in the machine of this step
+                            solver.assert(&next_machine_step._eq(machine_step));
+                            ast::Bool::from_bool(&ctx, true)
+                        } else {
+                            // A batch that is left in its vessel for the next step, like a
+                            // secondary fermentation in the fermentor or the carbonation in
+                            // a unitank, is not transfered
+                            next_machine_step._eq(machine_step)
+                        }
+                    }
+                    None => ast::Bool::from_bool(&ctx, false),
+                };
+                // The transfer and clean take as long as the chosen machine says, the made up
+                // post process time is only used for equipment that doesn't say.
+                let post_process_time = step_group.post_process_time(&batch.system);
+                let mut pump_time = ast::Int::from_i64(&ctx, post_process_time.num_seconds());
+                // Which clean is needed depends on the batch that uses the vessel next,
+                // so the time of every level is kept at hand.
+                let mut cip_times = CleanLevel::all()
+                    .into_iter()
+                    .map(|level| {
+                        let seconds = level.duration(post_process_time).num_seconds();
+                        (level, ast::Int::from_i64(&ctx, seconds))
+                    })
+                    .collect::<HashMap<CleanLevel, ast::Int>>();
+                if let Some(suited) = z3_machines.get(&(capability.clone(), batch.system.clone())) {
+                    for (machine, equipment) in suited.values() {
+                        let on_machine = machine_step._eq(machine);
+                        if let Some(duration) = equipment.transfer_time(&batch.volume) {
+                            let seconds = ast::Int::from_i64(&ctx, duration.num_seconds());
+                            pump_time = on_machine.ite(&seconds, &pump_time);
+                        }
+                        if let Some(duration) = equipment.cip_time {
+                            for (level, cip_time) in cip_times.iter_mut() {
+                                let seconds = level.duration(duration).num_seconds();
+                                let seconds = ast::Int::from_i64(&ctx, seconds);
+                                *cip_time = on_machine.ite(&seconds, cip_time);
+                            }
+                        }
+                    }
+                }
+                let no_time = ast::Int::from_i64(&ctx, 0);
                 //     Constraint: the next step may only start after the previous step is done.
                 //     Although we did not do a 'assert' here, the effect
                 //     is the same due to the way that we set up this loop.
-                let transfer_time = step_group.post_process_time(&batch.system);
-                solver.assert(&next_go._eq(&ast::Int::add(
-                    &ctx,
-                    &[
-                        &step_stop,
-                        &ast::Int::from_i64(&ctx, transfer_time.num_seconds()),
-                    ],
-                )));
+                let transfer_time = stays.ite(&no_time, &pump_time);
+                let ready = ast::Int::add(&ctx, &[&step_stop, &transfer_time]);
+                if pos + 1 == steps.len() && !batch.packaging.is_empty() {
+                    //     The beer is emptied by the packaging lines (see 4)
+                    solver.assert(&next_go.ge(&ready));
+                } else {
+                    solver.assert(&next_go._eq(&ready));
+                }
                 start = next_go.clone();
                 //     Constraint: the equipment is available after the cleaning
                 //     Implied Constraint: clean machine is the same as the machine that made it dirty
-                let clean_time = step_group.post_process_time(&batch.system);
-                solver.assert(&resource_available._eq(&ast::Int::add(
+                //     How thorough the clean is depends on the batch that comes next in the
+                //     vessel (see 3b).
+                let clean_times = cip_times
+                    .iter()
+                    .map(|(level, cip_time)| (level.clone(), stays.ite(&no_time, cip_time)))
+                    .collect::<HashMap<CleanLevel, ast::Int>>();
+                let clean_time = ast::Int::new_const(
                     &ctx,
-                    &[
-                        &next_go,
-                        &ast::Int::from_i64(&ctx, clean_time.num_seconds()),
-                    ],
-                )));
-                match prev {
-                    None => prev = Some((step_group, machine_step, machine_counts)),
-                    Some((ref prev_step_group, ref prev_machine_step, prev_machine_counts)) => {
-                        if &step_group == prev_step_group && prev_machine_counts > 1 {
-                            let same = machine_step._eq(prev_machine_step);
-                            //     Constraint: Previous step's machine is not this step's machine
-                            solver.assert(&ast::Bool::and(&ctx, &[&same]).not());
+                    format!("clean batch: {}, step: {:?}", batch.id, step_group),
+                );
+                let next_start = ast::Int::new_const(
+                    &ctx,
+                    format!(
+                        "next in vessel after batch: {}, step: {:?}",
+                        batch.id, step_group
+                    ),
+                );
+                solver.assert(
+                    &resource_available._eq(&ast::Int::add(&ctx, &[&next_go, &clean_time])),
+                );
+                z3_step_clean.insert((batch.id, step_group.clone()), clean_times);
+                z3_step_next.insert((batch.id, step_group.clone()), (clean_time, next_start));
+                //     The pumps, chillers and CIP skids are shared, the transfer and the clean
+                //     keep one of them busy. The CIP skid is held for the clean that is picked
+                //     from the changeover matrix.
+                let next_capability = steps.get(pos + 1).map(|(next, _)| next.capability());
+                for equipment_group in shared.keys() {
+                    let used_for_transfer = match &next_capability {
+                        Some(next_capability) => {
+                            equipment_group.used_for_transfer(&capability, next_capability)
+                        }
+                        None => false,
+                    };
+                    if used_for_transfer {
+                        shared_uses.push((
+                            equipment_group.clone(),
+                            stays.not(),
+                            step_stop.clone(),
+                            next_go.clone(),
+                        ));
+                    } else if equipment_group.used_for_clean() {
+                        shared_uses.push((
+                            equipment_group.clone(),
+                            stays.not(),
+                            next_go.clone(),
+                            resource_available.clone(),
+                        ));
+                    }
+                }
+                //     A quarantined machine is decontaminated before the first batch goes in,
+                //     whether it is the first one is only known when all batches are in (see 3b).
+                let mut decontamination = no_time.clone();
+                if let Some(suited) = z3_machines.get(&(capability.clone(), batch.system.clone())) {
+                    for (machine, equipment) in suited.values() {
+                        if factory.segregation.quarantined(equipment, batch.beer) {
+                            let full_cip = equipment.cip_time.unwrap_or(post_process_time);
+                            let seconds = CleanLevel::Decontamination.duration(full_cip);
+                            let seconds = ast::Int::from_i64(&ctx, seconds.num_seconds());
+                            decontamination =
+                                machine_step._eq(machine).ite(&seconds, &decontamination);
+                        }
+                    }
+                }
+                let decontamination = arrived.ite(&no_time, &decontamination);
+                let occupied = ast::Int::new_const(
+                    &ctx,
+                    format!(
+                        "decontaminated for batch: {}, step: {:?}",
+                        batch.id, step_group
+                    ),
+                );
+                solver.assert(&occupied.ge(&start_horizon));
+                z3_step_decontamination.insert(
+                    (batch.id, step_group.clone()),
+                    (decontamination, occupied.clone()),
+                );
+                if let Some(suited) = z3_machines.get(&(capability, batch.system.clone())) {
+                    for (machine, equipment) in suited.values() {
+                        let planned = maintenance.get(&equipment.name).into_iter().flatten();
+                        for downtime in equipment.downtime.iter().chain(planned) {
+                            if downtime.end <= earliest_start {
+                                continue;
+                            }
+                            let down = ast::Int::from_i64(&ctx, downtime.start.timestamp());
+                            let up = ast::Int::from_i64(&ctx, downtime.end.timestamp());
+                            //     Constraint: the machine is not used during its downtime
+                            solver.assert(&machine_step._eq(machine).implies(&ast::Bool::or(
+                                &ctx,
+                                &[&resource_available.le(&down), &occupied.ge(&up)],
+                            )));
                         }
                     }
                 }
+                arrived = stays;
             }
         }
         // 3b) Now that we have variables for the start/stop-times and the machines,
         //     we can set up that one machine can only do 1 task at the same time.
+        //     Because each vessel is booked on its own, the brew days of different
+        //     batches are pipelined over the brewhouse.
         let one_quart_day = ast::Int::from_i64(&ctx, 6 * 3600);
-        for ((this_batch_id, this_step_group), _this_step_machine) in z3_step_machine.iter() {
-            // we unwrap here 4 * 2 times, but a pyramid of 'if let Some()' could also work
+        // The start of the next batch in a vessel that is never used again
+        let nothing_next = ast::Int::from_i64(&ctx, i64::MAX);
+        let mut predecessors: HashMap<(usize, StepGroup), Vec<ast::Bool>> = HashMap::new();
+        for ((this_batch_id, this_step_group), this_step_machine) in z3_step_machine.iter() {
+            // we unwrap here 2 * 2 times, but a pyramid of 'if let Some()' could also work
             let this_step_start = z3_step_times
                 .get(&(*this_batch_id, this_step_group.clone(), S1A))
                 .unwrap();
             let this_next_go = z3_step_times
                 .get(&(*this_batch_id, this_step_group.clone(), S2A))
                 .unwrap();
-            let this_resource_available = z3_step_times
-                .get(&(*this_batch_id, this_step_group.clone(), S1F))
-                .unwrap();
-            let mut overlaps = Vec::new();
-            for ((other_batch_id, other_step_group), _other_step_machine) in z3_step_machine.iter()
-            {
-                if this_batch_id != other_batch_id {
-                    let other_step_start = z3_step_times
-                        .get(&(*other_batch_id, other_step_group.clone(), S1A))
-                        .unwrap();
-                    let other_next_go = z3_step_times
-                        .get(&(*other_batch_id, other_step_group.clone(), S2A))
-                        .unwrap();
-                    let other_resource_available = z3_step_times
-                        .get(&(*other_batch_id, other_step_group.clone(), S1F))
-                        .unwrap();
-                    if this_step_group != other_step_group {
-                        //     Constraint: This machine in occupied from step_start till resource_available
-                        overlaps.push(
-                            ast::Bool::and(
-                                &ctx,
-                                &[
-                                    &this_resource_available.ge(&other_step_start),
-                                    &other_resource_available.ge(&this_step_start),
-                                ],
+            let this_available = &z3_step_times[&(*this_batch_id, this_step_group.clone(), S1F)];
+            let this_cleans = &z3_step_clean[&(*this_batch_id, this_step_group.clone())];
+            let (this_clean, this_next) = &z3_step_next[&(*this_batch_id, this_step_group.clone())];
+            let this_batch = &batches_needed[this_batch_id];
+            let this_beer = this_batch.beer;
+            let this_suited =
+                &z3_machines[&(this_step_group.capability(), this_batch.system.clone())];
+            let mut successors = vec![this_next._eq(&nothing_next)];
+            for ((other_batch_id, other_step_group), other_step_machine) in z3_step_machine.iter() {
+                if this_batch_id == other_batch_id {
+                    continue;
+                }
+                let other_step_start = z3_step_times
+                    .get(&(*other_batch_id, other_step_group.clone(), S1A))
+                    .unwrap();
+                let other_next_go = z3_step_times
+                    .get(&(*other_batch_id, other_step_group.clone(), S2A))
+                    .unwrap();
+                let this_capability = this_step_group.capability();
+                let other_capability = other_step_group.capability();
+                if this_capability == other_capability
+                    || factory.equipments.values().any(|equipment| {
+                        equipment.can(&this_capability) && equipment.can(&other_capability)
+                    })
+                {
+                    let same_machine = this_step_machine._eq(other_step_machine);
+                    let later =
+                        ast::Bool::and(&ctx, &[&same_machine, &other_step_start.ge(this_next_go)]);
+                    //     Constraint: the next batch in the vessel is the first one that starts
+                    //                 after this one, and the clean is made for that batch.
+                    solver.assert(&later.implies(&this_next.le(other_step_start)));
+                    let follows = ast::Bool::and(&ctx, &[&later, &this_next._eq(other_step_start)]);
+                    let other_beer = batches_needed[other_batch_id].beer;
+                    let mut clean = this_cleans[&CleanLevel::FullCip].clone();
+                    for (machine, equipment) in this_suited.values() {
+                        let level = factory.clean_level(equipment, this_beer, other_beer);
+                        clean = this_step_machine
+                            ._eq(machine)
+                            .ite(&this_cleans[&level], &clean);
+                    }
+                    solver.assert(&follows.implies(&this_clean._eq(&clean)));
+                    predecessors
+                        .entry((*other_batch_id, other_step_group.clone()))
+                        .or_default()
+                        .push(follows.clone());
+                    successors.push(follows);
+                    //     Constraint: This machine in occupied from step_start till it is cleaned
+                    //                 for the batch that comes after it.
+                    // Every pair of batches only needs to be constrained once
+                    if this_batch_id < other_batch_id {
+                        let other_available =
+                            &z3_step_times[&(*other_batch_id, other_step_group.clone(), S1F)];
+                        solver.assert(&same_machine.implies(&ast::Bool::or(
+                            &ctx,
+                            &[
+                                &this_available.le(other_step_start),
+                                &other_available.le(this_step_start),
+                            ],
+                        )));
+                    }
+                }
+                // 3c) limit the number of brew that can happen 'simultanously'
+                if this_batch_id < other_batch_id
+                    && this_step_group == &StepGroup::Brewing
+                    && other_step_group == &StepGroup::Brewing
+                {
+                    //     Constraint: there are at least 6 hours between 2 brews
+                    //                 This basically limits it to one brew per day :-(
+                    //                 Recipes with a split up brew day are pipelined instead.
+                    solver.assert(&ast::Bool::or(
+                        &ctx,
+                        &[
+                            &ast::Int::add(&ctx, &[this_next_go, &one_quart_day])
+                                .lt(other_step_start),
+                            &ast::Int::add(&ctx, &[other_next_go, &one_quart_day])
+                                .lt(this_step_start),
+                        ],
+                    ));
+                }
+                // 3d) @TODO....The other machine is also occupied from step_stop till next_go
+            }
+            //     Constraint: the last batch in a vessel leaves it fully cleaned
+            let successors = successors.iter().collect::<Vec<&ast::Bool>>();
+            solver.assert(&ast::Bool::or(&ctx, successors.as_slice()));
+            solver.assert(
+                &this_next
+                    ._eq(&nothing_next)
+                    .implies(&this_clean._eq(&this_cleans[&CleanLevel::FullCip])),
+            );
+        }
+        //     Constraint: the first batch in a quarantined machine waits for its decontamination,
+        //                 which keeps a CIP skid busy as well.
+        for (step, (decontamination, occupied)) in z3_step_decontamination.iter() {
+            let step_start = &z3_step_times[&(step.0, step.1.clone(), S1A)];
+            let follows = predecessors.remove(step).unwrap_or_default();
+            let follows = follows.iter().collect::<Vec<&ast::Bool>>();
+            let first = ast::Bool::or(&ctx, follows.as_slice()).not();
+            let no_time = ast::Int::from_i64(&ctx, 0);
+            let waits = first.ite(decontamination, &no_time);
+            solver.assert(&occupied._eq(&ast::Int::sub(&ctx, &[step_start, &waits])));
+            for equipment_group in shared.keys() {
+                if equipment_group.used_for_clean() {
+                    shared_uses.push((
+                        equipment_group.clone(),
+                        ast::Bool::and(&ctx, &[&first, &decontamination.gt(&no_time)]),
+                        occupied.clone(),
+                        step_start.clone(),
+                    ));
+                }
+            }
+        }
+        // 3e) Never use more shared equipment at the same time than there is.
+        for (equipment_group, equipments) in shared.iter() {
+            let uses = shared_uses
+                .iter()
+                .filter(|(other_group, ..)| other_group == equipment_group)
+                .map(|(_, needed, begin, end)| (needed.clone(), begin.clone(), end.clone(), 1))
+                .collect::<Vec<(ast::Bool, ast::Int, ast::Int, u32)>>();
+            Plan::limit_cumulative(&ctx, &solver, uses.as_slice(), equipments.len() as u32);
+        }
+        // 3f) Keep the steps that use the glycol, the cold storage, .. within its limit.
+        for resource in &factory.resources {
+            let mut uses = Vec::new();
+            for batch in batches_needed.values() {
+                for (step_group, _interval) in batch.steps() {
+                    let usage = resource.usage(&step_group, batch);
+                    if usage == 0 {
+                        continue;
+                    }
+                    // More than there is is reported up front (see Factory::resource_overloads),
+                    // the step takes all of it.
+                    let usage = usage.min(resource.limit);
+                    let begin = &z3_step_times[&(batch.id, step_group.clone(), S1A)];
+                    let end = &z3_step_times[&(batch.id, step_group.clone(), E1A)];
+                    uses.push((
+                        ast::Bool::from_bool(&ctx, true),
+                        begin.clone(),
+                        end.clone(),
+                        usage,
+                    ));
+                }
+            }
+            Plan::limit_cumulative(&ctx, &solver, uses.as_slice(), resource.limit);
+        }
+        // 4) The beer is packaged after its last step, on a line that can fill the format.
+        let mut lines = factory
+            .equipments
+            .values()
+            .filter(|equipment| equipment.can(&Capability::Packaging))
+            .collect::<Vec<&Equipment>>();
+        lines.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut packaging_runs = Vec::new();
+        let mut visit_loads: HashMap<(usize, usize), Vec<(ast::Bool, u32)>> = HashMap::new();
+        for batch in batches_needed.values() {
+            let last_step = match batch.steps().last() {
+                Some((step_group, _interval)) => step_group.clone(),
+                None => continue,
+            };
+            let ready = &z3_step_times[&(batch.id, last_step.clone(), E1A)];
+            let emptied = &z3_step_times[&(batch.id, last_step.clone(), S2A)];
+            let last_machine = &z3_step_machine[&(batch.id, last_step)];
+            let brite_tanks = Plan::brite_tanks(factory, batch)
+                .iter()
+                .filter_map(|brite_tank| {
+                    z3_machines[&(Capability::Carbonation, batch.system.clone())]
+                        .values()
+                        .find(|(_machine, equipment)| equipment.name == brite_tank.name)
+                        .map(|(machine, _equipment)| last_machine._eq(machine))
+                })
+                .collect::<Vec<ast::Bool>>();
+            for (format, units) in batch.packaging.iter() {
+                let fills = Plan::packaging_lines(&lines, format, *units);
+                let suited = lines
+                    .iter()
+                    .enumerate()
+                    .filter(|(_line_id, line)| fills.contains(line))
+                    .filter(|(_line_id, line)| {
+                        line.visits.is_empty()
+                            || !Plan::visits_in_reach(
+                                factory,
+                                line,
+                                batch,
+                                format,
+                                *units,
+                                earliest_start,
                             )
-                            .not(),
-                        );
+                            .is_empty()
+                    })
+                    .map(|(line_id, line)| {
+                        let rate = line.packaging_rate(format).unwrap();
+                        (line_id as i64, rate.run_time(*units))
+                    })
+                    .collect::<Vec<(i64, chrono::Duration)>>();
+                if suited.is_empty() {
+                    if !fills.is_empty() {
+                        // The batch can't make a visit of the lines, it is left unpackaged, see
+                        // `Plan::missed_visits`
+                        continue;
                     }
-                    // 3c) limit the number of brew that can happen 'simultanously'
-                    if this_step_group == &StepGroup::Brewing
-                        && other_step_group == &StepGroup::Brewing
-                    {
-                        //     Constraint: there are at least 6 hours between 2 brews
-                        //                 This basically limits it to one brew per day :-(
-                        solver.assert(&ast::Bool::or(
+                    panic!(
+                        "{} x {} of batch {} does not appear to fit on any packaging line",
+                        units,
+                        format.lookup(),
+                        batch.id
+                    );
+                }
+                let name = format!(
+                    "batch: {}, beer: {} format: {}",
+                    batch.id,
+                    batch.beer.name,
+                    format.lookup()
+                );
+                let line = ast::Int::new_const(&ctx, format!("Line for {}", name));
+                let run_start = ast::Int::new_const(&ctx, format!("started packaging {}", name));
+                let run_stop = ast::Int::new_const(&ctx, format!("stopped packaging {}", name));
+                let mut run_time = ast::Int::from_i64(&ctx, suited[0].1.num_seconds());
+                let visit = ast::Int::new_const(&ctx, format!("Visit for {}", name));
+                let mut on_lines = Vec::with_capacity(suited.len());
+                for (line_id, duration) in suited.iter() {
+                    let on_line = line._eq(&ast::Int::from_i64(&ctx, *line_id));
+                    let seconds = ast::Int::from_i64(&ctx, duration.num_seconds());
+                    run_time = on_line.ite(&seconds, &run_time);
+                    let equipment = lines[*line_id as usize];
+                    if !equipment.visits.is_empty() {
+                        //     Constraint: an external service fills from a brite tank
+                        solver.assert(&on_line.implies(&ast::Bool::or(
                             &ctx,
-                            &[&ast::Bool::and(
+                            &brite_tanks.iter().collect::<Vec<&ast::Bool>>(),
+                        )));
+                        let mut in_visits = Vec::new();
+                        for (visit_id, window) in Plan::visits_in_reach(
+                            factory,
+                            equipment,
+                            batch,
+                            format,
+                            *units,
+                            earliest_start,
+                        ) {
+                            let on_visit = visit._eq(&ast::Int::from_i64(&ctx, visit_id as i64));
+                            let opens = ast::Int::from_i64(&ctx, window.start.timestamp());
+                            let closes = ast::Int::from_i64(&ctx, window.end.timestamp());
+                            in_visits.push(ast::Bool::and(
                                 &ctx,
-                                &[
-                                    &ast::Int::add(&ctx, &[&this_next_go, &one_quart_day])
-                                        .ge(other_step_start),
-                                    &ast::Int::add(&ctx, &[&other_next_go, &one_quart_day])
-                                        .ge(this_step_start),
-                                ],
-                            )
-                            .not()],
-                        ))
+                                &[&on_visit, &run_start.ge(&opens), &run_stop.le(&closes)],
+                            ));
+                            visit_loads
+                                .entry((*line_id as usize, visit_id))
+                                .or_default()
+                                .push((
+                                    ast::Bool::and(&ctx, &[&on_line, &on_visit]),
+                                    format.cases(*units),
+                                ));
+                        }
+                        //     Constraint: an external service is only used while it is on site
+                        solver.assert(&on_line.implies(&ast::Bool::or(
+                            &ctx,
+                            &in_visits.iter().collect::<Vec<&ast::Bool>>(),
+                        )));
+                        let wait = ast::Int::sub(&ctx, &[&run_start, ready]);
+                        waits.push(on_line.ite(&wait, &ast::Int::from_i64(&ctx, 0)));
                     }
+                    on_lines.push(on_line);
                 }
-                // 3d) @TODO....The other machine is also occupied from step_stop till next_go
+                //     Constraint: only one of the lines that fill this format is used
+                solver.assert(&ast::Bool::or(
+                    &ctx,
+                    &on_lines.iter().collect::<Vec<&ast::Bool>>(),
+                ));
+                //     Constraint: the beer is done before it is packaged, and it stays in
+                //     its vessel till then
+                solver.assert(&run_start.ge(ready));
+                solver.assert(&run_stop._eq(&ast::Int::add(&ctx, &[&run_start, &run_time])));
+                solver.assert(&emptied.ge(&run_stop));
+                if let Some(due) = batch.due {
+                    let due = ast::Int::from_i64(&ctx, due.timestamp());
+                    // It is packaged when it is due, or as little late as can be (see 7)
+                    late.push(Plan::lateness(&ctx, &run_stop, &due));
+                    //     Constraint: but not long before
+                    if let Some(max_age) = batch.beer.shelf_life() {
+                        let max_age = ast::Int::from_i64(&ctx, max_age.num_seconds());
+                        solver.assert(&ast::Int::add(&ctx, &[&run_start, &max_age]).ge(&due));
+                    }
+                } else {
+                    all_endings.push(run_stop.clone());
+                }
+                packaging_runs.push((batch.id, format.clone(), *units, line, run_start, run_stop));
+            }
+        }
+        for (pos, (this_batch_id, this_format, _, this_line, this_start, this_stop)) in
+            packaging_runs.iter().enumerate()
+        {
+            for (other_batch_id, other_format, _, other_line, other_start, other_stop) in
+                packaging_runs.iter().skip(pos + 1)
+            {
+                // The line is set up again for another beer or format
+                let same_setup = batches_needed[this_batch_id].beer.name
+                    == batches_needed[other_batch_id].beer.name
+                    && this_format == other_format;
+                for (line_id, line) in lines.iter().enumerate() {
+                    let changeover = match line.changeover_time {
+                        Some(changeover_time) if !same_setup => changeover_time.num_seconds(),
+                        _ => 0,
+                    };
+                    let changeover = ast::Int::from_i64(&ctx, changeover);
+                    let line_id = ast::Int::from_i64(&ctx, line_id as i64);
+                    //     Constraint: a line fills one run at a time
+                    solver.assert(
+                        &ast::Bool::and(
+                            &ctx,
+                            &[&this_line._eq(&line_id), &other_line._eq(&line_id)],
+                        )
+                        .implies(&ast::Bool::or(
+                            &ctx,
+                            &[
+                                &ast::Int::add(&ctx, &[this_stop, &changeover]).le(other_start),
+                                &ast::Int::add(&ctx, &[other_stop, &changeover]).le(this_start),
+                            ],
+                        )),
+                    );
+                }
+            }
+        }
+        for ((line_id, visit_id), loads) in visit_loads.iter() {
+            let no_cases = ast::Int::from_i64(&ctx, 0);
+            let cases = loads
+                .iter()
+                .map(|(on_visit, cases)| {
+                    on_visit.ite(&ast::Int::from_i64(&ctx, *cases as i64), &no_cases)
+                })
+                .collect::<Vec<ast::Int>>();
+            let limit = lines[*line_id].visits[*visit_id].cases as i64;
+            //     Constraint: a visit only fills so many cases
+            solver.assert(
+                &ast::Int::add(&ctx, &cases.iter().collect::<Vec<&ast::Int>>())
+                    .le(&ast::Int::from_i64(&ctx, limit)),
+            );
+        }
+        for fleet in factory.keg_fleets.iter() {
+            let runs = packaging_runs
+                .iter()
+                .filter(|(_, format, _, _, _, _)| format == &fleet.format)
+                .collect::<Vec<&PackagingRun>>();
+            let ordered = runs.iter().map(|(_, _, units, _, _, _)| units).sum::<u32>();
+            // More kegs than the fleet will ever have is reported up front (see
+            // Factory::keg_shortages), the kegs it is short are counted as if they were there.
+            let short = ordered.saturating_sub(fleet.most(earliest_start));
+            let availability = fleet.availability(earliest_start);
+            let no_kegs = ast::Int::from_i64(&ctx, 0);
+            for (_, _, _, _, this_start, _) in runs.iter() {
+                let mut clean = ast::Int::from_i64(&ctx, (availability[0].1 + short) as i64);
+                for (moment, kegs) in availability.iter().skip(1) {
+                    let moment = ast::Int::from_i64(&ctx, moment.timestamp());
+                    let kegs = ast::Int::from_i64(&ctx, (*kegs + short) as i64);
+                    clean = this_start.ge(&moment).ite(&kegs, &clean);
+                }
+                let filled = runs
+                    .iter()
+                    .map(|(_, _, units, _, other_start, _)| {
+                        let units = ast::Int::from_i64(&ctx, *units as i64);
+                        other_start.le(this_start).ite(&units, &no_kegs)
+                    })
+                    .collect::<Vec<ast::Int>>();
+                //     Constraint: only the kegs that are clean and in the building are filled
+                solver.assert(
+                    &ast::Int::add(&ctx, &filled.iter().collect::<Vec<&ast::Int>>()).le(&clean),
+                );
+            }
+        }
+        if let Some(crew) = factory.packaging_crew {
+            let mut uses = Vec::new();
+            for (_, _, _, line, run_start, run_stop) in packaging_runs.iter() {
+                for (line_id, equipment) in lines.iter().enumerate() {
+                    let on_line = line._eq(&ast::Int::from_i64(&ctx, line_id as i64));
+                    uses.push((on_line, run_start.clone(), run_stop.clone(), equipment.crew));
+                }
+            }
+            //     Constraint: there are only so many people to run the lines
+            Plan::limit_cumulative(&ctx, &solver, uses.as_slice(), crew);
+        }
+        // 5) The yeast is harvested at the end of the primary fermentation, to pitch the next
+        //    batch of the same strain. The slurry of the batches in progress counts as well.
+        //    Without a viable slurry, it is propagated in time.
+        let slurries = Plan::slurries(factory);
+        let zero = ast::Int::from_i64(&ctx, 0);
+        let one = ast::Int::from_i64(&ctx, 1);
+        let mut pitched = batches_needed
+            .values()
+            .filter_map(|batch| {
+                let strain = factory.yeasts.get(batch.beer.yeast.as_ref()?)?;
+                Some((batch, strain, batch.pitch_step()?))
+            })
+            .collect::<Vec<(&BatchNeed, &YeastStrain, StepGroup)>>();
+        pitched.sort_by_key(|(batch, _, _)| batch.id);
+        let yeast_links = pitched
+            .iter()
+            .map(|(batch, _, _)| {
+                (
+                    batch.id,
+                    ast::Int::new_const(&ctx, format!("Yeast source for batch: {}", batch.id)),
+                    ast::Int::new_const(&ctx, format!("Yeast generation for batch: {}", batch.id)),
+                )
+            })
+            .collect::<Vec<YeastLink>>();
+        let mut propagations = Vec::with_capacity(pitched.len());
+        let mut sooner_propagations = Vec::new();
+        for ((batch, strain, step_group), (_, source, generation)) in
+            pitched.iter().zip(yeast_links.iter())
+        {
+            let pitch = &z3_step_times[&(batch.id, step_group.clone(), S1A)];
+            let propagation = ast::Int::from_i64(&ctx, strain.propagation.num_seconds());
+            let propagated = source._eq(&zero);
+            //     Constraint: propagated yeast is the first generation, the lab starts right away
+            solver.assert(&propagated.implies(&ast::Bool::and(
+                &ctx,
+                &[
+                    &generation._eq(&one),
+                    &pitch.ge(&ast::Int::add(&ctx, &[&start_horizon, &propagation])),
+                ],
+            )));
+            let viability = ast::Int::from_i64(&ctx, strain.viability.num_seconds());
+            let mut origins = vec![propagated.clone()];
+            for ((other, _, other_step_group), (_, _, other_generation)) in
+                pitched.iter().zip(yeast_links.iter())
+            {
+                if other.id == batch.id || other.beer.yeast != batch.beer.yeast {
+                    continue;
+                }
+                let harvest = &z3_step_times[&(other.id, other_step_group.clone(), E1A)];
+                let repitched = source._eq(&ast::Int::from_i64(&ctx, other.id as i64));
+                //     Constraint: the slurry is pitched while it is viable, one generation older
+                solver.assert(&repitched.implies(&ast::Bool::and(
+                    &ctx,
+                    &[
+                        &pitch.ge(harvest),
+                        &pitch.le(&ast::Int::add(&ctx, &[harvest, &viability])),
+                        &generation._eq(&ast::Int::add(&ctx, &[other_generation, &one])),
+                    ],
+                )));
+                origins.push(repitched);
+            }
+            for (slurry_id, (name, slurry)) in slurries.iter().enumerate() {
+                if *name != strain.name {
+                    continue;
+                }
+                let harvested = ast::Int::from_i64(&ctx, slurry.harvested.timestamp());
+                let generation_on_hand = ast::Int::from_i64(&ctx, slurry.generation as i64);
+                let repitched = source._eq(&ast::Int::from_i64(&ctx, -(slurry_id as i64) - 1));
+                //     Constraint: so is the slurry on hand
+                solver.assert(&repitched.implies(&ast::Bool::and(
+                    &ctx,
+                    &[
+                        &pitch.ge(&harvested),
+                        &pitch.le(&ast::Int::add(&ctx, &[&harvested, &viability])),
+                        &generation._eq(&ast::Int::add(&ctx, &[&generation_on_hand, &one])),
+                    ],
+                )));
+                origins.push(repitched);
+            }
+            //     Constraint: the yeast is propagated or harvested from one of the batches
+            solver.assert(&ast::Bool::or(
+                &ctx,
+                &origins.iter().collect::<Vec<&ast::Bool>>(),
+            ));
+            //     Constraint: and it is not repitched too often
+            solver.assert(&generation.le(&ast::Int::from_i64(&ctx, strain.max_generations as i64)));
+            if strain.propagate_if_sooner {
+                sooner_propagations.push(propagated.ite(&one, &zero));
+            } else {
+                propagations.push(propagated.ite(&one, &zero));
+            }
+        }
+        for (pos, (_, source, _)) in yeast_links.iter().enumerate() {
+            for (_, other_source, _) in yeast_links.iter().skip(pos + 1) {
+                //     Constraint: a harvest pitches one batch
+                solver.assert(
+                    &ast::Bool::and(&ctx, &[&source._eq(other_source), &source._eq(&zero).not()])
+                        .not(),
+                );
+            }
+        }
+        // 6) The ingredients of a batch are in the store on its brew day, or it waits till the
+        //    supplier can deliver them. Only the materials the store keeps track of count.
+        let milli = |amount: f32| ast::Int::from_i64(&ctx, (amount * 1000.0).round() as i64);
+        let brew_days = batches_needed
+            .values()
+            .map(|batch| {
+                let (first_step, _) = batch.steps()[0].clone();
+                (batch.id, &z3_step_times[&(batch.id, first_step, S1A)])
+            })
+            .collect::<HashMap<usize, &ast::Int>>();
+        for batch in batches_needed.values() {
+            let brew = brew_days[&batch.id];
+            for ingredient in batch.beer.recipe.ingredients(&batch.system) {
+                let material = match factory.materials.get(&ingredient.name) {
+                    Some(material) => material,
+                    None => continue,
+                };
+                let mut used = Vec::new();
+                for other in batches_needed.values() {
+                    for other_ingredient in other.beer.recipe.ingredients(&other.system) {
+                        if other_ingredient.name == ingredient.name {
+                            used.push(
+                                brew_days[&other.id]
+                                    .le(brew)
+                                    .ite(&milli(other_ingredient.amount), &zero),
+                            );
+                        }
+                    }
+                }
+                let delivered = ast::Int::add(
+                    &ctx,
+                    &[
+                        &start_horizon,
+                        &ast::Int::from_i64(&ctx, material.lead_time.num_seconds()),
+                    ],
+                );
+                //     Constraint: what is brewed till then is on hand, or it is ordered in time
+                solver.assert(&ast::Bool::or(
+                    &ctx,
+                    &[
+                        &ast::Int::add(&ctx, &used.iter().collect::<Vec<&ast::Int>>())
+                            .le(&milli(material.on_hand)),
+                        &brew.ge(&delivered),
+                    ],
+                ));
             }
-            let ooverlaps = overlaps.iter().map(|x| x).collect::<Vec<&ast::Bool>>();
-            solver.assert(&ast::Bool::or(&ctx, ooverlaps.as_slice()));
         }
         // In the future, we might limit the periods where brew, transfer and
         // clean may happen, (officehours/atnight, workdays/weekends/ holidays).
@@ -326,18 +1179,95 @@ impl<'a> Plan<'a> {
         // when the beer is good enough to go to the nex stage are probably earlier.
         // thus the braumeister should have time to fine tune the schedule.
 
+        // 7) A batch that can't be finished by its due date is planned anyway, as little late as
+        //    it can be. That comes first, before the yeast and the makespan.
+        if !late.is_empty() {
+            //     Optimizer: on time
+            solver.minimize(&ast::Int::add(
+                &ctx,
+                &late.iter().collect::<Vec<&ast::Int>>(),
+            ));
+        }
+        if !propagations.is_empty() {
+            //     Optimizer: repitch the yeast, rather than propagate it, even if that takes longer
+            solver.minimize(&ast::Int::add(
+                &ctx,
+                &propagations.iter().collect::<Vec<&ast::Int>>(),
+            ));
+        }
         Plan::optimize(&solver, &ctx, earliest_start, all_endings.as_slice());
+        if !sooner_propagations.is_empty() {
+            //     Optimizer: repitch the yeast of the strains that may be propagated, unless that
+            //     takes longer
+            solver.minimize(&ast::Int::add(
+                &ctx,
+                &sooner_propagations.iter().collect::<Vec<&ast::Int>>(),
+            ));
+        }
+        // 8) The batches that are due, and those that go to a visiting canner, are brewed as late
+        //    as they can be, so that they are fresh.
+        if !waits.is_empty() {
+            //     Optimizer: just in time
+            solver.minimize(&ast::Int::add(
+                &ctx,
+                &waits.iter().collect::<Vec<&ast::Int>>(),
+            ));
+        }
+        if let Some(tap_list) = &factory.tap_list {
+            Plan::keep_taps_pouring(
+                &ctx,
+                &solver,
+                factory,
+                tap_list,
+                batches_needed,
+                &z3_step_times,
+                earliest_start,
+            );
+        }
         Plan::process_solution(
             factory,
             batches_needed,
             solver,
-            z3_machines,
-            z3_step_machine,
-            z3_step_times,
+            StepVariables {
+                machines: z3_machines,
+                step_machine: z3_step_machine,
+                step_times: z3_step_times,
+            },
+            packaging_runs,
+            yeast_links,
             all_endings.as_slice(),
         )
     }
 
+    /// How long `stop` is after `due`, or nothing when it isn't.
+    fn lateness<'ctx>(
+        ctx: &'ctx Context,
+        stop: &ast::Int<'ctx>,
+        due: &ast::Int<'ctx>,
+    ) -> ast::Int<'ctx> {
+        stop.gt(due).ite(
+            &ast::Int::sub(ctx, &[stop, due]),
+            &ast::Int::from_i64(ctx, 0),
+        )
+    }
+
+    /// The slurry on hand of every strain, in a fixed order: the yeast source of a batch that is
+    /// pitched with the slurry at position `n` is `-n - 1`.
+    fn slurries(factory: &Factory) -> Vec<(&str, &Slurry)> {
+        let mut slurries = factory
+            .yeasts
+            .values()
+            .flat_map(|strain| {
+                strain
+                    .slurries
+                    .iter()
+                    .map(move |slurry| (strain.name.as_str(), slurry))
+            })
+            .collect::<Vec<(&str, &Slurry)>>();
+        slurries.sort_by_key(|(name, slurry)| (*name, slurry.batch));
+        slurries
+    }
+
     fn optimize<'ctx>(
         solver: &'ctx Optimize,
         ctx: &'ctx Context,
@@ -360,18 +1290,100 @@ impl<'a> Plan<'a> {
         solver.minimize(&longest_duration_of_all_tasks);
     }
 
+    /// Try to keep the tap list full at the moments it is checked over its horizon. A beer is on
+    /// tap when one of its batches is finished, for the window of the tap list, and while its
+    /// stock on hand lasts. This is not a hard constraint: at the start of the planning the
+    /// beers that are brewed now are not finished yet.
+    fn keep_taps_pouring<'ctx>(
+        ctx: &'ctx Context,
+        solver: &Optimize<'ctx>,
+        factory: &Factory,
+        tap_list: &TapList,
+        batches_needed: &HashMap<usize, BatchNeed>,
+        z3_step_times: &HashMap<(usize, StepGroup, &'static str), ast::Int<'ctx>>,
+        earliest_start: DateTime<Utc>,
+    ) {
+        let stock_on_tap = factory.stock_on_tap(earliest_start);
+        let mut finished: HashMap<&str, (&Beer, Vec<&ast::Int>)> = HashMap::new();
+        for (beer, _till) in stock_on_tap.iter() {
+            finished.insert(beer.name.as_str(), (*beer, Vec::new()));
+        }
+        for batch in batches_needed.values() {
+            if let Some((step_group, _interval)) = batch.steps().last() {
+                finished
+                    .entry(batch.beer.name.as_str())
+                    .or_insert((batch.beer, Vec::new()))
+                    .1
+                    .push(&z3_step_times[&(batch.id, step_group.clone(), E1A)]);
+            }
+        }
+        let mut beers = finished
+            .into_values()
+            .collect::<Vec<(&Beer, Vec<&ast::Int>)>>();
+        beers.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+        let window = ast::Int::from_i64(ctx, tap_list.window.num_seconds());
+        let (one, none) = (ast::Int::from_i64(ctx, 1), ast::Int::from_i64(ctx, 0));
+        for moment in tap_list.moments(earliest_start) {
+            let at = ast::Int::from_i64(ctx, moment.timestamp());
+            let on_tap = beers
+                .iter()
+                .map(|(beer, finishes)| {
+                    let stocked = stock_on_tap.iter().any(|(other, till)| {
+                        other.name == beer.name
+                            && match till {
+                                Some(till) => moment < *till,
+                                None => true,
+                            }
+                    });
+                    let mut pouring = finishes
+                        .iter()
+                        .map(|finish| {
+                            let empty = ast::Int::add(ctx, &[*finish, &window]);
+                            ast::Bool::and(ctx, &[&finish.le(&at), &empty.gt(&at)])
+                        })
+                        .collect::<Vec<ast::Bool>>();
+                    pouring.push(ast::Bool::from_bool(ctx, stocked));
+                    let pouring = ast::Bool::or(ctx, &pouring.iter().collect::<Vec<&ast::Bool>>());
+                    (*beer, pouring.ite(&one, &none))
+                })
+                .collect::<Vec<(&Beer, ast::Int)>>();
+            let count = |requirement: Option<&TapRequirement>| {
+                let mut taps = vec![&none];
+                for (beer, tap) in on_tap.iter() {
+                    let counts = match requirement {
+                        Some(requirement) => requirement.matches(beer),
+                        None => true,
+                    };
+                    if counts {
+                        taps.push(tap);
+                    }
+                }
+                ast::Int::add(ctx, &taps)
+            };
+            //     Soft constraint: enough beers on tap, and enough of every kind
+            let minimum = ast::Int::from_i64(ctx, tap_list.minimum as i64);
+            solver.assert_soft(&count(None).ge(&minimum), 1, None);
+            for requirement in tap_list.requirements.iter() {
+                let minimum = ast::Int::from_i64(ctx, requirement.count as i64);
+                solver.assert_soft(&count(Some(requirement)).ge(&minimum), 1, None);
+            }
+        }
+    }
+
     fn process_solution<'ctx>(
         factory: &'a Factory,
         batches_needed: &'a HashMap<usize, BatchNeed<'a>>,
         solver: Optimize<'ctx>,
-        z3_machines: HashMap<
-            (EquipmentGroup, Capacity),
-            HashMap<usize, (ast::Int<'ctx>, Equipment)>,
-        >,
-        z3_step_machine: HashMap<(usize, StepGroup), ast::Int<'ctx>>,
-        z3_step_times: HashMap<(usize, StepGroup, &'static str), ast::Int<'ctx>>,
+        variables: StepVariables<'ctx>,
+        packaging_runs: Vec<PackagingRun<'ctx>>,
+        yeast_links: Vec<YeastLink<'ctx>>,
         all_endings: &[ast::Int<'ctx>],
     ) -> Vec<Plan<'a>> {
+        let StepVariables {
+            machines: z3_machines,
+            step_machine: z3_step_machine,
+            step_times: z3_step_times,
+        } = variables;
         let mut machine_lookup = HashMap::with_capacity(factory.equipments.len());
         for (k, (_int, equ)) in z3_machines.values().flatten() {
             machine_lookup.insert(*k, equ);
@@ -416,7 +1428,6 @@ impl<'a> Plan<'a> {
                         .unwrap();
                     let machine_value = model.eval(machine_step, true).unwrap().as_i64().unwrap();
                     let equipment = machine_lookup.get(&(machine_value as usize)).unwrap();
-                    let equipment_2 = factory.equipments.values().nth(1).unwrap();
                     let ts_value = model.eval(var, true).unwrap().as_i64().unwrap();
                     let ts = DateTime::<Utc>::from_utc(
                         NaiveDateTime::from_timestamp_opt(ts_value, 0).unwrap(),
@@ -447,7 +1458,7 @@ impl<'a> Plan<'a> {
                                 ),
                             );
                         }
-                        Some((_equipment, ts1a, te1a, ts2a, ts1f, other_equipment)) => {
+                        Some((_equipment, ts1a, te1a, ts2a, ts1f, _other_equipment)) => {
                             match *label {
                                 S1A => {
                                     *ts1a = Some(ts);
@@ -457,7 +1468,6 @@ impl<'a> Plan<'a> {
                                 }
                                 S2A => {
                                     *ts2a = Some(ts);
-                                    *other_equipment = Some(equipment_2.clone());
                                 }
                                 S1F => {
                                     *ts1f = Some(ts);
@@ -467,6 +1477,30 @@ impl<'a> Plan<'a> {
                         }
                     }
                 }
+                // The batch is transfered to the machine of its next step, unless it
+                // stays in the same vessel.
+                let mut stays_in_vessel = HashSet::new();
+                for batch in batches_needed.values() {
+                    let steps = batch.steps();
+                    for pair in steps.windows(2) {
+                        let (this_step_group, next_step_group) = (&pair[0].0, &pair[1].0);
+                        let this_equipment = events
+                            .get(&(batch.id, this_step_group.clone()))
+                            .and_then(|event| event.0.clone());
+                        let next_equipment = events
+                            .get(&(batch.id, next_step_group.clone()))
+                            .and_then(|event| event.0.clone());
+                        if next_step_group.stays_in_vessel_after(this_step_group)
+                            || this_equipment == next_equipment
+                        {
+                            stays_in_vessel.insert((batch.id, this_step_group.clone()));
+                            continue;
+                        }
+                        if let Some(event) = events.get_mut(&(batch.id, this_step_group.clone())) {
+                            event.5 = next_equipment;
+                        }
+                    }
+                }
                 // Now we can build a Vec<Plan> with the known actions
                 // In the future this could be refactored. The Plan struct
                 // might be replaced with the value type that we use in the events
@@ -476,7 +1510,7 @@ impl<'a> Plan<'a> {
                 for event in events.iter() {
                     let (
                         (batch_id, step_group),
-                        (equipment, ts1a, te1a, ts2a, ts1f, other_equipment),
+                        (equipment, ts1a, te1a, ts2a, _ts1f, other_equipment),
                     ) = event;
                     let batch = batches_needed.get(batch_id).unwrap();
                     solutions.push(Plan::new(
@@ -495,30 +1529,253 @@ impl<'a> Plan<'a> {
                             step_group.clone(),
                             Action::Transfer(
                                 equipment.as_ref().unwrap().clone(),
-                                other_equipment.as_ref().unwrap().clone(),
+                                Box::new(other_equipment.as_ref().unwrap().clone()),
+                                vec![],
                             ),
                             te1a.unwrap(),
                             ts2a.unwrap(),
                         ));
                         plan_id += 1;
                     }
+                    if !stays_in_vessel.contains(&(*batch_id, step_group.clone())) {
+                        let equipment = equipment.as_ref().unwrap();
+                        // The clean is chosen for the batch that uses the vessel next, the last
+                        // one in the vessel gets a full CIP.
+                        let next_batch = events
+                            .iter()
+                            .filter(|(_, (other, other_start, ..))| {
+                                other.as_ref().map(|other| &other.name) == Some(&equipment.name)
+                                    && *other_start >= *ts2a
+                            })
+                            .min_by_key(|(_, (_, other_start, ..))| *other_start)
+                            .map(|((other_batch_id, _), _)| {
+                                batches_needed.get(other_batch_id).unwrap()
+                            });
+                        let level = match next_batch {
+                            Some(next_batch) => {
+                                factory.clean_level(equipment, batch.beer, next_batch.beer)
+                            }
+                            None => CleanLevel::FullCip,
+                        };
+                        let full_cip = equipment
+                            .cip_time
+                            .unwrap_or_else(|| step_group.post_process_time(&batch.system));
+                        let end = ts2a.unwrap() + level.duration(full_cip);
+                        solutions.push(Plan::new(
+                            plan_id,
+                            batch,
+                            step_group.clone(),
+                            Action::Clean(equipment.clone(), level, vec![]),
+                            ts2a.unwrap(),
+                            end,
+                        ));
+                        plan_id += 1;
+                    }
+                }
+
+                // A quarantined vessel is decontaminated right before the first beer
+                // without the segregated tag goes in.
+                let mut first_uses: HashMap<&String, &Plan> = HashMap::new();
+                for plan in solutions.iter() {
+                    if let Action::Process(equipment) = &plan.action {
+                        let first = first_uses.entry(&equipment.name).or_insert(plan);
+                        if plan.start < first.start {
+                            *first = plan;
+                        }
+                    }
+                }
+                let mut decontaminations = Vec::new();
+                for plan in first_uses.values() {
+                    if let Action::Process(equipment) = &plan.action {
+                        if factory.segregation.quarantined(equipment, plan.batch.beer) {
+                            let full_cip = equipment.cip_time.unwrap_or_else(|| {
+                                plan.step_group.post_process_time(&plan.batch.system)
+                            });
+                            decontaminations.push(Plan::new(
+                                plan_id,
+                                plan.batch,
+                                plan.step_group.clone(),
+                                Action::Clean(
+                                    equipment.clone(),
+                                    CleanLevel::Decontamination,
+                                    vec![],
+                                ),
+                                plan.start - CleanLevel::Decontamination.duration(full_cip),
+                                plan.start,
+                            ));
+                            plan_id += 1;
+                        }
+                    }
+                }
+                solutions.extend(decontaminations);
+                let mut lines = factory
+                    .equipments
+                    .values()
+                    .filter(|equipment| equipment.can(&Capability::Packaging))
+                    .collect::<Vec<&Equipment>>();
+                lines.sort_by(|a, b| a.name.cmp(&b.name));
+                for (batch_id, format, units, line, run_start, run_stop) in packaging_runs {
+                    let line_id = model.eval(&line, true).unwrap().as_i64().unwrap();
+                    let start = model.eval(&run_start, true).unwrap().as_i64().unwrap();
+                    let stop = model.eval(&run_stop, true).unwrap().as_i64().unwrap();
                     solutions.push(Plan::new(
                         plan_id,
-                        batch,
-                        step_group.clone(),
-                        Action::Clean(equipment.as_ref().unwrap().clone()),
-                        ts2a.unwrap(),
-                        ts1f.unwrap(),
+                        batches_needed.get(&batch_id).unwrap(),
+                        StepGroup::Packaging,
+                        Action::Package(lines[line_id as usize].clone(), format, units),
+                        Utc.timestamp_opt(start, 0).unwrap(),
+                        Utc.timestamp_opt(stop, 0).unwrap(),
                     ));
                     plan_id += 1;
                 }
-
-                //println!(">{:?}", solutions);
-
-                return solutions;
-            }
+                let eval = |var: &ast::Int| model.eval(var, true).unwrap().as_i64().unwrap();
+                let slurries = Plan::slurries(factory);
+                for (batch_id, source, generation) in yeast_links {
+                    let batch = batches_needed.get(&batch_id).unwrap();
+                    let yeast = batch.beer.yeast.clone().unwrap();
+                    let pitch_step = batch.pitch_step().unwrap();
+                    let pitch = eval(&z3_step_times[&(batch_id, pitch_step.clone(), S1A)]);
+                    let (action, start) = match eval(&source) {
+                        0 => {
+                            let propagation = factory.yeasts[&yeast].propagation.num_seconds();
+                            (Action::Propagate(yeast), pitch - propagation)
+                        }
+                        slurry_id if slurry_id < 0 => {
+                            let (_, slurry) = slurries[(-slurry_id - 1) as usize];
+                            (
+                                Action::Pitch(yeast, slurry.batch, eval(&generation) as u32),
+                                slurry.harvested.timestamp(),
+                            )
+                        }
+                        from => {
+                            let from = from as usize;
+                            let harvest_step = batches_needed[&from].pitch_step().unwrap();
+                            let harvest = eval(&z3_step_times[&(from, harvest_step, E1A)]);
+                            (
+                                Action::Pitch(yeast, from, eval(&generation) as u32),
+                                harvest,
+                            )
+                        }
+                    };
+                    solutions.push(Plan::new(
+                        plan_id,
+                        batch,
+                        pitch_step,
+                        action,
+                        Utc.timestamp_opt(start, 0).unwrap(),
+                        Utc.timestamp_opt(pitch, 0).unwrap(),
+                    ));
+                    plan_id += 1;
+                }
+                Plan::reserve_shared(factory, &mut solutions);
+
+                //println!(">{:?}", solutions);
+
+                return solutions;
+            }
         };
     }
+    /// Each use takes an amount of a resource from `begin` till `end`, when it is `needed`.
+    /// Together they may never take more than the limit. It is enough to count what is in use
+    /// when each of them begins.
+    fn limit_cumulative<'ctx>(
+        ctx: &'ctx Context,
+        solver: &Optimize<'ctx>,
+        uses: &[(ast::Bool<'ctx>, ast::Int<'ctx>, ast::Int<'ctx>, u32)],
+        limit: u32,
+    ) {
+        let zero = ast::Int::from_i64(ctx, 0);
+        for (pos, (needed, begin, _end, amount)) in uses.iter().enumerate() {
+            let in_use = uses
+                .iter()
+                .enumerate()
+                .filter(|(other_pos, _)| *other_pos != pos)
+                .map(
+                    |(_, (other_needed, other_begin, other_end, other_amount))| {
+                        ast::Bool::and(
+                            ctx,
+                            &[other_needed, &other_begin.le(begin), &begin.lt(other_end)],
+                        )
+                        .ite(&ast::Int::from_i64(ctx, *other_amount as i64), &zero)
+                    },
+                )
+                .collect::<Vec<ast::Int>>();
+            if in_use.is_empty() {
+                continue;
+            }
+            let available = ast::Int::from_i64(ctx, limit as i64 - *amount as i64);
+            //     Constraint: the others that are busy leave enough for this one
+            solver.assert(&needed.implies(
+                &ast::Int::add(ctx, &in_use.iter().collect::<Vec<&ast::Int>>()).le(&available),
+            ));
+        }
+    }
+
+    /// Give every transfer and clean the shared equipment it needs. The solver made sure that
+    /// there is never more of it in use than there is, so the first one that is free will do.
+    /// A transfer or clean that finds all of them busy would be double booked, so it stops there.
+    fn reserve_shared(factory: &Factory, solutions: &mut [Plan]) {
+        let shared = factory.shared_equipment();
+        if shared.is_empty() {
+            return;
+        }
+        let mut order = (0..solutions.len()).collect::<Vec<usize>>();
+        order.sort_by_key(|pos| solutions[*pos].start);
+        let mut busy_till: HashMap<&String, DateTime<Utc>> = HashMap::new();
+        for pos in order {
+            let plan = &mut solutions[pos];
+            let groups = shared
+                .keys()
+                .filter(|equipment_group| match &plan.action {
+                    Action::Transfer(..) => {
+                        let steps = plan.batch.steps();
+                        let next = steps
+                            .iter()
+                            .skip_while(|(step_group, _)| step_group != &plan.step_group)
+                            .nth(1);
+                        match next {
+                            Some((next, _)) => equipment_group.used_for_transfer(
+                                &plan.step_group.capability(),
+                                &next.capability(),
+                            ),
+                            None => false,
+                        }
+                    }
+                    Action::Clean(..) => equipment_group.used_for_clean(),
+                    _ => false,
+                })
+                .collect::<Vec<&EquipmentGroup>>();
+            let mut reserved = Vec::with_capacity(groups.len());
+            for equipment_group in groups {
+                let equipments = &shared[equipment_group];
+                let free =
+                    equipments
+                        .iter()
+                        .find(|equipment| match busy_till.get(&equipment.name) {
+                            Some(till) => till <= &plan.start,
+                            None => true,
+                        });
+                let equipment = match free {
+                    Some(equipment) => equipment,
+                    None => panic!(
+                        "{} of batch {} does not appear to have a free {}, it would be double booked",
+                        plan.action.lookup(),
+                        plan.batch.id,
+                        equipment_group.lookup()
+                    ),
+                };
+                busy_till.insert(&equipment.name, plan.end);
+                reserved.push((*equipment).clone());
+            }
+            match &mut plan.action {
+                Action::Transfer(_, _, shared) | Action::Clean(_, _, shared) => {
+                    shared.extend(reserved);
+                }
+                _ => {}
+            }
+        }
+    }
+
     fn limit_further<'ctx>(
         solver: &'ctx Optimize,
         assumptions: &'ctx [ast::Bool<'ctx>],
@@ -552,6 +1809,334 @@ impl<'a> Plan<'a> {
         }
     }
 
+    /// The brite tanks that the batch can be carbonated in, as its last step. An external
+    /// service, like a mobile canner, only fills beer that is carbonated and in a brite tank.
+    fn brite_tanks<'b>(factory: &'b Factory, batch: &BatchNeed) -> Vec<&'b Equipment> {
+        match batch.steps().last() {
+            Some((StepGroup::Carbonation, _interval)) => factory
+                .equipments
+                .values()
+                .filter(|equipment| {
+                    equipment.equipment_group == EquipmentGroup::BriteTank
+                        && equipment.capacity == batch.system
+                        && factory.segregation.allows(batch.beer, equipment)
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// The lines that are willing to fill the packages in one run.
+    fn packaging_lines<'b>(
+        lines: &[&'b Equipment],
+        format: &PackageFormat,
+        units: u32,
+    ) -> Vec<&'b Equipment> {
+        lines
+            .iter()
+            .filter(|line| match line.packaging_rate(format) {
+                Some(rate) => units >= rate.minimum_run,
+                None => false,
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn missed_visit(
+        batch: &BatchNeed,
+        format: &PackageFormat,
+        units: u32,
+        lines: &[&Equipment],
+    ) -> String {
+        let mut names = lines
+            .iter()
+            .map(|line| line.name.clone())
+            .collect::<Vec<String>>();
+        names.sort();
+        format!(
+            "Batch {} of {} misses every visit of {} for {} x {}",
+            batch.id,
+            batch.beer.label(),
+            names.join(", "),
+            units,
+            format.lookup(),
+        )
+    }
+
+    /// The visits of a line that a packaging run of the batch can still make, by their index.
+    /// The batch is ready when all of its steps are done, at the earliest, and it can only make
+    /// a visit when it ends in a brite tank.
+    fn visits_in_reach<'b>(
+        factory: &Factory,
+        line: &'b Equipment,
+        batch: &BatchNeed,
+        format: &PackageFormat,
+        units: u32,
+        earliest_start: DateTime<Utc>,
+    ) -> Vec<(usize, &'b Visit)> {
+        let run_time = match line.packaging_rate(format) {
+            Some(rate) => rate.run_time(units),
+            None => return vec![],
+        };
+        if Plan::brite_tanks(factory, batch).is_empty() {
+            return vec![];
+        }
+        let ready = batch
+            .steps()
+            .iter()
+            .fold(earliest_start, |ready, (_step_group, interval)| {
+                ready + interval.duration()
+            });
+        line.visits
+            .iter()
+            .enumerate()
+            .filter(|(_visit_id, visit)| visit.fits(&ready, run_time, format.cases(units)))
+            .collect()
+    }
+
+    /// Report the packaging that can't be ready for any visit of the external services, like a
+    /// mobile canner, that could fill it. The planner leaves that packaging out.
+    pub fn missed_visits(
+        factory: &Factory,
+        batches_needed: &HashMap<usize, BatchNeed>,
+        earliest_start: DateTime<Utc>,
+    ) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let lines = factory.equipments.values().collect::<Vec<&Equipment>>();
+        let mut batches = batches_needed.values().collect::<Vec<&BatchNeed>>();
+        batches.sort_by_key(|batch| batch.id);
+        for batch in batches {
+            for (format, units) in batch.packaging.iter() {
+                let suited = Plan::packaging_lines(&lines, format, *units);
+                if suited.is_empty() || suited.iter().any(|line| line.visits.is_empty()) {
+                    continue;
+                }
+                if suited.iter().all(|line| {
+                    Plan::visits_in_reach(factory, line, batch, format, *units, earliest_start)
+                        .is_empty()
+                }) {
+                    warnings.push(Plan::missed_visit(batch, format, *units, &suited));
+                }
+            }
+        }
+        warnings
+    }
+
+    /// The beers that pour at a moment: those with a batch that is finished, for the window
+    /// that a batch is on tap.
+    pub fn on_tap(
+        planning: &[Plan<'a>],
+        window: chrono::Duration,
+        at: DateTime<Utc>,
+    ) -> Vec<&'a Beer> {
+        let mut finished: HashMap<usize, (&'a Beer, DateTime<Utc>)> = HashMap::new();
+        for plan in planning {
+            if let Action::Process(_) = plan.action {
+                let entry = finished
+                    .entry(plan.batch.id)
+                    .or_insert((plan.batch.beer, plan.end));
+                entry.1 = entry.1.max(plan.end);
+            }
+        }
+        let mut beers = finished
+            .values()
+            .filter(|(_beer, end)| end <= &at && at < *end + window)
+            .map(|(beer, _end)| *beer)
+            .collect::<Vec<&Beer>>();
+        beers.sort_by(|a, b| a.name.cmp(&b.name));
+        beers.dedup_by(|a, b| a.name == b.name);
+        beers
+    }
+
+    /// Warn about the days that the tap list falls short, over its horizon. The stock on hand
+    /// pours while it lasts.
+    pub fn tap_list_warnings(
+        planning: &[Plan],
+        factory: &Factory,
+        tap_list: &TapList,
+        earliest_start: DateTime<Utc>,
+    ) -> Vec<String> {
+        let stock_on_tap = factory.stock_on_tap(earliest_start);
+        let mut warnings = Vec::new();
+        for day in 0..=tap_list.horizon.num_days() {
+            let at = earliest_start + chrono::Duration::days(day);
+            let mut on_tap = Plan::on_tap(planning, tap_list.window, at);
+            for (beer, till) in stock_on_tap.iter() {
+                match till {
+                    Some(till) if at >= *till => {}
+                    _ => on_tap.push(beer),
+                }
+            }
+            on_tap.sort_by(|a, b| a.name.cmp(&b.name));
+            on_tap.dedup_by(|a, b| a.name == b.name);
+            let shortfalls = tap_list.shortfalls(on_tap.as_slice());
+            if !shortfalls.is_empty() {
+                warnings.push(format!(
+                    "On {} the taproom has {}",
+                    at.format("%Y-%m-%d"),
+                    shortfalls.join(", ")
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// What to order from the suppliers, and by when, so that the materials that the store
+    /// keeps track of are in on the brew days. The batches are brewed in order, and each takes
+    /// what is left in the store first. A batch that is brewed before its materials can arrive
+    /// is flagged.
+    pub fn material_requirements(
+        factory: &Factory,
+        planning: &[Plan],
+        earliest_start: DateTime<Utc>,
+    ) -> Vec<String> {
+        let mut brew_days: HashMap<usize, &Plan> = HashMap::new();
+        for plan in planning {
+            if let Action::Process(_) = plan.action {
+                let first = brew_days.entry(plan.batch.id).or_insert(plan);
+                if plan.start < first.start {
+                    *first = plan;
+                }
+            }
+        }
+        let mut brews = brew_days.into_values().collect::<Vec<&Plan>>();
+        brews.sort_by_key(|plan| (plan.start, plan.batch.id));
+        let mut left = factory
+            .materials
+            .values()
+            .map(|material| (material.name.as_str(), material.on_hand))
+            .collect::<HashMap<&str, f32>>();
+        let mut requirements = Vec::new();
+        for plan in brews {
+            let batch = plan.batch;
+            for ingredient in batch.beer.recipe.ingredients(&batch.system) {
+                let material = match factory.materials.get(&ingredient.name) {
+                    Some(material) => material,
+                    None => continue,
+                };
+                let on_hand = left.get_mut(material.name.as_str()).unwrap();
+                if ingredient.amount > *on_hand {
+                    let short = ingredient.amount - *on_hand;
+                    let order_by = plan.start - material.lead_time;
+                    if order_by < earliest_start {
+                        requirements.push(format!(
+                            "Batch {} of {} is brewed on {}, before {:.1} {} of {} can arrive on {}",
+                            batch.id,
+                            batch.beer.label(),
+                            plan.start.format("%Y-%m-%d %H:%M"),
+                            short,
+                            material.unit,
+                            material.name,
+                            (earliest_start + material.lead_time).format("%Y-%m-%d"),
+                        ));
+                    } else {
+                        requirements.push(format!(
+                            "Order {:.1} {} of {} from {} by {}, for batch {} of {} on {}",
+                            short,
+                            material.unit,
+                            material.name,
+                            material.supplier,
+                            order_by.format("%Y-%m-%d"),
+                            batch.id,
+                            batch.beer.label(),
+                            plan.start.format("%Y-%m-%d %H:%M"),
+                        ));
+                    }
+                }
+                *on_hand = (*on_hand - ingredient.amount).max(0.0);
+            }
+        }
+        requirements
+    }
+
+    /// Warn about the batches that are finished after they are due: the end of their last step,
+    /// or of their last packaging run.
+    pub fn late_warnings(planning: &[Plan]) -> Vec<String> {
+        let mut finished: HashMap<usize, &Plan> = HashMap::new();
+        for plan in planning {
+            if let Action::Process(_) | Action::Package(_, _, _) = plan.action {
+                let last = finished.entry(plan.batch.id).or_insert(plan);
+                if plan.end > last.end {
+                    *last = plan;
+                }
+            }
+        }
+        let mut late = finished
+            .into_values()
+            .filter(|plan| matches!(plan.batch.due, Some(due) if plan.end > due))
+            .collect::<Vec<&Plan>>();
+        late.sort_by_key(|plan| plan.batch.id);
+        late.iter()
+            .map(|plan| {
+                format!(
+                    "Batch {} of {} is finished on {}, it is due on {}",
+                    plan.batch.id,
+                    plan.batch.beer.label(),
+                    plan.end.format("%Y-%m-%d %H:%M"),
+                    plan.batch.due.unwrap().format("%Y-%m-%d %H:%M"),
+                )
+            })
+            .collect()
+    }
+
+    /// Warn about the steps that had to wait for the downtime of their equipment, because they
+    /// don't fit in before it. A step could have started as soon as its batch could start and
+    /// the vessel was free, it keeps the vessel till it is transfered out and cleaned.
+    pub fn downtime_warnings(planning: &[Plan], earliest_start: DateTime<Utc>) -> Vec<String> {
+        let uses = |plan: &Plan, equipment: &Equipment| match &plan.action {
+            Action::Process(other)
+            | Action::Transfer(other, _, _)
+            | Action::Clean(other, _, _)
+            | Action::Maintain(other, _)
+            | Action::Package(other, _, _) => other.name == equipment.name,
+            Action::Propagate(_) | Action::Pitch(_, _, _) => false,
+        };
+        let mut warnings = Vec::new();
+        for plan in planning {
+            if let Action::Process(equipment) = &plan.action {
+                let batch_start = planning
+                    .iter()
+                    .filter(|other| other.batch.id == plan.batch.id)
+                    .filter(|other| matches!(other.action, Action::Process(_)))
+                    .map(|other| other.start)
+                    .min()
+                    .unwrap_or(plan.start);
+                let released = planning
+                    .iter()
+                    .filter(|other| other.batch.id == plan.batch.id)
+                    .filter(|other| other.step_group == plan.step_group && uses(other, equipment))
+                    .map(|other| other.end)
+                    .max()
+                    .unwrap_or(plan.end);
+                let earliest = earliest_start + (plan.start - batch_start);
+                for downtime in &equipment.downtime {
+                    if downtime.end <= earliest || plan.start < downtime.end {
+                        continue;
+                    }
+                    let free = planning
+                        .iter()
+                        .filter(|other| uses(other, equipment) && other.end <= downtime.start)
+                        .map(|other| other.end)
+                        .max()
+                        .map_or(earliest, |free| free.max(earliest));
+                    if free < downtime.start && free + (released - plan.start) > downtime.start {
+                        warnings.push(format!(
+                            "Batch {} of {} does not fit in {} before {} ({}), {} is moved till {}",
+                            plan.batch.id,
+                            plan.batch.beer.label(),
+                            equipment.name,
+                            downtime.start.format("%Y-%m-%d %H:%M"),
+                            downtime.reason,
+                            plan.step_group.lookup(),
+                            plan.start.format("%Y-%m-%d %H:%M"),
+                        ));
+                    }
+                }
+            }
+        }
+        warnings
+    }
+
     pub fn sort_by_step_group(planning: &'a [Plan<'a>]) -> HashMap<String, Vec<&'a Plan<'a>>> {
         Plan::sort_by_xxxx(planning, |plan| plan.step_group.lookup().to_string())
     }
@@ -585,9 +2170,9 @@ impl<'a> Plan<'a> {
                 }
             }
         }
-        // Put steps in each sequence in a logical order: from brew->aging
+        // Put steps in each sequence in a logical order: the sequence of the recipe
         for seq in out.values_mut() {
-            seq.sort_by(|a, b| a.step_group.cmp(&b.step_group));
+            seq.sort_by_key(|plan| (plan.batch.step_position(&plan.step_group), plan.start));
         }
 
         out
@@ -601,7 +2186,7 @@ impl<'a> Plan<'a> {
         let mut blocks = Vec::with_capacity(planning.len());
         for (_ids, plans) in sorted.iter() {
             let first = plans.get(0).unwrap();
-            let name = first.batch.beer.name.clone();
+            let name = first.batch.beer.label();
             let mut prev = None;
             let children = plans
                 .iter()
@@ -689,10 +2274,51 @@ mod tests {
     use crate::action;
     use crate::batchneed;
     use crate::beer;
-    use crate::equipment;
-    // use crate::factory;
+    use crate::beer::Beer;
     use crate::capacity;
+    use crate::clean_level;
+    use crate::cumulative_resource::{Consumption, CumulativeResource};
+    use crate::equipment;
+    use crate::equipment_group;
+    use crate::equipment_group::EquipmentGroup;
+    use crate::factory;
+    use crate::interval::Interval;
+    use crate::inventory::{Depletion, Stock};
+    use crate::keg_fleet::KegFleet;
+    use crate::maintenance;
+    use crate::material;
+    use crate::material::Ingredient;
+    use crate::package_format;
+    use crate::packaging::PackagingRate;
+    use crate::recipe;
+    use crate::recipe::Recipe;
+    use crate::segregation::SegregationRule;
     use crate::step_group;
+    use crate::steps::Steps;
+    use crate::style;
+    use crate::style::Style;
+    use crate::volume::Volume;
+    use crate::yeast;
+
+    /// The moment the plans of the tests are made.
+    fn mock_now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2020, 12, 30, 8, 0, 0).unwrap()
+    }
+
+    /// Batches 1 till `count` of 10 gallons of the beer on the 10 gallon system, each passed
+    /// through `with` to add packaging or a due date.
+    fn mock_batches<'b>(
+        beer: &'b Beer,
+        count: usize,
+        with: impl Fn(BatchNeed<'b>) -> BatchNeed<'b>,
+    ) -> HashMap<usize, BatchNeed<'b>> {
+        (1..=count)
+            .map(|id| {
+                let batch = BatchNeed::new(id, beer, Capacity::G10, Volume::GallonUS(10.0));
+                (id, with(batch))
+            })
+            .collect()
+    }
 
     #[test]
     fn test_plan_mocks() {
@@ -711,6 +2337,1254 @@ mod tests {
         assert!(plan.start < plan.end);
     }
 
+    #[test]
+    fn test_plan_sort_follows_recipe_sequence() {
+        let beer = Beer::new(
+            "Kegged after carbonation".to_string(),
+            style::mock::mock_blonde_ale(),
+            Recipe::new(
+                Capacity::G10,
+                Volume::GallonUS(10.0),
+                Steps::from_sequence(vec![
+                    (StepGroup::Carbonation, Interval::Days(2)),
+                    (StepGroup::Aging, Interval::Weeks(2)),
+                ]),
+            ),
+        );
+        let batchneed = batchneed::BatchNeed::new(1, &beer, Capacity::G10, Volume::GallonUS(10.0));
+        let unitank = Equipment::new(
+            "Unitank".to_string(),
+            Capacity::G10,
+            EquipmentGroup::Unitank,
+        );
+        let brite_tank = Equipment::new(
+            "Brite Tank".to_string(),
+            Capacity::G10,
+            EquipmentGroup::BriteTank,
+        );
+        let planning = vec![
+            mock::mock_plan(unitank, StepGroup::Aging, &batchneed),
+            mock::mock_plan(brite_tank, StepGroup::Carbonation, &batchneed),
+        ];
+        let sorted = Plan::sort_by_batch(planning.as_slice());
+        assert_eq!(
+            sorted
+                .get("1")
+                .unwrap()
+                .iter()
+                .map(|plan| plan.step_group.clone())
+                .collect::<Vec<StepGroup>>(),
+            vec![StepGroup::Carbonation, StepGroup::Aging]
+        );
+    }
+
+    #[test]
+    fn test_plan_pipelines_brew_days() {
+        let factory = factory::mock::mock_brewhouse_factory();
+        let beer = beer::mock::mock_brew_day_beer();
+        let batches_needed = mock_batches(&beer, 2, |batch| batch);
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        // Every vessel only holds one thing at a time
+        let vessel = |plan: &Plan| match &plan.action {
+            Action::Process(equipment)
+            | Action::Clean(equipment, _, _)
+            | Action::Maintain(equipment, _)
+            | Action::Package(equipment, _, _) => Some(equipment.name.clone()),
+            Action::Transfer(_, _, _) | Action::Propagate(_) | Action::Pitch(_, _, _) => None,
+        };
+        for (i, this) in solution.iter().enumerate() {
+            for other in solution.iter().skip(i + 1) {
+                if this.batch.id != other.batch.id
+                    && vessel(this).is_some()
+                    && vessel(this) == vessel(other)
+                {
+                    assert!(this.end <= other.start || other.end <= this.start);
+                }
+            }
+        }
+        // The whirlpool happens in the kettle that did the boil, without a clean in between
+        for plan in solution.iter() {
+            if plan.step_group == StepGroup::Boil {
+                assert!(!matches!(plan.action, Action::Clean(_, _, _)));
+                assert!(!matches!(plan.action, Action::Transfer(_, _, _)));
+            }
+        }
+        // The second brew day starts before the first one is done
+        let brew_day = |batch_id: usize| {
+            let plans = solution
+                .iter()
+                .filter(|plan| plan.batch.id == batch_id)
+                .filter(|plan| StepGroup::brew_day().contains(&plan.step_group));
+            let start = plans.clone().map(|plan| plan.start).min().unwrap();
+            let end = plans.map(|plan| plan.end).max().unwrap();
+            (start, end)
+        };
+        let (first, second) = if brew_day(1).0 < brew_day(2).0 {
+            (brew_day(1), brew_day(2))
+        } else {
+            (brew_day(2), brew_day(1))
+        };
+        assert!(second.0 < first.1);
+    }
+
+    #[test]
+    fn test_plan_shares_pump_and_chiller() {
+        let mut factory = factory::mock::mock_brewhouse_factory();
+        for (name, equipment_group) in [
+            ("Pump", EquipmentGroup::Pump),
+            ("Plate Chiller", EquipmentGroup::HeatExchanger),
+            ("CIP Skid", EquipmentGroup::CipSkid),
+        ] {
+            factory.equipments.insert(
+                name.to_string(),
+                Equipment::new(name.to_string(), Capacity::G10, equipment_group),
+            );
+        }
+        let beer = beer::mock::mock_brew_day_beer();
+        let batches_needed = mock_batches(&beer, 2, |batch| batch);
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        let shared = |plan: &Plan| match &plan.action {
+            Action::Transfer(_, _, shared) | Action::Clean(_, _, shared) => shared
+                .iter()
+                .map(|equipment| equipment.name.clone())
+                .collect::<Vec<String>>(),
+            _ => vec![],
+        };
+        for plan in solution.iter() {
+            match &plan.action {
+                Action::Transfer(_, to, _) => {
+                    assert!(shared(plan).contains(&"Pump".to_string()));
+                    // Only the knockout goes through the chiller
+                    assert_eq!(
+                        shared(plan).contains(&"Plate Chiller".to_string()),
+                        to.can(&Capability::Fermentation)
+                    );
+                }
+                Action::Clean(_, _, _) => {
+                    assert_eq!(shared(plan), vec!["CIP Skid".to_string()]);
+                }
+                _ => {}
+            }
+        }
+        // The pump, the chiller and the skid are used for one thing at a time
+        for (i, this) in solution.iter().enumerate() {
+            for other in solution.iter().skip(i + 1) {
+                if shared(this).iter().any(|name| shared(other).contains(name)) {
+                    assert!(this.end <= other.start || other.end <= this.start);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_shares_cip_skid_for_decontamination() {
+        let fermentor = |name: &str| {
+            Equipment::new(name.to_string(), Capacity::G10, EquipmentGroup::Fermentor)
+                .with_cip_time(chrono::Duration::hours(5))
+                .with_quarantine("sour".to_string())
+        };
+        let mut factory = segregation_factory(vec![fermentor("FV-001"), fermentor("FV-002")]);
+        let skid = Equipment::new(
+            "CIP Skid".to_string(),
+            Capacity::G10,
+            EquipmentGroup::CipSkid,
+        );
+        factory.equipments.insert(skid.name.clone(), skid);
+        let clean = segregation_beer("Blues Don't Bother Me", vec![]);
+        let batches_needed = mock_batches(&clean, 2, |batch| batch);
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        // A decontamination takes longer than the time between two brew days
+        let cleans = solution
+            .iter()
+            .filter(|plan| matches!(plan.action, Action::Clean(..)))
+            .collect::<Vec<&Plan>>();
+        let decontaminations = cleans
+            .iter()
+            .filter(|plan| {
+                matches!(
+                    plan.action,
+                    Action::Clean(_, CleanLevel::Decontamination, _)
+                )
+            })
+            .count();
+        assert_eq!(decontaminations, 2);
+        for (i, this) in cleans.iter().enumerate() {
+            for other in cleans.iter().skip(i + 1) {
+                assert!(this.end <= other.start || other.end <= this.start);
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_packages_after_the_last_step() {
+        let mut factory = factory::mock::mock_brewhouse_factory();
+        let canning_line = Equipment::new(
+            "Canning Line".to_string(),
+            Capacity::G10,
+            equipment_group::mock::mock_packaging_line(),
+        )
+        .with_packaging_rate(PackagingRate::new(
+            package_format::mock::mock_can(),
+            1200,
+            24,
+        ))
+        .with_changeover_time(chrono::Duration::hours(1));
+        factory
+            .equipments
+            .insert(canning_line.name.clone(), canning_line);
+        let beer = beer::mock::mock_brew_day_beer();
+        let batches_needed = mock_batches(&beer, 2, |batch| {
+            batch.with_packaging(package_format::mock::mock_can(), 80)
+        });
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        let runs = solution
+            .iter()
+            .filter(|plan| plan.step_group == StepGroup::Packaging)
+            .collect::<Vec<&Plan>>();
+        assert_eq!(runs.len(), 2);
+        for run in runs.iter() {
+            assert_eq!(
+                run.action,
+                Action::Package(
+                    factory.equipments["Canning Line"].clone(),
+                    package_format::mock::mock_can(),
+                    80
+                )
+            );
+            // 80 cans at 1200 an hour take 4 minutes
+            assert_eq!(run.end - run.start, chrono::Duration::minutes(4));
+            let batch_plans = solution
+                .iter()
+                .filter(|plan| plan.batch.id == run.batch.id)
+                .collect::<Vec<&Plan>>();
+            for plan in batch_plans.iter() {
+                if let Action::Process(_) = plan.action {
+                    assert!(plan.end <= run.start);
+                }
+            }
+            // The vessel is only cleaned once it is emptied by the packaging line
+            let last_clean = batch_plans
+                .iter()
+                .filter(|plan| matches!(plan.action, Action::Clean(_, _, _)))
+                .map(|plan| plan.start)
+                .max()
+                .unwrap();
+            assert!(last_clean >= run.end);
+        }
+        assert!(runs[0].end <= runs[1].start || runs[1].end <= runs[0].start);
+    }
+
+    #[test]
+    fn test_plan_packages_during_a_visit() {
+        let visit = Visit::new(
+            Utc.with_ymd_and_hms(2021, 1, 8, 8, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 1, 8, 14, 0, 0).unwrap(),
+            200,
+        );
+        let mut factory = factory::mock::mock_brewhouse_factory();
+        let mobile_canner = Equipment::new(
+            "Mobile Canner".to_string(),
+            Capacity::G10,
+            equipment_group::mock::mock_packaging_line(),
+        )
+        .with_packaging_rate(PackagingRate::new(
+            package_format::mock::mock_can(),
+            1200,
+            24,
+        ))
+        .with_visit(visit.clone());
+        let brite_tank = Equipment::new(
+            "Brite Tank".to_string(),
+            Capacity::G10,
+            EquipmentGroup::BriteTank,
+        );
+        for equipment in [mobile_canner, brite_tank] {
+            factory.equipments.insert(equipment.name.clone(), equipment);
+        }
+        let recipe = |carbonation| {
+            Recipe::new(
+                Capacity::G10,
+                Volume::GallonUS(10.0),
+                Steps::new(
+                    Some(Interval::Hours(6)),
+                    Some(Interval::Days(2)),
+                    None,
+                    None,
+                    None,
+                    carbonation,
+                ),
+            )
+        };
+        let beer = Beer::new(
+            "Carbonated ale".to_string(),
+            style::mock::mock_blonde_ale(),
+            recipe(Some(Interval::Days(1))),
+        );
+        let flat = Beer::new(
+            "Flat ale".to_string(),
+            style::mock::mock_blonde_ale(),
+            recipe(None),
+        );
+        fn cans(batch: BatchNeed) -> BatchNeed {
+            batch.with_packaging(package_format::mock::mock_can(), 80)
+        }
+        let now = mock_now();
+        // The canner only fills beer that is carbonated in a brite tank
+        assert_eq!(
+            Plan::missed_visits(&factory, &mock_batches(&flat, 1, cans), now),
+            vec!["Batch 1 of Flat ale misses every visit of Mobile Canner for 80 x 16oz Can"]
+        );
+        let batches_needed = mock_batches(&beer, 1, cans);
+        assert!(Plan::missed_visits(&factory, &batches_needed, now).is_empty());
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        let run = solution
+            .iter()
+            .find(|plan| plan.step_group == StepGroup::Packaging)
+            .unwrap();
+        assert!(run.start >= visit.start && run.end <= visit.end);
+        // The brew is pushed back, so that the beer is done just in time for the canner
+        let done = solution
+            .iter()
+            .filter(|plan| matches!(plan.action, Action::Process(_)))
+            .map(|plan| plan.end)
+            .max()
+            .unwrap();
+        assert!(done <= run.start);
+        assert!(run.start - done < chrono::Duration::hours(1));
+        let carbonation = solution
+            .iter()
+            .find(|plan| plan.step_group == StepGroup::Carbonation)
+            .unwrap();
+        assert_eq!(
+            carbonation.action,
+            Action::Process(factory.equipments["Brite Tank"].clone())
+        );
+
+        // The canner comes too early for the next batch
+        let now = Utc.with_ymd_and_hms(2021, 1, 7, 8, 0, 0).unwrap();
+        assert_eq!(
+            Plan::missed_visits(&factory, &batches_needed, now),
+            vec!["Batch 1 of Carbonated ale misses every visit of Mobile Canner for 80 x 16oz Can"]
+        );
+        let solution = Plan::plan(&factory, &batches_needed, now);
+        assert!(solution
+            .iter()
+            .all(|plan| plan.step_group != StepGroup::Packaging));
+    }
+
+    #[test]
+    fn test_plan_fills_clean_kegs_only() {
+        let mut factory = factory::mock::mock_brewhouse_factory();
+        let kegging_line = Equipment::new(
+            "Kegging Line".to_string(),
+            Capacity::G10,
+            equipment_group::mock::mock_packaging_line(),
+        )
+        .with_packaging_rate(PackagingRate::new(PackageFormat::SixthBarrel, 30, 1));
+        factory
+            .equipments
+            .insert(kegging_line.name.clone(), kegging_line);
+        // One keg is clean, the other one is only washed in ten days
+        factory.keg_fleets.push(
+            KegFleet::new(PackageFormat::SixthBarrel, 1)
+                .with_dirty(1)
+                .with_wash_time(chrono::Duration::days(10)),
+        );
+        let beer = beer::mock::mock_brew_day_beer();
+        let batches_needed = mock_batches(&beer, 2, |batch| {
+            batch.with_packaging(PackageFormat::SixthBarrel, 1)
+        });
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        let mut runs = solution
+            .iter()
+            .filter(|plan| plan.step_group == StepGroup::Packaging)
+            .map(|plan| plan.start)
+            .collect::<Vec<DateTime<Utc>>>();
+        runs.sort();
+        assert_eq!(runs.len(), 2);
+        assert!(runs[0] < now + chrono::Duration::days(10));
+        assert!(runs[1] >= now + chrono::Duration::days(10));
+    }
+
+    #[test]
+    fn test_plan_packages_past_a_keg_shortage() {
+        let mut factory = factory::mock::mock_brewhouse_factory();
+        let kegging_line = Equipment::new(
+            "Kegging Line".to_string(),
+            Capacity::G10,
+            equipment_group::mock::mock_packaging_line(),
+        )
+        .with_packaging_rate(PackagingRate::new(PackageFormat::SixthBarrel, 30, 1));
+        factory
+            .equipments
+            .insert(kegging_line.name.clone(), kegging_line);
+        factory
+            .keg_fleets
+            .push(KegFleet::new(PackageFormat::SixthBarrel, 1));
+        let beer = beer::mock::mock_brew_day_beer();
+        let batches_needed = mock_batches(&beer, 2, |batch| {
+            batch.with_packaging(PackageFormat::SixthBarrel, 1)
+        });
+        let now = mock_now();
+        assert_eq!(factory.keg_shortages(&batches_needed, now).len(), 1);
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        assert_eq!(
+            solution
+                .iter()
+                .filter(|plan| plan.step_group == StepGroup::Packaging)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_plan_keeps_taps_pouring() {
+        let mut factory = factory::mock::mock_brewhouse_factory();
+        factory.tap_list = Some(
+            TapList::new(2, chrono::Duration::days(3), chrono::Duration::days(6))
+                .with_requirement(TapRequirement::new("dark".to_string(), 1)),
+        );
+        let blonde = beer::mock::mock_brew_day_beer();
+        let stout = Beer::new(
+            "Dobroy Nochi".to_string(),
+            Style::ImperialStout,
+            recipe::mock::mock_brew_day_recipe(),
+        );
+        let mut batches_needed = HashMap::new();
+        for (id, beer) in [(1, &blonde), (2, &stout)] {
+            batches_needed.insert(
+                id,
+                batchneed::BatchNeed::new(id, beer, Capacity::G10, Volume::GallonUS(10.0)),
+            );
+        }
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        let tap_list = factory.tap_list.as_ref().unwrap();
+        let finished = solution
+            .iter()
+            .filter(|plan| matches!(plan.action, Action::Process(_)))
+            .map(|plan| plan.end)
+            .max()
+            .unwrap();
+        let on_tap = Plan::on_tap(solution.as_slice(), tap_list.window, finished);
+        assert_eq!(on_tap, vec![&stout, &blonde]);
+        assert!(tap_list.shortfalls(on_tap.as_slice()).is_empty());
+        // Nothing pours before the first batches are finished
+        let warnings = Plan::tap_list_warnings(solution.as_slice(), &factory, tap_list, now);
+        assert_eq!(
+            warnings[0],
+            "On 2020-12-30 the taproom has 0 of 2 beers on tap, 0 of 1 dark on tap"
+        );
+        assert!(warnings.len() < 7);
+
+        // The stout on hand pours from the start, till it is sold
+        factory.beers.insert(
+            stout.name.clone(),
+            Beer::new(
+                stout.name.clone(),
+                Style::ImperialStout,
+                recipe::mock::mock_brew_day_recipe(),
+            ),
+        );
+        factory.inventory.push(
+            Stock::new(stout.name.clone(), PackageFormat::Can16oz, 14)
+                .with_depletion(Depletion::new("taproom".to_string(), 49.0)),
+        );
+        let solution = Plan::plan(&factory, &batches_needed, now);
+        let tap_list = factory.tap_list.as_ref().unwrap();
+        let warnings = Plan::tap_list_warnings(solution.as_slice(), &factory, tap_list, now);
+        assert_eq!(
+            warnings[0],
+            "On 2020-12-30 the taproom has 1 of 2 beers on tap"
+        );
+    }
+
+    #[test]
+    fn test_plan_finishes_batches_when_due() {
+        let factory = factory::mock::mock_brewhouse_factory();
+        let beer = beer::mock::mock_brew_day_beer();
+        let now = mock_now();
+        let due = now + chrono::Duration::days(4);
+        // Three batches share two fermentors, the one that is due can't wait for the others
+        let batches_needed = mock_batches(&beer, 3, |batch| match batch.id {
+            3 => batch.with_due(due),
+            _ => batch,
+        });
+        let solution = Plan::plan(&factory, &batches_needed, now);
+        for plan in solution.iter() {
+            if plan.batch.id == 3 && matches!(plan.action, Action::Process(_)) {
+                assert!(plan.end <= due);
+            }
+        }
+        assert!(Plan::late_warnings(solution.as_slice()).is_empty());
+    }
+
+    #[test]
+    fn test_plan_finishes_late_batches_anyway() {
+        let factory = factory::mock::mock_brewhouse_factory();
+        let beer = beer::mock::mock_brew_day_beer();
+        let now = mock_now();
+        // The batch can't be finished in an hour, it is planned as soon as it can be
+        let due = now + chrono::Duration::hours(1);
+        let batches_needed = mock_batches(&beer, 1, |batch| batch.with_due(due));
+        let solution = Plan::plan(&factory, &batches_needed, now);
+        let first = solution.iter().map(|plan| plan.start).min().unwrap();
+        assert_eq!(first, now);
+        let warnings = Plan::late_warnings(solution.as_slice());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Batch 1 of foobeer 3000 is finished on"));
+        let due = due.format("%Y-%m-%d %H:%M");
+        assert!(warnings[0].ends_with(&format!("it is due on {}", due)));
+    }
+
+    #[test]
+    fn test_plan_keeps_beer_fresh_till_due() {
+        let factory = factory::mock::mock_brewhouse_factory();
+        let beer = beer::mock::mock_brew_day_beer().with_max_age(chrono::Duration::weeks(1));
+        let now = mock_now();
+        let due = now + chrono::Duration::days(20);
+        let batches_needed = mock_batches(&beer, 1, |batch| batch.with_due(due));
+        let solution = Plan::plan(&factory, &batches_needed, now);
+        let finished = solution
+            .iter()
+            .filter(|plan| matches!(plan.action, Action::Process(_)))
+            .map(|plan| plan.end)
+            .max()
+            .unwrap();
+        // The batch is not brewed right away, but just in time
+        assert!(finished <= due);
+        assert!(finished >= due - chrono::Duration::weeks(1));
+    }
+
+    #[test]
+    fn test_plan_repitches_yeast() {
+        let mut factory = factory::mock::mock_brewhouse_factory();
+        let mut strain = yeast::mock::mock_yeast_strain();
+        strain.max_generations = 2;
+        factory.yeasts.insert(strain.name.clone(), strain.clone());
+        let beer = beer::mock::mock_brew_day_beer().with_yeast(strain.name.clone());
+        let batches_needed = mock_batches(&beer, 3, |batch| batch);
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        // Two generations: one batch is pitched with the slurry of another, the others need
+        // propagated yeast
+        let propagations = solution
+            .iter()
+            .filter(|plan| matches!(plan.action, Action::Propagate(_)))
+            .collect::<Vec<&Plan>>();
+        assert_eq!(propagations.len(), 2);
+        for plan in propagations {
+            assert!(plan.start >= now);
+            assert_eq!(plan.end - plan.start, strain.propagation);
+        }
+        let pitches = solution
+            .iter()
+            .filter(|plan| matches!(plan.action, Action::Pitch(_, _, _)))
+            .collect::<Vec<&Plan>>();
+        assert_eq!(pitches.len(), 1);
+        let Action::Pitch(_, from, generation) = pitches[0].action else {
+            panic!("Should not happen")
+        };
+        assert_ne!(from, pitches[0].batch.id);
+        assert_eq!(generation, 2);
+        assert!(pitches[0].start <= pitches[0].end);
+        assert!(pitches[0].end <= pitches[0].start + strain.viability);
+        // The yeast is pitched at the start of the primary fermentation
+        for plan in solution.iter() {
+            if plan.batch.id == pitches[0].batch.id
+                && plan.step_group == StepGroup::PrimaryFermentation
+                && matches!(plan.action, Action::Process(_))
+            {
+                assert_eq!(plan.start, pitches[0].end);
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_repitches_the_slurry_on_hand() {
+        let mut factory = factory::mock::mock_brewhouse_factory();
+        let now = mock_now();
+        let strain = yeast::mock::mock_yeast_strain().with_slurry(yeast::Slurry::new(
+            7,
+            now - chrono::Duration::days(1),
+            1,
+        ));
+        factory.yeasts.insert(strain.name.clone(), strain.clone());
+        let beer = beer::mock::mock_brew_day_beer().with_yeast(strain.name.clone());
+        let batches_needed = mock_batches(&beer, 1, |batch| batch);
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        // The first batch of the strain is pitched with the slurry of batch 7, not propagated
+        assert!(!solution
+            .iter()
+            .any(|plan| matches!(plan.action, Action::Propagate(_))));
+        let pitch = solution
+            .iter()
+            .find(|plan| matches!(plan.action, Action::Pitch(_, _, _)))
+            .unwrap();
+        assert!(matches!(pitch.action, Action::Pitch(_, 7, 2)));
+        assert_eq!(pitch.start, now - chrono::Duration::days(1));
+        assert!(pitch.end <= pitch.start + strain.viability);
+    }
+
+    #[test]
+    fn test_plan_propagates_yeast_if_sooner() {
+        let mut factory = factory::mock::mock_brewhouse_factory();
+        let strain = yeast::mock::mock_yeast_strain().with_propagate_if_sooner(true);
+        factory.yeasts.insert(strain.name.clone(), strain.clone());
+        let beer = beer::mock::mock_brew_day_beer().with_yeast(strain.name.clone());
+        let batches_needed = mock_batches(&beer, 2, |batch| batch);
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        // Waiting for the slurry of the first batch takes longer than propagating fresh yeast
+        let propagations = solution
+            .iter()
+            .filter(|plan| matches!(plan.action, Action::Propagate(_)))
+            .count();
+        assert_eq!(propagations, 2);
+    }
+
+    #[test]
+    fn test_plan_waits_for_materials() {
+        let mut factory = factory::mock::mock_brewhouse_factory();
+        let material = material::mock::mock_material();
+        factory
+            .materials
+            .insert(material.name.clone(), material.clone());
+        let mut beer = beer::mock::mock_brew_day_beer();
+        beer.recipe = recipe::mock::mock_brew_day_recipe().with_ingredients(
+            Capacity::G10,
+            vec![Ingredient::new(material.name.clone(), 10.0)],
+        );
+        let batches_needed = mock_batches(&beer, 3, |batch| batch);
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        // There is malt for two batches, the third waits for the delivery
+        let (first_step, _) = batches_needed[&1].steps()[0].clone();
+        let mut brew_days = solution
+            .iter()
+            .filter(|plan| {
+                plan.step_group == first_step && matches!(plan.action, Action::Process(_))
+            })
+            .map(|plan| plan.start)
+            .collect::<Vec<DateTime<Utc>>>();
+        brew_days.sort();
+        assert_eq!(brew_days.len(), 3);
+        assert!(brew_days[1] < now + material.lead_time);
+        assert!(brew_days[2] >= now + material.lead_time);
+        let requirements = Plan::material_requirements(&factory, &solution, now);
+        assert_eq!(requirements.len(), 1);
+        assert!(requirements[0].starts_with(&format!(
+            "Order 5.0 kg of Maris Otter from Country Malt by {}",
+            (brew_days[2] - material.lead_time).format("%Y-%m-%d")
+        )));
+
+        // Too late to order
+        let requirements = Plan::material_requirements(&factory, &solution, brew_days[2]);
+        assert!(requirements[0].contains("before 5.0 kg of Maris Otter can arrive on"));
+    }
+
+    #[test]
+    fn test_plan_keeps_batch_in_unitank() {
+        let mut factory = Factory::new("unitanks");
+        for (name, equipment_group) in [
+            ("Mash Tun", EquipmentGroup::MashTun),
+            ("Unitank", EquipmentGroup::Unitank),
+        ] {
+            factory.equipments.insert(
+                name.to_string(),
+                Equipment::new(name.to_string(), Capacity::G10, equipment_group),
+            );
+        }
+        let beer = Beer::new(
+            "Unitank lager".to_string(),
+            style::mock::mock_blonde_ale(),
+            Recipe::new(
+                Capacity::G10,
+                Volume::GallonUS(10.0),
+                Steps::new(
+                    Some(Interval::Hours(6)),
+                    Some(Interval::Days(2)),
+                    None,
+                    None,
+                    None,
+                    Some(Interval::Days(2)),
+                ),
+            ),
+        );
+        let batches_needed = mock_batches(&beer, 1, |batch| batch);
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        // Fermentation and carbonation both happen in the unitank, without a transfer in between
+        for plan in solution.iter() {
+            match (&plan.step_group, &plan.action) {
+                (StepGroup::PrimaryFermentation, Action::Process(equipment))
+                | (StepGroup::Carbonation, Action::Process(equipment)) => {
+                    assert_eq!(equipment.name, "Unitank");
+                }
+                (StepGroup::PrimaryFermentation, _) => {
+                    panic!("the batch should stay in the unitank");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_leaves_batch_in_fermentor() {
+        let mut factory = Factory::new("one fermentor");
+        for (name, equipment_group) in [
+            ("Mash Tun", EquipmentGroup::MashTun),
+            ("Fermentor", EquipmentGroup::Fermentor),
+        ] {
+            factory.equipments.insert(
+                name.to_string(),
+                Equipment::new(name.to_string(), Capacity::G10, equipment_group),
+            );
+        }
+        let beer = Beer::new(
+            "Two stage ale".to_string(),
+            style::mock::mock_blonde_ale(),
+            Recipe::new(
+                Capacity::G10,
+                Volume::GallonUS(10.0),
+                Steps::new(
+                    Some(Interval::Hours(6)),
+                    Some(Interval::Days(2)),
+                    None,
+                    Some(Interval::Weeks(1)),
+                    None,
+                    None,
+                ),
+            ),
+        );
+        let batches_needed = mock_batches(&beer, 1, |batch| batch);
+        let solution = Plan::plan(&factory, &batches_needed, mock_now());
+
+        // Both fermentations are done in the one fermentor, it is not transfered to itself
+        for plan in solution.iter() {
+            match (&plan.step_group, &plan.action) {
+                (_, Action::Transfer(from, to, _)) => assert_ne!(from.name, to.name),
+                (StepGroup::PrimaryFermentation, Action::Clean(_, _, _)) => {
+                    panic!("the fermentor should not be cleaned under the batch");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_respects_downtime() {
+        let now = mock_now();
+        let downtime = Downtime::new(
+            now + chrono::Duration::days(1),
+            now + chrono::Duration::days(3),
+            "Passivation".to_string(),
+        );
+        let mut factory = Factory::new("downtime");
+        let mash_tun = Equipment::new(
+            "Mash Tun".to_string(),
+            Capacity::G10,
+            EquipmentGroup::MashTun,
+        );
+        let fermentor = Equipment::new(
+            "Fermentor".to_string(),
+            Capacity::G10,
+            EquipmentGroup::Fermentor,
+        )
+        .with_downtime(downtime.clone());
+        for equipment in [mash_tun, fermentor] {
+            factory.equipments.insert(equipment.name.clone(), equipment);
+        }
+        let beer = Beer::new(
+            "Waits for the fermentor".to_string(),
+            style::mock::mock_blonde_ale(),
+            Recipe::new(
+                Capacity::G10,
+                Volume::GallonUS(10.0),
+                Steps::new(
+                    Some(Interval::Hours(6)),
+                    Some(Interval::Days(2)),
+                    None,
+                    None,
+                    None,
+                    None,
+                ),
+            ),
+        );
+        let batches_needed = mock_batches(&beer, 1, |batch| batch);
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        for plan in solution.iter() {
+            if let Action::Process(equipment) | Action::Clean(equipment, _, _) = &plan.action {
+                if equipment.name == "Fermentor" {
+                    assert!(!downtime.overlaps(&plan.start, &plan.end));
+                }
+            }
+        }
+        let warnings = Plan::downtime_warnings(solution.as_slice(), now);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("Passivation"));
+    }
+
+    #[test]
+    fn test_plan_warns_about_downtime_after_the_brew_day() {
+        let now = mock_now();
+        // A short fermentation fits in the first seven hours, but not after a brew day of six
+        let downtime = Downtime::new(
+            now + chrono::Duration::hours(7),
+            now + chrono::Duration::days(1),
+            "Pressure test".to_string(),
+        );
+        let mut factory = Factory::new("downtime");
+        let mash_tun = Equipment::new(
+            "Mash Tun".to_string(),
+            Capacity::G10,
+            EquipmentGroup::MashTun,
+        );
+        let fermentor = Equipment::new(
+            "Fermentor".to_string(),
+            Capacity::G10,
+            EquipmentGroup::Fermentor,
+        )
+        .with_downtime(downtime.clone());
+        for equipment in [mash_tun, fermentor] {
+            factory.equipments.insert(equipment.name.clone(), equipment);
+        }
+        let beer = Beer::new(
+            "Quick ferment".to_string(),
+            style::mock::mock_blonde_ale(),
+            Recipe::new(
+                Capacity::G10,
+                Volume::GallonUS(10.0),
+                Steps::new(
+                    Some(Interval::Hours(6)),
+                    Some(Interval::Hours(1)),
+                    None,
+                    None,
+                    None,
+                    None,
+                ),
+            ),
+        );
+        let batches_needed = mock_batches(&beer, 1, |batch| batch);
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        let warnings = Plan::downtime_warnings(solution.as_slice(), now);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Batch 1 of Quick ferment does not fit in Fermentor"));
+        assert!(warnings[0].contains("Primary Fermentation"));
+    }
+
+    #[test]
+    fn test_plan_inserts_maintenance() {
+        let mut factory = Factory::new("maintenance");
+        let mash_tun = Equipment::new(
+            "Mash Tun".to_string(),
+            Capacity::G10,
+            EquipmentGroup::MashTun,
+        )
+        .with_maintenance(maintenance::mock::mock_screen_service());
+        factory.equipments.insert(mash_tun.name.clone(), mash_tun);
+        for i in 1..=3 {
+            let fermentor = Equipment::new(
+                format!("Fermentor {}", i),
+                Capacity::G10,
+                EquipmentGroup::Fermentor,
+            );
+            factory.equipments.insert(fermentor.name.clone(), fermentor);
+        }
+        let beer = Beer::new(
+            "Three brews".to_string(),
+            style::mock::mock_blonde_ale(),
+            Recipe::new(
+                Capacity::G10,
+                Volume::GallonUS(10.0),
+                Steps::new(
+                    Some(Interval::Hours(6)),
+                    Some(Interval::Days(2)),
+                    None,
+                    None,
+                    None,
+                    None,
+                ),
+            ),
+        );
+        let batches_needed = mock_batches(&beer, 3, |batch| batch);
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        // The screens are serviced once, after the second brew
+        let maintenance = solution
+            .iter()
+            .filter(|plan| matches!(plan.action, Action::Maintain(_, _)))
+            .collect::<Vec<&Plan>>();
+        assert_eq!(maintenance.len(), 1);
+        for plan in solution.iter() {
+            if let Action::Process(equipment) | Action::Clean(equipment, _, _) = &plan.action {
+                if equipment.name == "Mash Tun" {
+                    assert!(plan.end <= maintenance[0].start || maintenance[0].end <= plan.start);
+                }
+            }
+        }
+        let counters = Plan::maintenance_counters(solution.as_slice());
+        assert_eq!(counters.get("Mash Tun").unwrap()[0].usage.uses, 1);
+    }
+
+    #[test]
+    fn test_plan_counts_unitank_use_once() {
+        let mut factory = Factory::new("unitanks");
+        let mash_tun = Equipment::new(
+            "Mash Tun".to_string(),
+            Capacity::G10,
+            EquipmentGroup::MashTun,
+        );
+        let unitank = Equipment::new(
+            "Unitank".to_string(),
+            Capacity::G10,
+            EquipmentGroup::Unitank,
+        )
+        .with_maintenance(maintenance::mock::mock_screen_service());
+        for equipment in [mash_tun, unitank] {
+            factory.equipments.insert(equipment.name.clone(), equipment);
+        }
+        let beer = Beer::new(
+            "Unitank lager".to_string(),
+            style::mock::mock_blonde_ale(),
+            Recipe::new(
+                Capacity::G10,
+                Volume::GallonUS(10.0),
+                Steps::new(
+                    Some(Interval::Hours(6)),
+                    Some(Interval::Days(2)),
+                    None,
+                    None,
+                    None,
+                    Some(Interval::Days(2)),
+                ),
+            ),
+        );
+        let batches_needed = mock_batches(&beer, 1, |batch| batch);
+        let solution = Plan::plan(&factory, &batches_needed, mock_now());
+
+        // Fermented and carbonated in the unitank, that is one use and no service yet
+        assert!(!solution
+            .iter()
+            .any(|plan| matches!(plan.action, Action::Maintain(_, _))));
+        let counters = Plan::maintenance_counters(solution.as_slice());
+        assert_eq!(counters.get("Unitank").unwrap()[0].usage.uses, 1);
+    }
+
+    #[test]
+    fn test_plan_uses_transfer_and_cip_times() {
+        let mut factory = Factory::new("handling");
+        let mash_tun = Equipment::new(
+            "Mash Tun".to_string(),
+            Capacity::G10,
+            EquipmentGroup::MashTun,
+        )
+        .with_pump_rate(Volume::GallonUS(1.0))
+        .with_cip_time(chrono::Duration::minutes(45));
+        let fermentor = Equipment::new(
+            "Fermentor".to_string(),
+            Capacity::G10,
+            EquipmentGroup::Fermentor,
+        );
+        for equipment in [mash_tun, fermentor] {
+            factory.equipments.insert(equipment.name.clone(), equipment);
+        }
+        let beer = Beer::new(
+            "Slow pump".to_string(),
+            style::mock::mock_blonde_ale(),
+            Recipe::new(
+                Capacity::G10,
+                Volume::GallonUS(10.0),
+                Steps::new(
+                    Some(Interval::Hours(6)),
+                    Some(Interval::Days(2)),
+                    None,
+                    None,
+                    None,
+                    None,
+                ),
+            ),
+        );
+        let batches_needed = mock_batches(&beer, 1, |batch| batch);
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        for plan in solution.iter() {
+            match (&plan.step_group, &plan.action) {
+                (StepGroup::Brewing, Action::Transfer(_, _, _)) => {
+                    assert_eq!(plan.end - plan.start, chrono::Duration::minutes(10));
+                }
+                (StepGroup::Brewing, Action::Clean(_, _, _)) => {
+                    assert_eq!(plan.end - plan.start, chrono::Duration::minutes(45));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_cleans_for_the_next_beer() {
+        let mut factory = Factory::new("changeover");
+        let mash_tun = Equipment::new(
+            "Mash Tun".to_string(),
+            Capacity::G10,
+            EquipmentGroup::MashTun,
+        )
+        .with_cip_time(chrono::Duration::minutes(40));
+        let fermentor = Equipment::new(
+            "Fermentor".to_string(),
+            Capacity::G10,
+            EquipmentGroup::Fermentor,
+        );
+        for equipment in [mash_tun, fermentor] {
+            factory.equipments.insert(equipment.name.clone(), equipment);
+        }
+        let recipe = || {
+            Recipe::new(
+                Capacity::G10,
+                Volume::GallonUS(10.0),
+                Steps::new(
+                    Some(Interval::Hours(6)),
+                    Some(Interval::Days(2)),
+                    None,
+                    None,
+                    None,
+                    None,
+                ),
+            )
+        };
+        let stout = Beer::new("Stout".to_string(), Style::SpecialtyStout, recipe());
+        let blonde = Beer::new("Blonde".to_string(), Style::BlondeAle, recipe());
+        let mut batches_needed = HashMap::new();
+        batches_needed.insert(
+            1,
+            batchneed::BatchNeed::new(1, &stout, Capacity::G10, Volume::GallonUS(10.0)),
+        );
+        batches_needed.insert(
+            2,
+            batchneed::BatchNeed::new(2, &blonde, Capacity::G10, Volume::GallonUS(10.0)),
+        );
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        let mut cleans = solution
+            .iter()
+            .filter(|plan| plan.step_group == StepGroup::Brewing)
+            .filter_map(|plan| match &plan.action {
+                Action::Clean(_, level, _) => Some((plan, level.clone())),
+                _ => None,
+            })
+            .collect::<Vec<(&Plan, CleanLevel)>>();
+        cleans.sort_by_key(|(plan, _)| plan.start);
+        assert_eq!(cleans.len(), 2);
+        let (first, first_level) = &cleans[0];
+        let (last, last_level) = &cleans[1];
+        // Going from a dark to a light beer needs a full clean, the other way around a sanitize
+        let (expected, minutes) = match first.batch.beer.name.as_str() {
+            "Stout" => (CleanLevel::FullCip, 40),
+            _ => (CleanLevel::Sanitize, 20),
+        };
+        assert_eq!(first_level, &expected);
+        assert_eq!(first.end - first.start, chrono::Duration::minutes(minutes));
+        assert_eq!(last_level, &clean_level::mock::mock_full_cip());
+        assert_eq!(last.end - last.start, chrono::Duration::minutes(40));
+    }
+
+    #[test]
+    fn test_plan_keeps_glycol_within_its_limit() {
+        let mut factory = factory::mock::mock_brewhouse_factory();
+        factory
+            .resources
+            .push(CumulativeResource::new("Glycol".to_string(), 1));
+        let mut beer = beer::mock::mock_brew_day_beer();
+        beer.recipe = beer.recipe.with_consumption(
+            StepGroup::PrimaryFermentation,
+            Consumption::new("Glycol".to_string(), 1, None),
+        );
+        let batches_needed = mock_batches(&beer, 2, |batch| batch);
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        // There are two fermentors, but the glycol can only cool one of them
+        let fermentations = solution
+            .iter()
+            .filter(|plan| plan.step_group == StepGroup::PrimaryFermentation)
+            .filter(|plan| matches!(plan.action, Action::Process(_)))
+            .collect::<Vec<&Plan>>();
+        assert_eq!(fermentations.len(), 2);
+        let (this, other) = (fermentations[0], fermentations[1]);
+        assert!(this.end <= other.start || other.end <= this.start);
+    }
+
+    fn segregation_factory(fermentors: Vec<Equipment>) -> Factory {
+        let mut factory = Factory::new("segregation");
+        // The boil takes care of the hot side, it is only the cold side that is kept apart
+        factory.segregation.rules.push(SegregationRule::new(
+            "sour".to_string(),
+            Some(EquipmentGroup::Fermentor),
+            true,
+        ));
+        let mash_tun = Equipment::new(
+            "Mash Tun".to_string(),
+            Capacity::G10,
+            EquipmentGroup::MashTun,
+        );
+        factory.equipments.insert(mash_tun.name.clone(), mash_tun);
+        for equipment in fermentors {
+            factory.equipments.insert(equipment.name.clone(), equipment);
+        }
+        factory
+    }
+
+    fn segregation_beer(name: &str, tags: Vec<String>) -> Beer {
+        Beer::new(
+            name.to_string(),
+            Style::FruitBeer,
+            Recipe::new(
+                Capacity::G10,
+                Volume::GallonUS(10.0),
+                Steps::new(
+                    Some(Interval::Hours(6)),
+                    Some(Interval::Days(2)),
+                    None,
+                    None,
+                    None,
+                    None,
+                ),
+            ),
+        )
+        .with_tags(tags)
+    }
+
+    #[test]
+    fn test_plan_keeps_sours_apart() {
+        let factory = segregation_factory(vec![
+            Equipment::new(
+                "FV-001".to_string(),
+                Capacity::G10,
+                EquipmentGroup::Fermentor,
+            ),
+            Equipment::new(
+                "FV-S1".to_string(),
+                Capacity::G10,
+                EquipmentGroup::Fermentor,
+            )
+            .with_tags(vec!["sour".to_string()]),
+        ]);
+        let sour = segregation_beer("Lacto Lemon", vec!["sour".to_string()]);
+        let mut batches_needed = HashMap::new();
+        batches_needed.insert(
+            1,
+            batchneed::BatchNeed::new(1, &sour, Capacity::G10, Volume::GallonUS(10.0)),
+        );
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        for plan in solution.iter() {
+            if let Action::Process(equipment) = &plan.action {
+                if plan.step_group == StepGroup::PrimaryFermentation {
+                    assert_eq!(equipment.name, "FV-S1");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_plan_decontaminates_quarantined_vessel() {
+        let factory = segregation_factory(vec![Equipment::new(
+            "FV-001".to_string(),
+            Capacity::G10,
+            EquipmentGroup::Fermentor,
+        )
+        .with_cip_time(chrono::Duration::hours(1))
+        .with_quarantine("sour".to_string())]);
+        let clean = segregation_beer("Blues Don't Bother Me", vec![]);
+        let mut batches_needed = HashMap::new();
+        batches_needed.insert(
+            1,
+            batchneed::BatchNeed::new(1, &clean, Capacity::G10, Volume::GallonUS(10.0)),
+        );
+        let now = mock_now();
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        let fermentation = solution
+            .iter()
+            .find(|plan| {
+                plan.step_group == StepGroup::PrimaryFermentation
+                    && matches!(plan.action, Action::Process(_))
+            })
+            .unwrap();
+        let decontamination = solution
+            .iter()
+            .find(|plan| {
+                matches!(
+                    plan.action,
+                    Action::Clean(_, CleanLevel::Decontamination, _)
+                )
+            })
+            .unwrap();
+        assert_eq!(decontamination.end, fermentation.start);
+        assert_eq!(
+            decontamination.end - decontamination.start,
+            chrono::Duration::hours(2)
+        );
+        assert!(decontamination.start >= now);
+    }
+
+    #[test]
+    fn test_plan_decontaminates_outside_the_downtime() {
+        let now = mock_now();
+        let downtime = Downtime::new(
+            now,
+            now + chrono::Duration::hours(7),
+            "Passivation".to_string(),
+        );
+        let factory = segregation_factory(vec![Equipment::new(
+            "FV-001".to_string(),
+            Capacity::G10,
+            EquipmentGroup::Fermentor,
+        )
+        .with_cip_time(chrono::Duration::hours(1))
+        .with_quarantine("sour".to_string())
+        .with_downtime(downtime.clone())]);
+        let clean = segregation_beer("Blues Don't Bother Me", vec![]);
+        let batches_needed = mock_batches(&clean, 1, |batch| batch);
+        let solution = Plan::plan(&factory, &batches_needed, now);
+
+        // The brew day is over before the downtime is, but the decontamination has to wait
+        let decontamination = solution
+            .iter()
+            .find(|plan| {
+                matches!(
+                    plan.action,
+                    Action::Clean(_, CleanLevel::Decontamination, _)
+                )
+            })
+            .unwrap();
+        assert!(decontamination.start >= downtime.end);
+    }
+
     // #[test]
     // fn test_plan_do_magic() {
     //     let factory = factory::mock::mock_factory();
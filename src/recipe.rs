@@ -2,24 +2,40 @@ use std::collections::HashMap;
 use std::str::FromStr;
 
 use crate::capacity::Capacity;
-use crate::config::{FactoryConfig, RecipeConfig};
+use crate::carbonation_method::CarbonationMethod;
+use crate::config::{FactoryConfig, PhaseConfig, RecipeConfig};
+use crate::cumulative_resource::Consumption;
+use crate::interval::Interval;
+use crate::material::Ingredient;
+use crate::scaling::Scaling;
+use crate::step_group::StepGroup;
 use crate::steps::Steps;
 use crate::volume::Volume;
 
 /**
  * A Recipe contains a mapping of `BatchSize`s to pairs of `(Volume, Steps)`, where
  * each pair contains a _yield_ volume and a set of `Steps` (also called Phases) that allow the
- * beer in question to be constructed.
+ * beer in question to be constructed. The bill of materials says what goes in a batch of each
+ * size, and the consumption what the steps take of the resources of the factory.
+ *
+ * The entries for the other systems of the factory can be scaled from these (see `Scaling`),
+ * `scaled_from` tells which entries were generated, and from which system.
  */
 #[derive(Debug, PartialEq)]
 pub struct Recipe {
     pub map: HashMap<Capacity, (Volume, Steps)>,
+    pub bill_of_materials: HashMap<Capacity, Vec<Ingredient>>,
+    pub scaled_from: HashMap<Capacity, Capacity>,
+    pub consumption: HashMap<StepGroup, Vec<Consumption>>,
 }
 
 impl Recipe {
     pub fn blank() -> Self {
         Self {
             map: HashMap::new(),
+            bill_of_materials: HashMap::new(),
+            scaled_from: HashMap::new(),
+            consumption: HashMap::new(),
         }
     }
 
@@ -41,11 +57,96 @@ impl Recipe {
     pub fn get(&self, system_capacity: &Capacity) -> Option<&(Volume, Steps)> {
         self.map.get(system_capacity)
     }
+
+    pub fn with_ingredients(
+        mut self,
+        system_capacity: Capacity,
+        ingredients: Vec<Ingredient>,
+    ) -> Self {
+        self.bill_of_materials.insert(system_capacity, ingredients);
+        self
+    }
+
+    pub fn with_consumption(mut self, step_group: StepGroup, consumption: Consumption) -> Self {
+        self.consumption
+            .entry(step_group)
+            .or_default()
+            .push(consumption);
+        self
+    }
+
+    /// What the step takes of the resources of the factory, nothing when the recipe doesn't say.
+    pub fn consumption(&self, step_group: &StepGroup) -> &[Consumption] {
+        match self.consumption.get(step_group) {
+            Some(consumption) => consumption.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Add an entry for a system that the recipe has none for, scaled from the entry that was
+    /// not scaled itself and is closest in size. The ingredients grow with the yield. Returns
+    /// whether there is an entry for the system now.
+    pub fn scale(&mut self, system_capacity: &Capacity, scaling: &Scaling) -> bool {
+        if self.map.contains_key(system_capacity) {
+            return true;
+        }
+        let target = system_capacity.volume().liters();
+        let base_system = match self
+            .map
+            .keys()
+            .filter(|base| !self.scaled_from.contains_key(base))
+            .min_by(|a, b| {
+                let a = (a.volume().liters() - target).abs();
+                let b = (b.volume().liters() - target).abs();
+                a.partial_cmp(&b).unwrap()
+            }) {
+            Some(base_system) => base_system.clone(),
+            None => return false,
+        };
+        let (base_yield, base_steps) = self.map.get(&base_system).unwrap();
+        let r#yield = scaling.r#yield(&base_system, base_yield, system_capacity);
+        if r#yield.liters() <= 0.0 {
+            return false;
+        }
+        let ratio = r#yield.liters() / base_yield.liters();
+        let steps = Steps::from_sequence(
+            base_steps
+                .iter()
+                .map(|(step_group, interval)| {
+                    let interval =
+                        scaling.interval(&base_system, system_capacity, &step_group, &interval);
+                    (step_group, interval)
+                })
+                .collect(),
+        );
+        if let Some(ingredients) = self.bill_of_materials.get(&base_system) {
+            let ingredients = ingredients
+                .iter()
+                .map(|ingredient| {
+                    Ingredient::new(ingredient.name.clone(), ingredient.amount * ratio)
+                })
+                .collect();
+            self.bill_of_materials
+                .insert(system_capacity.clone(), ingredients);
+        }
+        self.map.insert(system_capacity.clone(), (r#yield, steps));
+        self.scaled_from
+            .insert(system_capacity.clone(), base_system);
+        true
+    }
+
+    /// What goes in a batch of the size, nothing when the bill of materials doesn't say.
+    pub fn ingredients(&self, system_capacity: &Capacity) -> &[Ingredient] {
+        match self.bill_of_materials.get(system_capacity) {
+            Some(ingredients) => ingredients.as_slice(),
+            None => &[],
+        }
+    }
 }
 
 impl From<(&FactoryConfig, &RecipeConfig)> for Recipe {
     fn from(config_pair: (&FactoryConfig, &RecipeConfig)) -> Self {
-        let (factory_config_ref, _recipe_config_ref) = config_pair;
+        let (factory_config_ref, recipe_config_ref) = config_pair;
         let system_capacity = match Capacity::from_str(&factory_config_ref.capacity) {
             Ok(c) => c,
             Err(_) => panic!(
@@ -54,14 +155,147 @@ impl From<(&FactoryConfig, &RecipeConfig)> for Recipe {
             ),
         };
 
-        Recipe::new(
-            system_capacity,
-            Volume::GallonUS(10 as f32),
-            Steps::new(None, None, None, None, None, None),
-        )
+        let batch_size = match Volume::from_str(&recipe_config_ref.batch_size) {
+            Ok(v) => v,
+            Err(_) => panic!(
+                "{} does not appear to be a valid batch size",
+                &recipe_config_ref.batch_size
+            ),
+        };
+
+        // Phases are either one of the built-in steps, or a step declared by the factory.
+        // They are done in the order in which the recipe lists them.
+        let definitions = factory_config_ref.step_definitions();
+        let mut steps = Steps::new(None, None, None, None, None, None);
+        let mut brew_day = HashMap::new();
+        let mut consumption = Vec::new();
+        for phase in &recipe_config_ref.phases {
+            // A brew day that lists its operations is split up in them
+            if !phase.operations.is_empty() {
+                if StepGroup::from_str(&phase.name) != Ok(StepGroup::Brewing) {
+                    panic!(
+                        "{} does not appear to be a valid phase to split up for recipe {}",
+                        &phase.name, &recipe_config_ref.name
+                    );
+                }
+                for operation in &phase.operations {
+                    let step_group = match StepGroup::from_str(&operation.name) {
+                        Ok(s) if StepGroup::brew_day().contains(&s) => s,
+                        _ => panic!(
+                            "{} does not appear to be a valid brew day operation for recipe {}",
+                            &operation.name, &recipe_config_ref.name
+                        ),
+                    };
+                    let interval = match Interval::from_str(&operation.duration) {
+                        Ok(i) => i,
+                        Err(_) => panic!(
+                            "{} does not appear to be a valid duration for operation {}",
+                            &operation.duration, &operation.name
+                        ),
+                    };
+                    consumption.extend(consumes(
+                        factory_config_ref,
+                        recipe_config_ref,
+                        operation,
+                        &step_group,
+                    ));
+                    brew_day.insert(step_group, interval);
+                }
+                continue;
+            }
+            // The carbonation method decides which step, and thus which vessel, is used
+            let method =
+                phase
+                    .method
+                    .as_ref()
+                    .map(|method| match CarbonationMethod::from_str(method) {
+                        Ok(m) => m,
+                        Err(_) => panic!(
+                            "{} does not appear to be a valid carbonation method for phase {}",
+                            method, &phase.name
+                        ),
+                    });
+            let step_group = match (&method, StepGroup::named(&phase.name, &definitions)) {
+                (Some(m), _) => m.step_group(),
+                (None, Some(s)) => s,
+                (None, None) => panic!(
+                    "{} does not appear to be a known step for recipe {}",
+                    &phase.name, &recipe_config_ref.name
+                ),
+            };
+            if steps.get(&step_group).is_some() {
+                panic!(
+                    "{} does not appear to be a valid phase for recipe {}, it is there twice",
+                    &phase.name, &recipe_config_ref.name
+                );
+            }
+            let interval = match (&method, Interval::from_str(&phase.duration)) {
+                (_, Ok(i)) => i,
+                // Without a duration, the carbonation takes as long as its method usually does
+                (Some(m), Err(_)) if phase.duration.is_empty() => m.interval(),
+                (_, Err(_)) => panic!(
+                    "{} does not appear to be a valid duration for phase {}",
+                    &phase.duration, &phase.name
+                ),
+            };
+            consumption.extend(consumes(
+                factory_config_ref,
+                recipe_config_ref,
+                phase,
+                &step_group,
+            ));
+            steps.push(step_group, interval);
+        }
+        if !brew_day.is_empty() {
+            steps.split_brew_day(
+                brew_day.remove(&StepGroup::HeatStrikeWater),
+                brew_day.remove(&StepGroup::Mash),
+                brew_day.remove(&StepGroup::Lauter),
+                brew_day.remove(&StepGroup::Boil),
+                brew_day.remove(&StepGroup::Whirlpool),
+            );
+        }
+
+        let ingredients = recipe_config_ref
+            .ingredients
+            .iter()
+            .map(Ingredient::from)
+            .collect::<Vec<Ingredient>>();
+        let mut recipe = Recipe::new(system_capacity.clone(), batch_size, steps)
+            .with_ingredients(system_capacity, ingredients);
+        for (step_group, consumption) in consumption {
+            recipe = recipe.with_consumption(step_group, consumption);
+        }
+        recipe
     }
 }
 
+/// What a phase of the recipe takes of the resources that the factory declares.
+fn consumes(
+    factory_config: &FactoryConfig,
+    recipe_config: &RecipeConfig,
+    phase: &PhaseConfig,
+    step_group: &StepGroup,
+) -> Vec<(StepGroup, Consumption)> {
+    phase
+        .consumes
+        .iter()
+        .map(|consumption_config| {
+            if !factory_config
+                .resources
+                .iter()
+                .any(|resource| resource.name == consumption_config.resource)
+            {
+                panic!(
+                    "{} does not appear to be a valid resource for phase {} of recipe {}",
+                    consumption_config.resource, phase.name, recipe_config.name
+                );
+            }
+            (step_group.clone(), Consumption::from(consumption_config))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 pub mod mock {
     use super::*;
@@ -76,6 +310,14 @@ pub mod mock {
             steps::mock::mock_steps(),
         )
     }
+
+    pub fn mock_brew_day_recipe() -> Recipe {
+        Recipe::new(
+            capacity::mock::mock_g10(),
+            Volume::GallonUS(10.0),
+            steps::mock::mock_brew_day_steps(),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -84,6 +326,42 @@ mod tests {
     use crate::capacity;
     use crate::steps;
     use crate::volume;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn test_recipe_scale() {
+        let mut recipe = mock::mock_recipe().with_ingredients(
+            capacity::mock::mock_g5(),
+            vec![Ingredient::new("Cascade".to_string(), 0.5)],
+        );
+        assert!(recipe.scale(&capacity::mock::mock_g10(), &Scaling::new()));
+        let (r#yield, steps) = recipe.get(&capacity::mock::mock_g10()).unwrap();
+        assert_approx_eq!(r#yield.liters(), Volume::GallonUS(10.0).liters(), 0.01);
+        assert_eq!(steps, &steps::mock::mock_steps());
+        let ingredients = recipe.ingredients(&capacity::mock::mock_g10());
+        assert_eq!(ingredients[0].name, "Cascade");
+        assert_approx_eq!(ingredients[0].amount, 1.0, 0.001);
+        assert_eq!(
+            recipe.scaled_from.get(&capacity::mock::mock_g10()),
+            Some(&capacity::mock::mock_g5())
+        );
+        // The entry that was given is not scaled
+        assert!(recipe.scale(&capacity::mock::mock_g5(), &Scaling::new()));
+        assert_eq!(recipe.scaled_from.len(), 1);
+    }
+
+    #[test]
+    fn test_recipe_ingredients() {
+        let recipe = mock::mock_recipe().with_ingredients(
+            capacity::mock::mock_g5(),
+            vec![Ingredient::new("Cascade".to_string(), 0.5)],
+        );
+        assert_eq!(
+            recipe.ingredients(&capacity::mock::mock_g5()),
+            &[Ingredient::new("Cascade".to_string(), 0.5)]
+        );
+        assert!(recipe.ingredients(&capacity::mock::mock_g10()).is_empty());
+    }
 
     #[test]
     fn test_recipe_new() {
@@ -93,4 +371,64 @@ mod tests {
             Some(&(volume::mock::mock_gallon_us(), steps::mock::mock_steps()))
         );
     }
+
+    #[test]
+    fn test_recipe_from_config_with_brew_day_operations() {
+        let factory_config: FactoryConfig = serde_json::from_str(
+            r#"{"name": "brewhouse", "capacity": "10g", "equipment": [], "recipes": []}"#,
+        )
+        .unwrap();
+        let recipe_config: RecipeConfig = serde_json::from_str(
+            r#"{"name": "Split Pale", "batch_size": "10g", "phases": [
+                {"name": "brewing", "operations": [
+                    {"name": "hlt", "duration": "30min"},
+                    {"name": "mash", "duration": "1h"},
+                    {"name": "lauter", "duration": "45min"},
+                    {"name": "boil", "duration": "1h"},
+                    {"name": "whirlpool", "duration": "20min"}
+                ]},
+                {"name": "primary", "duration": "2w"}
+            ]}"#,
+        )
+        .unwrap();
+        let recipe = Recipe::from((&factory_config, &recipe_config));
+        let (_volume, steps) = recipe.get(&Capacity::G10).unwrap();
+        assert_eq!(
+            steps.iter().collect::<Vec<(StepGroup, Interval)>>(),
+            vec![
+                (StepGroup::HeatStrikeWater, Interval::Minutes(30)),
+                (StepGroup::Mash, Interval::Hours(1)),
+                (StepGroup::Lauter, Interval::Minutes(45)),
+                (StepGroup::Boil, Interval::Hours(1)),
+                (StepGroup::Whirlpool, Interval::Minutes(20)),
+                (StepGroup::PrimaryFermentation, Interval::Weeks(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recipe_from_config_with_carbonation_method() {
+        let factory_config: FactoryConfig = serde_json::from_str(
+            r#"{"name": "spunders", "capacity": "10g", "equipment": [], "recipes": []}"#,
+        )
+        .unwrap();
+        let recipe_config: RecipeConfig = serde_json::from_str(
+            r#"{"name": "Spunded Lager", "batch_size": "10g", "phases": [
+                {"name": "primary", "duration": "2w"},
+                {"name": "carbonation", "method": "spunding"},
+                {"name": "aging", "duration": "4w"}
+            ]}"#,
+        )
+        .unwrap();
+        let recipe = Recipe::from((&factory_config, &recipe_config));
+        let (_volume, steps) = recipe.get(&Capacity::G10).unwrap();
+        assert_eq!(
+            steps.iter().collect::<Vec<(StepGroup, Interval)>>(),
+            vec![
+                (StepGroup::PrimaryFermentation, Interval::Weeks(2)),
+                (StepGroup::Spunding, Interval::Days(5)),
+                (StepGroup::Aging, Interval::Weeks(4)),
+            ]
+        );
+    }
 }
@@ -0,0 +1,99 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+
+use crate::config::ReleaseConfig;
+use crate::volume::Volume;
+
+/// A seasonal beer, like an Oktoberfest, that is released every year on the same day, and the
+/// volume that is released.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Release {
+    pub beer: String,
+    pub month: u32,
+    pub day: u32,
+    pub volume: Volume,
+}
+
+impl Release {
+    pub fn new(beer: String, month: u32, day: u32, volume: Volume) -> Self {
+        // A release on a leap day would skip most years
+        assert!(NaiveDate::from_ymd_opt(2021, month, day).is_some());
+        Self {
+            beer,
+            month,
+            day,
+            volume,
+        }
+    }
+
+    /// The first release at or after the moment.
+    pub fn next_date(&self, after: DateTime<Utc>) -> DateTime<Utc> {
+        let date = |year| {
+            Utc.with_ymd_and_hms(year, self.month, self.day, 0, 0, 0)
+                .unwrap()
+        };
+        let this_year = date(after.year());
+        if this_year >= after {
+            return this_year;
+        }
+        date(after.year() + 1)
+    }
+}
+
+impl std::convert::From<&ReleaseConfig> for Release {
+    fn from(config: &ReleaseConfig) -> Self {
+        if NaiveDate::from_ymd_opt(2021, config.month, config.day).is_none() {
+            panic!(
+                "{}-{} does not appear to be a valid release date for {}",
+                config.month, config.day, config.beer
+            );
+        }
+        let volume = match Volume::from_str(&config.volume) {
+            Ok(x) => x,
+            Err(_e) => panic!(
+                "{} does not appear to be a valid volume for the release of {}",
+                config.volume, config.beer
+            ),
+        };
+        Release::new(String::from(&config.beer), config.month, config.day, volume)
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_release() -> Release {
+        Release::new("foobeer 3000".to_string(), 9, 15, Volume::GallonUS(20.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_next_date() {
+        let release = mock::mock_release();
+        let summer = Utc.with_ymd_and_hms(2021, 6, 1, 8, 0, 0).unwrap();
+        let fall = Utc.with_ymd_and_hms(2021, 10, 1, 8, 0, 0).unwrap();
+        assert_eq!(
+            release.next_date(summer),
+            Utc.with_ymd_and_hms(2021, 9, 15, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            release.next_date(fall),
+            Utc.with_ymd_and_hms(2022, 9, 15, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_release_from_config() {
+        let config: ReleaseConfig = serde_json::from_str(
+            r#"{"beer": "foobeer 3000", "month": 9, "day": 15, "volume": "20g"}"#,
+        )
+        .unwrap();
+        assert_eq!(Release::from(&config), mock::mock_release());
+    }
+}
@@ -0,0 +1,192 @@
+use std::str::FromStr;
+
+use crate::capability::Capability;
+use crate::capacity::Capacity;
+use crate::config::ScalingConfig;
+use crate::interval::Interval;
+use crate::step_group::StepGroup;
+use crate::volume::Volume;
+
+/**
+ * How a recipe for one brew system is scaled to another. The yield is the share of the vessel
+ * that ends up as beer (the efficiency), less what is lost on every batch whatever its size,
+ * like the beer in the lines. Without an efficiency, the base recipe tells what it is.
+ *
+ * The steps of the brew day, heating, mashing, lautering and boiling, take longer in a larger
+ * vessel when there is a duration exponent: the duration grows with the volume to that power.
+ * Fermentation and the steps after it take as long on any system.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scaling {
+    pub efficiency: Option<f32>,
+    pub loss: Volume,
+    pub duration_exponent: Option<f32>,
+}
+
+impl Scaling {
+    pub fn new() -> Self {
+        Self {
+            efficiency: None,
+            loss: Volume::Liter(0.0),
+            duration_exponent: None,
+        }
+    }
+
+    pub fn with_efficiency(mut self, efficiency: f32) -> Self {
+        assert!(efficiency > 0.0 && efficiency <= 1.0);
+        self.efficiency = Some(efficiency);
+        self
+    }
+
+    pub fn with_loss(mut self, loss: Volume) -> Self {
+        self.loss = loss;
+        self
+    }
+
+    pub fn with_duration_exponent(mut self, duration_exponent: f32) -> Self {
+        self.duration_exponent = Some(duration_exponent);
+        self
+    }
+
+    /// What a batch on the system yields, when the base recipe yields `base_yield` on its system.
+    pub fn r#yield(
+        &self,
+        base_system: &Capacity,
+        base_yield: &Volume,
+        system: &Capacity,
+    ) -> Volume {
+        let loss = self.loss.liters();
+        let efficiency = self
+            .efficiency
+            .unwrap_or_else(|| (base_yield.liters() + loss) / base_system.volume().liters());
+        let system_liters = system.volume().liters();
+        Volume::Liter((system_liters * efficiency - loss).clamp(0.0, system_liters))
+    }
+
+    /// How long the step of the base recipe takes on the system.
+    pub fn interval(
+        &self,
+        base_system: &Capacity,
+        system: &Capacity,
+        step_group: &StepGroup,
+        interval: &Interval,
+    ) -> Interval {
+        let on_brew_day = matches!(
+            step_group.capability(),
+            Capability::HeatingWater
+                | Capability::Mashing
+                | Capability::Lautering
+                | Capability::Boiling
+        );
+        match self.duration_exponent {
+            Some(exponent) if on_brew_day => {
+                let ratio = system.volume().liters() / base_system.volume().liters();
+                let seconds = interval.duration().num_seconds() as f32 * ratio.powf(exponent);
+                Interval::approximate(chrono::Duration::seconds(seconds.round() as i64))
+            }
+            _ => interval.clone(),
+        }
+    }
+}
+
+impl Default for Scaling {
+    fn default() -> Self {
+        Scaling::new()
+    }
+}
+
+impl std::convert::From<&ScalingConfig> for Scaling {
+    fn from(config: &ScalingConfig) -> Self {
+        let mut scaling = Scaling::new();
+        if let Some(efficiency) = config.efficiency {
+            if efficiency <= 0.0 || efficiency > 1.0 {
+                panic!("{} does not appear to be a valid efficiency", efficiency);
+            }
+            scaling = scaling.with_efficiency(efficiency);
+        }
+        if let Some(loss) = &config.loss {
+            scaling = match Volume::from_str(loss) {
+                Ok(x) => scaling.with_loss(x),
+                Err(_e) => panic!("{} does not appear to be a valid loss", loss),
+            };
+        }
+        if let Some(exponent) = config.duration_exponent {
+            scaling = scaling.with_duration_exponent(exponent);
+        }
+        scaling
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_scaling() -> Scaling {
+        Scaling::new()
+            .with_efficiency(0.8)
+            .with_loss(Volume::GallonUS(1.0))
+            .with_duration_exponent(0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn test_scaling_yield() {
+        // The base recipe yields 10G out of 15G: two thirds
+        let base_yield = Volume::GallonUS(10.0);
+        let scaled = Scaling::new().r#yield(&Capacity::G15, &base_yield, &Capacity::BBL5);
+        assert_approx_eq!(
+            scaled.liters(),
+            Volume::BeerBarrel(5.0).liters() * 2.0 / 3.0,
+            0.1
+        );
+
+        let scaled = mock::mock_scaling().r#yield(&Capacity::G15, &base_yield, &Capacity::G10);
+        assert_approx_eq!(scaled.liters(), Volume::GallonUS(7.0).liters(), 0.1);
+    }
+
+    #[test]
+    fn test_scaling_interval() {
+        let scaling = mock::mock_scaling();
+        // Twice the volume boils 1.4 times as long, but ferments as long
+        assert_eq!(
+            scaling.interval(
+                &Capacity::G5,
+                &Capacity::G10,
+                &StepGroup::Boil,
+                &Interval::Hours(1)
+            ),
+            Interval::Minutes(85)
+        );
+        assert_eq!(
+            scaling.interval(
+                &Capacity::G5,
+                &Capacity::G10,
+                &StepGroup::PrimaryFermentation,
+                &Interval::Days(7)
+            ),
+            Interval::Days(7)
+        );
+        assert_eq!(
+            Scaling::new().interval(
+                &Capacity::G5,
+                &Capacity::G10,
+                &StepGroup::Boil,
+                &Interval::Hours(1)
+            ),
+            Interval::Hours(1)
+        );
+    }
+
+    #[test]
+    fn test_scaling_from_config() {
+        let config: ScalingConfig =
+            serde_json::from_str(r#"{"efficiency": 0.8, "loss": "1g", "duration_exponent": 0.5}"#)
+                .unwrap();
+        assert_eq!(Scaling::from(&config), mock::mock_scaling());
+    }
+}
@@ -0,0 +1,226 @@
+use std::str::FromStr;
+
+use crate::beer::Beer;
+use crate::config::SegregationConfig;
+use crate::equipment::Equipment;
+use crate::equipment_group::EquipmentGroup;
+
+/// Keeps the beers with a tag, like "sour" or "gluten-free", apart from the other beers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SegregationRule {
+    pub tag: String,
+    pub equipment_group: Option<EquipmentGroup>,
+    pub decontamination: bool,
+}
+
+impl SegregationRule {
+    pub fn new(
+        tag: String,
+        equipment_group: Option<EquipmentGroup>,
+        decontamination: bool,
+    ) -> Self {
+        Self {
+            tag,
+            equipment_group,
+            decontamination,
+        }
+    }
+
+    fn applies(&self, equipment: &Equipment) -> bool {
+        match &self.equipment_group {
+            Some(equipment_group) => equipment_group == &equipment.equipment_group,
+            None => true,
+        }
+    }
+
+    pub fn allows(&self, beer: &Beer, equipment: &Equipment) -> bool {
+        if !self.applies(equipment) {
+            return true;
+        }
+        let restricted = beer.tags.contains(&self.tag);
+        if restricted {
+            return equipment.tags.contains(&self.tag);
+        }
+        // The equipment is dedicated, unless it can be decontaminated for the other beers
+        self.decontamination
+            || !(equipment.tags.contains(&self.tag) || equipment.quarantine.contains(&self.tag))
+    }
+}
+
+impl std::convert::From<&SegregationConfig> for SegregationRule {
+    fn from(config: &SegregationConfig) -> Self {
+        let equipment_group = config.equipment_type.as_ref().map(|equipment_type| {
+            match EquipmentGroup::from_str(equipment_type) {
+                Ok(x) => x,
+                Err(_e) => panic!(
+                    "{} does not appear to be a valid equipment type for the segregation of {}",
+                    equipment_type, config.tag
+                ),
+            }
+        });
+        SegregationRule::new(
+            String::from(&config.tag),
+            equipment_group,
+            config.decontamination,
+        )
+    }
+}
+
+/**
+ * The segregation rules of the factory. A beer may only go into equipment that every rule allows.
+ * A vessel that held a segregated beer is quarantined: it needs a decontamination before a beer
+ * without the tag can use it.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segregation {
+    pub rules: Vec<SegregationRule>,
+}
+
+impl Segregation {
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    pub fn allows(&self, beer: &Beer, equipment: &Equipment) -> bool {
+        self.rules.iter().all(|rule| rule.allows(beer, equipment))
+    }
+
+    /// Whether the equipment has to be decontaminated before the beer can go in.
+    pub fn quarantined(&self, equipment: &Equipment, beer: &Beer) -> bool {
+        self.rules.iter().any(|rule| {
+            rule.decontamination
+                && rule.applies(equipment)
+                && equipment.quarantine.contains(&rule.tag)
+                && !beer.tags.contains(&rule.tag)
+        })
+    }
+
+    /// Whether the equipment has to be decontaminated when it goes `from` one beer `to` another.
+    pub fn needs_decontamination(&self, equipment: &Equipment, from: &Beer, to: &Beer) -> bool {
+        self.rules.iter().any(|rule| {
+            rule.decontamination
+                && rule.applies(equipment)
+                && from.tags.contains(&rule.tag)
+                && !to.tags.contains(&rule.tag)
+        })
+    }
+}
+
+impl Default for Segregation {
+    fn default() -> Self {
+        Segregation::new()
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_segregation() -> Segregation {
+        let mut segregation = Segregation::new();
+        segregation
+            .rules
+            .push(SegregationRule::new("sour".to_string(), None, true));
+        segregation.rules.push(SegregationRule::new(
+            "gluten-free".to_string(),
+            Some(EquipmentGroup::BriteTank),
+            false,
+        ));
+        segregation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capacity::Capacity;
+    use crate::recipe;
+    use crate::style::Style;
+
+    fn beer(name: &str, tags: Vec<&str>) -> Beer {
+        Beer::new(
+            name.to_string(),
+            Style::FruitBeer,
+            recipe::mock::mock_recipe(),
+        )
+        .with_tags(tags.iter().map(|tag| tag.to_string()).collect())
+    }
+
+    fn equipment(name: &str, equipment_group: EquipmentGroup, tags: Vec<&str>) -> Equipment {
+        Equipment::new(name.to_string(), Capacity::G10, equipment_group)
+            .with_tags(tags.iter().map(|tag| tag.to_string()).collect())
+    }
+
+    #[test]
+    fn test_segregation_sour() {
+        let segregation = mock::mock_segregation();
+        let sour = beer("Lacto Lemon", vec!["sour"]);
+        let clean = beer("Blues Don't Bother Me", vec![]);
+        let sour_side = equipment("FV-S1", EquipmentGroup::Fermentor, vec!["sour"]);
+        let clean_side = equipment("FV-001", EquipmentGroup::Fermentor, vec![]);
+        assert!(segregation.allows(&sour, &sour_side));
+        assert!(!segregation.allows(&sour, &clean_side));
+        assert!(segregation.allows(&clean, &sour_side));
+        assert!(segregation.allows(&clean, &clean_side));
+        assert!(segregation.needs_decontamination(&sour_side, &sour, &clean));
+        assert!(!segregation.needs_decontamination(&sour_side, &clean, &sour));
+        assert!(!segregation.needs_decontamination(&sour_side, &sour, &sour));
+    }
+
+    #[test]
+    fn test_segregation_dedicated_brite_tanks() {
+        let segregation = mock::mock_segregation();
+        let gluten_free = beer("Sorghum Blonde", vec!["gluten-free"]);
+        let regular = beer("Blues Don't Bother Me", vec![]);
+        let gf_brite_tank = equipment("BT-GF", EquipmentGroup::BriteTank, vec!["gluten-free"]);
+        let brite_tank = equipment("BT-001", EquipmentGroup::BriteTank, vec![]);
+        let fermentor = equipment("FV-001", EquipmentGroup::Fermentor, vec![]);
+        assert!(segregation.allows(&gluten_free, &gf_brite_tank));
+        assert!(!segregation.allows(&gluten_free, &brite_tank));
+        assert!(segregation.allows(&gluten_free, &fermentor));
+        assert!(!segregation.allows(&regular, &gf_brite_tank));
+        assert!(!segregation.needs_decontamination(&gf_brite_tank, &gluten_free, &regular));
+    }
+
+    #[test]
+    fn test_segregation_cold_side() {
+        let mut segregation = Segregation::new();
+        segregation.rules.push(SegregationRule::new(
+            "sour".to_string(),
+            Some(EquipmentGroup::Fermentor),
+            true,
+        ));
+        let sour = beer("Lacto Lemon", vec!["sour"]);
+        let clean = beer("Blues Don't Bother Me", vec![]);
+        let mash_tun = equipment("Mash Tun", EquipmentGroup::MashTun, vec![]);
+        let fermentor = equipment("FV-S1", EquipmentGroup::Fermentor, vec!["sour"]);
+        assert!(!segregation.needs_decontamination(&mash_tun, &sour, &clean));
+        assert!(segregation.needs_decontamination(&fermentor, &sour, &clean));
+    }
+
+    #[test]
+    fn test_segregation_quarantine() {
+        let segregation = mock::mock_segregation();
+        let clean = beer("Blues Don't Bother Me", vec![]);
+        let sour = beer("Lacto Lemon", vec!["sour"]);
+        let fermentor = equipment("FV-001", EquipmentGroup::Fermentor, vec![])
+            .with_quarantine("sour".to_string());
+        assert!(segregation.allows(&clean, &fermentor));
+        assert!(segregation.quarantined(&fermentor, &clean));
+        assert!(!segregation.quarantined(&fermentor, &sour));
+    }
+
+    #[test]
+    fn test_segregation_rule_from_config() {
+        let config: SegregationConfig =
+            serde_json::from_str(r#"{"tag": "gluten-free", "equipment_type": "brite"}"#).unwrap();
+        assert_eq!(
+            SegregationRule::from(&config),
+            SegregationRule::new(
+                "gluten-free".to_string(),
+                Some(EquipmentGroup::BriteTank),
+                false
+            )
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::Duration;
+use std::str::FromStr;
+
+use crate::capability::Capability;
+use crate::config::StepConfig;
+use crate::interval::Interval;
+
+/**
+ * A process step that is declared by a factory, instead of being one of the built-in
+ * `StepGroup`s. Think dry hopping, cold crashing, fruit additions or packaging.
+ *
+ * The `order` places the step between the built-in steps when a recipe is put together, see
+ * `StepGroup::order`.
+ */
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct StepDefinition {
+    pub name: String,
+    pub capability: Capability,
+    pub order: u32,
+    pub post_process_time: Duration,
+}
+
+impl StepDefinition {
+    pub fn new(
+        name: String,
+        capability: Capability,
+        order: u32,
+        post_process_time: Duration,
+    ) -> Self {
+        Self {
+            name,
+            capability,
+            order,
+            post_process_time,
+        }
+    }
+}
+
+impl std::convert::From<&StepConfig> for StepDefinition {
+    fn from(config: &StepConfig) -> Self {
+        let capability = match Capability::from_str(&config.capability) {
+            Ok(x) => x,
+            Err(_e) => panic!(
+                "{} does not appear to be a valid capability for step {}",
+                config.capability, config.name
+            ),
+        };
+        let post_process_time = match Interval::from_str(&config.post_process_time) {
+            Ok(x) => x.duration(),
+            Err(_e) => panic!(
+                "{} does not appear to be a valid post process time for step {}",
+                config.post_process_time, config.name
+            ),
+        };
+
+        StepDefinition::new(
+            String::from(&config.name),
+            capability,
+            config.order,
+            post_process_time,
+        )
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use crate::capability;
+
+    pub fn mock_dry_hopping() -> StepDefinition {
+        StepDefinition::new(
+            "Dry Hopping".to_string(),
+            capability::mock::mock_fermentation(),
+            450,
+            Duration::minutes(30),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_definition_new() {
+        let definition = mock::mock_dry_hopping();
+        assert_eq!(&definition.name, "Dry Hopping");
+        assert_eq!(definition.capability, Capability::Fermentation);
+        assert_eq!(definition.order, 450);
+        assert_eq!(definition.post_process_time, Duration::minutes(30));
+    }
+
+    #[test]
+    fn test_step_definition_from_config() {
+        let config = StepConfig {
+            name: "Cold Crash".to_string(),
+            capability: "fermentation".to_string(),
+            order: 550,
+            post_process_time: "1h".to_string(),
+        };
+        let definition = StepDefinition::from(&config);
+        assert_eq!(&definition.name, "Cold Crash");
+        assert_eq!(definition.capability, Capability::Fermentation);
+        assert_eq!(definition.order, 550);
+        assert_eq!(definition.post_process_time, Duration::hours(1));
+    }
+}
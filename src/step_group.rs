@@ -1,52 +1,158 @@
 use chrono::Duration;
+use std::collections::HashMap;
 
+use crate::capability::Capability;
 use crate::capacity::Capacity;
-use crate::equipment_group::EquipmentGroup;
+use crate::carbonation_method::CarbonationMethod;
+use crate::step_definition::StepDefinition;
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 pub enum StepGroup {
     Brewing,
+    HeatStrikeWater,
+    Mash,
+    Lauter,
+    Boil,
+    Whirlpool,
     PrimaryFermentation,
     DiacetylRest,
     SecondaryFermentation,
     Aging,
     Carbonation,
+    Spunding,
+    KegConditioning,
+    Packaging,
+    Custom(StepDefinition),
 }
 
 impl StepGroup {
-    pub fn lookup(&self) -> &'static str {
+    pub fn lookup(&self) -> &str {
         match self {
             StepGroup::Aging => "Aging",
             StepGroup::Brewing => "Brewing",
+            StepGroup::Boil => "Boil",
             StepGroup::Carbonation => "Carbonation",
             StepGroup::DiacetylRest => "Diacetyl Rest",
+            StepGroup::HeatStrikeWater => "Heat Strike Water",
+            StepGroup::Lauter => "Lauter",
+            StepGroup::Mash => "Mash",
             StepGroup::PrimaryFermentation => "Primary Fermentation",
             StepGroup::SecondaryFermentation => "Secondary Fermentation",
+            StepGroup::Whirlpool => "Whirlpool",
+            StepGroup::Spunding => "Spunding",
+            StepGroup::KegConditioning => "Keg Conditioning",
+            StepGroup::Packaging => "Packaging",
+            StepGroup::Custom(definition) => &definition.name,
         }
     }
+
+    /// The built-in steps, custom steps are declared per factory. The packaging is not a step of
+    /// a recipe, it is planned from the packaging runs of a batch.
     pub fn all() -> Vec<StepGroup> {
         vec![
             StepGroup::Brewing,
+            StepGroup::HeatStrikeWater,
+            StepGroup::Mash,
+            StepGroup::Lauter,
+            StepGroup::Boil,
+            StepGroup::Whirlpool,
             StepGroup::PrimaryFermentation,
             StepGroup::DiacetylRest,
             StepGroup::SecondaryFermentation,
             StepGroup::Aging,
             StepGroup::Carbonation,
+            StepGroup::Spunding,
+            StepGroup::KegConditioning,
         ]
     }
 
-    pub fn equipment_group(&self) -> EquipmentGroup {
+    /// Look up a step by name, either one of the built-in steps or one of the `definitions`
+    /// that a factory declares.
+    pub fn named(name: &str, definitions: &HashMap<String, StepDefinition>) -> Option<StepGroup> {
+        match name.parse() {
+            Ok(step_group) => Some(step_group),
+            Err(_) => definitions
+                .get(name)
+                .map(|definition| StepGroup::Custom(definition.clone())),
+        }
+    }
+
+    /// The operations a brew day is made of, in the order they happen on the brewhouse.
+    pub fn brew_day() -> Vec<StepGroup> {
+        vec![
+            StepGroup::HeatStrikeWater,
+            StepGroup::Mash,
+            StepGroup::Lauter,
+            StepGroup::Boil,
+            StepGroup::Whirlpool,
+        ]
+    }
+
+    /// Where the step goes in a recipe. The built-in steps leave gaps, so that custom steps can
+    /// be put in between them: dry hopping (450) goes after secondary fermentation, for example.
+    pub fn order(&self) -> u32 {
         match self {
-            StepGroup::Aging => EquipmentGroup::Keg,
-            //StepGroup::Brewing => unimplemented!(), //EquipmentGroup::HotLiquorTank EquipmentGroup::MashTun,
-            StepGroup::Brewing => EquipmentGroup::MashTun,
-            StepGroup::Carbonation => EquipmentGroup::CO2Tank,
-            StepGroup::DiacetylRest => EquipmentGroup::Fermentor,
-            StepGroup::PrimaryFermentation => EquipmentGroup::Fermentor,
-            StepGroup::SecondaryFermentation => EquipmentGroup::Fermentor,
+            StepGroup::Brewing => 100,
+            StepGroup::HeatStrikeWater => 110,
+            StepGroup::Mash => 120,
+            StepGroup::Lauter => 130,
+            StepGroup::Boil => 140,
+            StepGroup::Whirlpool => 150,
+            StepGroup::PrimaryFermentation => 200,
+            // The CO2 is captured at the end of the fermentation
+            StepGroup::Spunding => 210,
+            StepGroup::DiacetylRest => 300,
+            StepGroup::SecondaryFermentation => 400,
+            StepGroup::Aging => 500,
+            StepGroup::Carbonation => 600,
+            StepGroup::KegConditioning => 600,
+            StepGroup::Packaging => 700,
+            StepGroup::Custom(definition) => definition.order,
         }
     }
 
+    /// What the equipment needs to be able to do for this step.
+    pub fn capability(&self) -> Capability {
+        match self {
+            StepGroup::Aging => Capability::Conditioning,
+            // A brew day that is not split up in its operations only books the mash tun.
+            StepGroup::Brewing => Capability::Mashing,
+            StepGroup::HeatStrikeWater => Capability::HeatingWater,
+            StepGroup::Mash => Capability::Mashing,
+            StepGroup::Lauter => Capability::Lautering,
+            StepGroup::Boil | StepGroup::Whirlpool => Capability::Boiling,
+            StepGroup::Carbonation => Capability::Carbonation,
+            StepGroup::Spunding => Capability::Fermentation,
+            StepGroup::KegConditioning => Capability::Serving,
+            StepGroup::Packaging => Capability::Packaging,
+            StepGroup::DiacetylRest => Capability::Fermentation,
+            StepGroup::PrimaryFermentation => Capability::Fermentation,
+            StepGroup::SecondaryFermentation => Capability::Fermentation,
+            StepGroup::Custom(definition) => definition.capability.clone(),
+        }
+    }
+
+    /// Whether the batch has to stay in the vessel of the `previous` step, instead of being
+    /// transfered to another one. The wort is whirlpooled in the kettle it was boiled in,
+    /// and a beer is spunded in the fermentor it fermented in, so there is no transfer nor
+    /// clean in between.
+    pub fn stays_in_vessel_after(&self, previous: &StepGroup) -> bool {
+        (self == &StepGroup::Whirlpool || self == &StepGroup::Spunding)
+            && previous.capability() == self.capability()
+    }
+
+    /// The carbonation method this step stands for, if any.
+    pub fn carbonation_method(&self) -> Option<CarbonationMethod> {
+        match self {
+            StepGroup::Carbonation => Some(CarbonationMethod::Forced),
+            StepGroup::Spunding => Some(CarbonationMethod::Spunding),
+            StepGroup::KegConditioning => Some(CarbonationMethod::KegConditioned),
+            _ => None,
+        }
+    }
+
+    /// The time for the transfer, and for the clean, of equipment that doesn't know its own
+    /// pump rate or cip time.
     pub fn post_process_time(&self, system_capacity: &Capacity) -> Duration {
         // @TODO: this is all made up, get some more sensable magic numbers
         let factor = match system_capacity {
@@ -58,12 +164,21 @@ impl StepGroup {
             Capacity::UNKNOWN => 1000000,
         };
         let dur = match self {
-            StepGroup::Aging => Duration::minutes(2),
+            StepGroup::Aging | StepGroup::KegConditioning | StepGroup::Packaging => {
+                Duration::minutes(2)
+            }
             StepGroup::Brewing => Duration::minutes(5),
+            StepGroup::HeatStrikeWater | StepGroup::Mash | StepGroup::Lauter => {
+                Duration::minutes(3)
+            }
+            StepGroup::Boil | StepGroup::Whirlpool => Duration::minutes(5),
             StepGroup::Carbonation => Duration::minutes(1),
             StepGroup::DiacetylRest
             | StepGroup::PrimaryFermentation
-            | StepGroup::SecondaryFermentation => Duration::minutes(10),
+            | StepGroup::SecondaryFermentation
+            | StepGroup::Spunding => Duration::minutes(10),
+            // Declared by the factory, so these are taken as is
+            StepGroup::Custom(definition) => return definition.post_process_time,
         };
         dur * factor
     }
@@ -73,13 +188,21 @@ impl std::str::FromStr for StepGroup {
     type Err = ();
 
     fn from_str(s: &str) -> Result<StepGroup, ()> {
-        match s {
-            "Aging" => Ok(StepGroup::Aging),
-            "Brewing" => Ok(StepGroup::Brewing),
-            "Carbonation" => Ok(StepGroup::Carbonation),
-            "Diacetyl Rest" => Ok(StepGroup::DiacetylRest),
-            "Primary Fermentation" => Ok(StepGroup::PrimaryFermentation),
-            "Secondary Fermentation" => Ok(StepGroup::SecondaryFermentation),
+        // The lookup names in any case, and the short names that recipes use
+        match s.to_lowercase().as_str() {
+            "aging" => Ok(StepGroup::Aging),
+            "boil" => Ok(StepGroup::Boil),
+            "brewing" => Ok(StepGroup::Brewing),
+            "carbonation" => Ok(StepGroup::Carbonation),
+            "diacetyl rest" | "diacetyl" => Ok(StepGroup::DiacetylRest),
+            "heat strike water" | "hlt" => Ok(StepGroup::HeatStrikeWater),
+            "keg conditioning" => Ok(StepGroup::KegConditioning),
+            "lauter" => Ok(StepGroup::Lauter),
+            "mash" => Ok(StepGroup::Mash),
+            "primary fermentation" | "primary" => Ok(StepGroup::PrimaryFermentation),
+            "spunding" => Ok(StepGroup::Spunding),
+            "secondary fermentation" | "secondary" => Ok(StepGroup::SecondaryFermentation),
+            "whirlpool" => Ok(StepGroup::Whirlpool),
             _ => Err(()),
         }
     }
@@ -88,6 +211,8 @@ impl std::str::FromStr for StepGroup {
 #[cfg(test)]
 pub mod mock {
     use super::*;
+    use crate::step_definition;
+
     pub fn mock_aging() -> StepGroup {
         StepGroup::Aging
     }
@@ -96,6 +221,18 @@ pub mod mock {
         StepGroup::Brewing
     }
 
+    pub fn mock_mash() -> StepGroup {
+        StepGroup::Mash
+    }
+
+    pub fn mock_whirlpool() -> StepGroup {
+        StepGroup::Whirlpool
+    }
+
+    pub fn mock_dry_hopping() -> StepGroup {
+        StepGroup::Custom(step_definition::mock::mock_dry_hopping())
+    }
+
     pub fn mock_carbonation() -> StepGroup {
         StepGroup::Carbonation
     }
@@ -120,6 +257,7 @@ mod tests {
     #[test]
     fn test_stepgroup_lookup() {
         assert_eq!(StepGroup::Aging.lookup(), "Aging");
+        assert_eq!(StepGroup::Boil.lookup(), "Boil");
         assert_eq!(StepGroup::Brewing.lookup(), "Brewing");
         assert_eq!(StepGroup::Carbonation.lookup(), "Carbonation");
         assert_eq!(StepGroup::DiacetylRest.lookup(), "Diacetyl Rest");
@@ -131,33 +269,72 @@ mod tests {
             StepGroup::SecondaryFermentation.lookup(),
             "Secondary Fermentation"
         );
+        assert_eq!(StepGroup::HeatStrikeWater.lookup(), "Heat Strike Water");
+        assert_eq!(StepGroup::Lauter.lookup(), "Lauter");
+        assert_eq!(StepGroup::Mash.lookup(), "Mash");
+        assert_eq!(StepGroup::Whirlpool.lookup(), "Whirlpool");
+        assert_eq!(StepGroup::Spunding.lookup(), "Spunding");
+        assert_eq!(StepGroup::KegConditioning.lookup(), "Keg Conditioning");
+        assert_eq!(StepGroup::Packaging.lookup(), "Packaging");
+        assert_eq!(mock::mock_dry_hopping().lookup(), "Dry Hopping");
     }
 
     #[test]
-    fn test_stepgroup_equipment_group() {
-        assert_eq!(StepGroup::Aging.equipment_group(), EquipmentGroup::Keg);
+    fn test_stepgroup_order() {
+        let mut order = StepGroup::all();
+        order.push(mock::mock_dry_hopping());
+        order.sort_by_key(|step_group| step_group.order());
+        assert_eq!(order.first(), Some(&StepGroup::Brewing));
+        assert_eq!(order.get(7), Some(&StepGroup::Spunding));
+        assert_eq!(order.get(10), Some(&mock::mock_dry_hopping()));
+        assert_eq!(order.get(12), Some(&StepGroup::Carbonation));
+    }
+
+    #[test]
+    fn test_stepgroup_capability() {
+        assert_eq!(StepGroup::Aging.capability(), Capability::Conditioning);
+        assert_eq!(StepGroup::Brewing.capability(), Capability::Mashing);
+        assert_eq!(StepGroup::Carbonation.capability(), Capability::Carbonation);
         assert_eq!(
-            StepGroup::Brewing.equipment_group(),
-            EquipmentGroup::MashTun
+            StepGroup::DiacetylRest.capability(),
+            Capability::Fermentation
         );
         assert_eq!(
-            StepGroup::Carbonation.equipment_group(),
-            EquipmentGroup::CO2Tank
+            StepGroup::PrimaryFermentation.capability(),
+            Capability::Fermentation
         );
         assert_eq!(
-            StepGroup::DiacetylRest.equipment_group(),
-            EquipmentGroup::Fermentor
+            StepGroup::SecondaryFermentation.capability(),
+            Capability::Fermentation
         );
         assert_eq!(
-            StepGroup::PrimaryFermentation.equipment_group(),
-            EquipmentGroup::Fermentor
+            StepGroup::HeatStrikeWater.capability(),
+            Capability::HeatingWater
         );
+        assert_eq!(StepGroup::Mash.capability(), Capability::Mashing);
+        assert_eq!(StepGroup::Lauter.capability(), Capability::Lautering);
+        assert_eq!(StepGroup::Boil.capability(), Capability::Boiling);
+        assert_eq!(StepGroup::Whirlpool.capability(), Capability::Boiling);
+        assert_eq!(StepGroup::Spunding.capability(), Capability::Fermentation);
+        assert_eq!(StepGroup::KegConditioning.capability(), Capability::Serving);
+        assert_eq!(StepGroup::Packaging.capability(), Capability::Packaging);
         assert_eq!(
-            StepGroup::SecondaryFermentation.equipment_group(),
-            EquipmentGroup::Fermentor
+            mock::mock_dry_hopping().capability(),
+            Capability::Fermentation
         );
     }
 
+    #[test]
+    fn test_stepgroup_stays_in_vessel() {
+        assert!(StepGroup::Whirlpool.stays_in_vessel_after(&StepGroup::Boil));
+        assert!(!StepGroup::Whirlpool.stays_in_vessel_after(&StepGroup::Lauter));
+        assert!(!StepGroup::Boil.stays_in_vessel_after(&StepGroup::Lauter));
+        assert!(!StepGroup::SecondaryFermentation
+            .stays_in_vessel_after(&StepGroup::PrimaryFermentation));
+        assert!(StepGroup::Spunding.stays_in_vessel_after(&StepGroup::PrimaryFermentation));
+        assert!(!StepGroup::Spunding.stays_in_vessel_after(&StepGroup::Aging));
+    }
+
     #[test]
     fn test_stepgroup_parse() {
         assert_eq!("Aging".parse(), Ok(StepGroup::Aging));
@@ -172,11 +349,21 @@ mod tests {
             "Secondary Fermentation".parse(),
             Ok(StepGroup::SecondaryFermentation)
         );
+        assert_eq!("Heat Strike Water".parse(), Ok(StepGroup::HeatStrikeWater));
+        assert_eq!("Mash".parse(), Ok(StepGroup::Mash));
+        assert_eq!("Lauter".parse(), Ok(StepGroup::Lauter));
+        assert_eq!("Boil".parse(), Ok(StepGroup::Boil));
+        assert_eq!("Whirlpool".parse(), Ok(StepGroup::Whirlpool));
+        assert_eq!("primary".parse(), Ok(StepGroup::PrimaryFermentation));
+        assert_eq!("spunding".parse(), Ok(StepGroup::Spunding));
+        assert_eq!("keg conditioning".parse(), Ok(StepGroup::KegConditioning));
+        assert_eq!("Dry Hopping".parse::<StepGroup>(), Err(()));
     }
 
     #[test]
     fn test_stepgroup_all() {
-        assert_eq!(StepGroup::all().len(), 6);
+        assert_eq!(StepGroup::all().len(), 13);
+        assert_eq!(StepGroup::brew_day().len(), 5);
     }
 
     #[test]
@@ -205,5 +392,9 @@ mod tests {
             StepGroup::Aging.post_process_time(&Capacity::BBL15),
             Duration::minutes(40)
         );
+        assert_eq!(
+            mock::mock_dry_hopping().post_process_time(&Capacity::BBL15),
+            Duration::minutes(30)
+        );
     }
 }
@@ -11,16 +11,26 @@ use crate::step_group::StepGroup;
  * There are 6 major steps to a brew process: Brewing (also called 'Brew Day'), Primary Fermentation,
  * Diacetyl Rest (optional), Secondary Fermentation, Aging, and Carbonation. Each of these, if
  * present, requires some non-zero amount of time (an interval).
+ *
+ * The brew day can be split up into the operations that happen on the brewhouse: heating the
+ * strike water, mashing, lautering, boiling and the whirlpool/knockout. Each of these is bound to
+ * its own vessel, so that the brew days of different batches can overlap.
+ *
+ * Next to these, a recipe can use the steps that are declared by its factory (see
+ * `StepDefinition`). Steps that are stored are put in line by their `StepGroup::order`, while
+ * steps that are pushed follow the sequence of the recipe itself. That way a sour can skip primary
+ * fermentation, or a beer can be carbonated before it is aged in the keg.
  */
 #[derive(Debug, PartialEq)]
 pub struct Steps {
     map: HashMap<StepGroup, Interval>,
+    sequence: Vec<StepGroup>,
 }
 
 macro_rules! step_needed {
-    ($map: expr, $var: expr, $key: expr) => {
+    ($steps: expr, $var: expr, $key: expr) => {
         if let Some(x) = $var {
-            $map.insert($key, x);
+            $steps.store($key, x);
         }
     };
 }
@@ -36,17 +46,74 @@ impl Steps {
     ) -> Self {
         let mut steps = Self {
             map: HashMap::with_capacity(6),
+            sequence: Vec::with_capacity(6),
+        };
+        step_needed!(steps, brewing, StepGroup::Brewing);
+        step_needed!(steps, primary, StepGroup::PrimaryFermentation);
+        step_needed!(steps, diacetyl, StepGroup::DiacetylRest);
+        step_needed!(steps, secondary, StepGroup::SecondaryFermentation);
+        step_needed!(steps, aging, StepGroup::Aging);
+        step_needed!(steps, carbonation, StepGroup::Carbonation);
+
+        steps
+    }
+
+    /// Steps that follow the sequence in which they are given, instead of the default order.
+    pub fn from_sequence(sequence: Vec<(StepGroup, Interval)>) -> Self {
+        let mut steps = Self {
+            map: HashMap::with_capacity(sequence.len()),
+            sequence: Vec::with_capacity(sequence.len()),
         };
-        step_needed!(steps.map, brewing, StepGroup::Brewing);
-        step_needed!(steps.map, primary, StepGroup::PrimaryFermentation);
-        step_needed!(steps.map, diacetyl, StepGroup::DiacetylRest);
-        step_needed!(steps.map, secondary, StepGroup::SecondaryFermentation);
-        step_needed!(steps.map, aging, StepGroup::Aging);
-        step_needed!(steps.map, carbonation, StepGroup::Carbonation);
+        for (step_group, interval) in sequence {
+            steps.push(step_group, interval);
+        }
 
         steps
     }
 
+    /// Replace the single `Brewing` step by the separate brew day operations.
+    pub fn split_brew_day(
+        &mut self,
+        heat_strike_water: Option<Interval>,
+        mash: Option<Interval>,
+        lauter: Option<Interval>,
+        boil: Option<Interval>,
+        whirlpool: Option<Interval>,
+    ) {
+        self.map.remove(&StepGroup::Brewing);
+        self.sequence
+            .retain(|step_group| step_group != &StepGroup::Brewing);
+        step_needed!(self, heat_strike_water, StepGroup::HeatStrikeWater);
+        step_needed!(self, mash, StepGroup::Mash);
+        step_needed!(self, lauter, StepGroup::Lauter);
+        step_needed!(self, boil, StepGroup::Boil);
+        step_needed!(self, whirlpool, StepGroup::Whirlpool);
+    }
+
+    /// Put the step in line by its `StepGroup::order`.
+    pub fn store(&mut self, step_group: StepGroup, interval: Interval) {
+        assert_eq!(self.get(&step_group), None);
+        let pos = self
+            .sequence
+            .iter()
+            .position(|other| other.order() > step_group.order())
+            .unwrap_or(self.sequence.len());
+        self.sequence.insert(pos, step_group.clone());
+        self.map.insert(step_group, interval);
+    }
+
+    /// Put the step at the end of the sequence, whatever its `StepGroup::order` is.
+    pub fn push(&mut self, step_group: StepGroup, interval: Interval) {
+        assert_eq!(self.get(&step_group), None);
+        self.sequence.push(step_group.clone());
+        self.map.insert(step_group, interval);
+    }
+
+    /// Where the step is in the sequence of this recipe.
+    pub fn position(&self, step_group: &StepGroup) -> Option<usize> {
+        self.sequence.iter().position(|other| other == step_group)
+    }
+
     pub fn get(&self, key: &StepGroup) -> Option<&Interval> {
         self.map.get(key)
     }
@@ -84,14 +151,7 @@ impl<'a> StepIterator<'a> {
         Self {
             steps,
             pos: 0,
-            order: vec![
-                StepGroup::Brewing,
-                StepGroup::PrimaryFermentation,
-                StepGroup::DiacetylRest,
-                StepGroup::SecondaryFermentation,
-                StepGroup::Aging,
-                StepGroup::Carbonation,
-            ],
+            order: steps.sequence.clone(),
         }
     }
 }
@@ -127,11 +187,33 @@ pub mod mock {
             Some(Interval::Days(2)),
         )
     }
+
+    pub fn mock_brew_day_steps() -> Steps {
+        let mut steps = Steps::new(
+            Some(Interval::Hours(6)),
+            Some(Interval::Days(2)),
+            None,
+            None,
+            None,
+            None,
+        );
+        steps.split_brew_day(
+            Some(Interval::Hours(1)),
+            Some(Interval::Hours(1)),
+            Some(Interval::Hours(1)),
+            Some(Interval::Hours(1)),
+            Some(Interval::Minutes(30)),
+        );
+        steps
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::capability::Capability;
+    use crate::step_definition::StepDefinition;
+    use crate::step_group;
 
     #[test]
     fn test_steps_new() {
@@ -152,4 +234,74 @@ mod tests {
         );
         assert_eq!(steps.needs_diacetyl_rest(), false);
     }
+
+    #[test]
+    fn test_steps_store_custom_step() {
+        let mut steps = mock::mock_steps();
+        let dry_hopping = step_group::mock::mock_dry_hopping();
+        steps.store(dry_hopping.clone(), Interval::Days(3));
+        assert_eq!(steps.get(&dry_hopping), Some(&Interval::Days(3)));
+        assert_eq!(
+            steps
+                .iter()
+                .map(|(step_group, _interval)| step_group)
+                .collect::<Vec<StepGroup>>(),
+            vec![
+                StepGroup::Brewing,
+                StepGroup::PrimaryFermentation,
+                StepGroup::SecondaryFermentation,
+                dry_hopping,
+                StepGroup::Aging,
+                StepGroup::Carbonation,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_steps_from_sequence() {
+        let mixed_fermentation = StepGroup::Custom(StepDefinition::new(
+            "Mixed Fermentation".to_string(),
+            Capability::Fermentation,
+            250,
+            Duration::minutes(30),
+        ));
+        let steps = Steps::from_sequence(vec![
+            (StepGroup::Brewing, Interval::Hours(6)),
+            (mixed_fermentation.clone(), Interval::Months(6)),
+            (StepGroup::Carbonation, Interval::Days(2)),
+            (StepGroup::Aging, Interval::Weeks(2)),
+        ]);
+        assert_eq!(
+            steps
+                .iter()
+                .map(|(step_group, _interval)| step_group)
+                .collect::<Vec<StepGroup>>(),
+            vec![
+                StepGroup::Brewing,
+                mixed_fermentation.clone(),
+                StepGroup::Carbonation,
+                StepGroup::Aging,
+            ]
+        );
+        assert_eq!(steps.position(&mixed_fermentation), Some(1));
+        assert_eq!(steps.position(&StepGroup::Aging), Some(3));
+        assert_eq!(steps.position(&StepGroup::PrimaryFermentation), None);
+    }
+
+    #[test]
+    fn test_steps_split_brew_day() {
+        let steps = mock::mock_brew_day_steps();
+        assert_eq!(steps.get(&StepGroup::Brewing), None);
+        assert_eq!(
+            steps.iter().collect::<Vec<(StepGroup, Interval)>>(),
+            vec![
+                (StepGroup::HeatStrikeWater, Interval::Hours(1)),
+                (StepGroup::Mash, Interval::Hours(1)),
+                (StepGroup::Lauter, Interval::Hours(1)),
+                (StepGroup::Boil, Interval::Hours(1)),
+                (StepGroup::Whirlpool, Interval::Minutes(30)),
+                (StepGroup::PrimaryFermentation, Interval::Days(2)),
+            ]
+        );
+    }
 }
@@ -1,3 +1,5 @@
+use chrono::Duration;
+
 use crate::r#type::Type;
 
 #[derive(Debug, PartialEq)]
@@ -34,6 +36,33 @@ impl Style {
         }
     }
 
+    /// The typical color of the style, in SRM.
+    pub fn color(&self) -> u8 {
+        match self {
+            Style::Pilsner => 3,
+            Style::BlondeAle => 4,
+            Style::Kellerbier => 5,
+            Style::FruitBeer => 6,
+            Style::IPA => 8,
+            Style::AmberLager | Style::CaliforniaCommon => 12,
+            Style::IrishRedAle | Style::SmokedAle => 15,
+            Style::BrownAle => 22,
+            Style::SpecialtyStout => 35,
+            Style::ImperialStout => 45,
+        }
+    }
+
+    /// How old the beer may be when it is due, counted from the moment it is packaged. Hoppy
+    /// and unfiltered beers fade fast, the others keep.
+    pub fn max_age(&self) -> Option<Duration> {
+        match self {
+            Style::IPA => Some(Duration::weeks(2)),
+            Style::Kellerbier => Some(Duration::weeks(3)),
+            Style::FruitBeer => Some(Duration::weeks(6)),
+            _ => None,
+        }
+    }
+
     pub fn r#type(&self) -> Type {
         match self {
             Style::AmberLager | Style::Kellerbier | Style::Pilsner => Type::Lager,
@@ -137,6 +166,19 @@ mod tests {
         assert_eq!(Style::SpecialtyStout.lookup(), "Specialty Stout");
     }
 
+    #[test]
+    fn test_style_color() {
+        assert!(Style::Pilsner.color() < Style::IPA.color());
+        assert!(Style::IPA.color() < Style::ImperialStout.color());
+        assert_eq!(Style::BlondeAle.color(), 4);
+    }
+
+    #[test]
+    fn test_style_max_age() {
+        assert_eq!(Style::IPA.max_age(), Some(Duration::weeks(2)));
+        assert_eq!(Style::ImperialStout.max_age(), None);
+    }
+
     #[test]
     fn test_style_parse() {
         assert_eq!("Amber Lager".parse(), Ok(Style::AmberLager));
@@ -0,0 +1,219 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::beer::Beer;
+use crate::config::{TapListConfig, TapRequirementConfig};
+use crate::interval::Interval;
+use crate::r#type::Type;
+
+/// A kind of beer the taproom should pour. The kind is a beer name, a style, "lager", "ale" or
+/// "dark", for the styles of 20 SRM and up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TapRequirement {
+    pub kind: String,
+    pub count: u32,
+}
+
+impl TapRequirement {
+    pub fn new(kind: String, count: u32) -> Self {
+        Self { kind, count }
+    }
+
+    pub fn matches(&self, beer: &Beer) -> bool {
+        match self.kind.as_str() {
+            "lager" => beer.style.r#type() == Type::Lager,
+            "ale" => beer.style.r#type() == Type::Ale,
+            "dark" => beer.style.color() >= 20,
+            kind => kind == beer.name || kind == beer.style.lookup(),
+        }
+    }
+}
+
+impl std::convert::From<&TapRequirementConfig> for TapRequirement {
+    fn from(config: &TapRequirementConfig) -> Self {
+        TapRequirement::new(String::from(&config.kind), config.count)
+    }
+}
+
+/**
+ * What the taproom should always have on tap: at least `minimum` different beers, and enough
+ * of every kind that is required. A batch pours for `window` after it is finished, and the tap
+ * list is kept from the start of the planning till the end of the `horizon`.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct TapList {
+    pub minimum: u32,
+    pub requirements: Vec<TapRequirement>,
+    pub window: Duration,
+    pub horizon: Duration,
+}
+
+impl TapList {
+    pub fn new(minimum: u32, window: Duration, horizon: Duration) -> Self {
+        Self {
+            minimum,
+            requirements: Vec::new(),
+            window,
+            horizon,
+        }
+    }
+
+    pub fn with_requirement(mut self, requirement: TapRequirement) -> Self {
+        self.requirements.push(requirement);
+        self
+    }
+
+    /// The moments the tap list is checked, from `now` till the end of the horizon: every half
+    /// a window, so that a batch is seen on tap at least once, but not more than once a day.
+    pub fn moments(&self, now: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        let step = (self.window / 2).max(Duration::days(1));
+        let mut moments = Vec::new();
+        let mut at = now;
+        while at <= now + self.horizon {
+            moments.push(at);
+            at += step;
+        }
+        moments
+    }
+
+    /// What the beers on tap fall short of.
+    pub fn shortfalls(&self, on_tap: &[&Beer]) -> Vec<String> {
+        let mut shortfalls = Vec::new();
+        if (on_tap.len() as u32) < self.minimum {
+            shortfalls.push(format!("{} of {} beers on tap", on_tap.len(), self.minimum));
+        }
+        for requirement in self.requirements.iter() {
+            let count = on_tap
+                .iter()
+                .filter(|beer| requirement.matches(beer))
+                .count() as u32;
+            if count < requirement.count {
+                shortfalls.push(format!(
+                    "{} of {} {} on tap",
+                    count, requirement.count, requirement.kind
+                ));
+            }
+        }
+        shortfalls
+    }
+}
+
+impl std::convert::From<&TapListConfig> for TapList {
+    fn from(config: &TapListConfig) -> Self {
+        let window = match Interval::from_str(&config.window) {
+            Ok(x) => x.duration(),
+            Err(_e) => panic!(
+                "{} does not appear to be a valid window for a batch on tap",
+                config.window
+            ),
+        };
+        let horizon = match Interval::from_str(&config.horizon) {
+            Ok(x) => x.duration(),
+            Err(_e) => panic!(
+                "{} does not appear to be a valid horizon for the tap list",
+                config.horizon
+            ),
+        };
+        let mut tap_list = TapList::new(config.minimum, window, horizon);
+        for requirement_config in &config.requirements {
+            tap_list = tap_list.with_requirement(TapRequirement::from(requirement_config));
+        }
+        tap_list
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_tap_list() -> TapList {
+        TapList::new(2, Duration::weeks(3), Duration::weeks(4))
+            .with_requirement(TapRequirement::new("lager".to_string(), 1))
+            .with_requirement(TapRequirement::new("dark".to_string(), 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capacity::Capacity;
+    use crate::recipe::Recipe;
+    use crate::steps::Steps;
+    use crate::style::Style;
+    use crate::volume::Volume;
+    use chrono::TimeZone;
+
+    fn beer(name: &str, style: Style) -> Beer {
+        // A lager gets a diacetyl rest
+        let diacetyl_rest = match style.r#type() {
+            Type::Lager => Some(Interval::Days(3)),
+            Type::Ale => None,
+        };
+        let steps = Steps::new(
+            Some(Interval::Hours(6)),
+            Some(Interval::Days(7)),
+            diacetyl_rest,
+            None,
+            None,
+            None,
+        );
+        Beer::new(
+            name.to_string(),
+            style,
+            Recipe::new(Capacity::G10, Volume::GallonUS(10.0), steps),
+        )
+    }
+
+    #[test]
+    fn test_tap_requirement_matches() {
+        let pilsner = beer("Bier", Style::Pilsner);
+        let stout = beer("Dobroy Nochi", Style::ImperialStout);
+        let lager = TapRequirement::new("lager".to_string(), 1);
+        assert!(lager.matches(&pilsner));
+        assert!(!lager.matches(&stout));
+        let dark = TapRequirement::new("dark".to_string(), 1);
+        assert!(dark.matches(&stout));
+        assert!(!dark.matches(&pilsner));
+        assert!(TapRequirement::new("Imperial Stout".to_string(), 1).matches(&stout));
+        assert!(TapRequirement::new("Bier".to_string(), 1).matches(&pilsner));
+    }
+
+    #[test]
+    fn test_tap_list_shortfalls() {
+        let tap_list = mock::mock_tap_list();
+        let pilsner = beer("Bier", Style::Pilsner);
+        let stout = beer("Dobroy Nochi", Style::ImperialStout);
+        assert!(tap_list.shortfalls(&[&pilsner, &stout]).is_empty());
+        assert_eq!(
+            tap_list.shortfalls(&[&pilsner]),
+            vec![
+                "1 of 2 beers on tap".to_string(),
+                "0 of 1 dark on tap".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tap_list_moments() {
+        let tap_list = mock::mock_tap_list();
+        let now = Utc.with_ymd_and_hms(2021, 1, 4, 8, 0, 0).unwrap();
+        let half_window = Duration::hours(252);
+        assert_eq!(
+            tap_list.moments(now),
+            vec![now, now + half_window, now + half_window * 2]
+        );
+        let daily = TapList::new(2, Duration::hours(36), Duration::days(2));
+        assert_eq!(daily.moments(now).len(), 3);
+    }
+
+    #[test]
+    fn test_tap_list_from_config() {
+        let config: TapListConfig = serde_json::from_str(
+            r#"{"minimum": 2, "window": "3w", "horizon": "4w",
+                "requirements": [{"kind": "lager", "count": 1}, {"kind": "dark", "count": 1}]}"#,
+        )
+        .unwrap();
+        assert_eq!(TapList::from(&config), mock::mock_tap_list());
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::config::VisitConfig;
+
+/**
+ * A window in which an external service, like a mobile canner, is on site. Outside its visits
+ * the equipment can not be used at all, and during a visit it fills at most `cases` cases.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Visit {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub cases: u32,
+}
+
+impl Visit {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>, cases: u32) -> Self {
+        assert!(start < end);
+        Self { start, end, cases }
+    }
+
+    /// Whether a run that takes `run_time`, and can start at `ready` at the earliest, fits in.
+    pub fn fits(&self, ready: &DateTime<Utc>, run_time: Duration, cases: u32) -> bool {
+        let start = std::cmp::max(*ready, self.start);
+        start + run_time <= self.end && cases <= self.cases
+    }
+}
+
+impl std::convert::From<&VisitConfig> for Visit {
+    fn from(config: &VisitConfig) -> Self {
+        if config.start >= config.end {
+            panic!(
+                "{} does not appear to be a valid end of the visit starting at {}",
+                config.end, config.start
+            );
+        }
+        Visit::new(config.start, config.end, config.cases)
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use chrono::TimeZone;
+
+    pub fn mock_visit() -> Visit {
+        Visit::new(
+            Utc.with_ymd_and_hms(2021, 2, 3, 8, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 2, 3, 14, 0, 0).unwrap(),
+            200,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_visit_fits() {
+        let visit = mock::mock_visit();
+        let early = Utc.with_ymd_and_hms(2021, 1, 20, 8, 0, 0).unwrap();
+        let late = Utc.with_ymd_and_hms(2021, 2, 3, 12, 0, 0).unwrap();
+        assert!(visit.fits(&early, Duration::hours(6), 200));
+        assert!(!visit.fits(&early, Duration::hours(7), 200));
+        assert!(!visit.fits(&early, Duration::hours(1), 201));
+        assert!(visit.fits(&late, Duration::hours(2), 10));
+        assert!(!visit.fits(&late, Duration::hours(3), 10));
+    }
+
+    #[test]
+    fn test_visit_from_config() {
+        let config: VisitConfig = serde_json::from_str(
+            r#"{"start": "2021-02-03T08:00:00Z", "end": "2021-02-03T14:00:00Z", "cases": 200}"#,
+        )
+        .unwrap();
+        assert_eq!(Visit::from(&config), mock::mock_visit());
+    }
+}
@@ -68,6 +68,13 @@ impl Volume {
         convert_to!(Volume::BeerBarrel, self)
     }
 
+    pub fn liters(&self) -> f32 {
+        match self.to_liter() {
+            Volume::Liter(liter) => liter,
+            _ => panic!("Should not happen"),
+        }
+    }
+
     pub fn full_batches(&self, batch_size: &Volume) -> usize {
         if let Volume::Liter(need) = self.to_liter() {
             if let Volume::Liter(size) = batch_size.to_liter() {
@@ -92,6 +99,11 @@ impl std::str::FromStr for Volume {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Volume, ()> {
+        if s.len() > 2 && (s.ends_with("lb") || s.ends_with("LB")) {
+            if let Ok(pounds) = s[0..s.len() - 2].parse() {
+                return Ok(Volume::Lb(pounds));
+            }
+        }
         if s.len() > 1 && (s.ends_with('g') || s.ends_with('G')) {
             if let Ok(gallons) = s[0..s.len() - 1].parse() {
                 return Ok(Volume::GallonUS(gallons));
@@ -144,6 +156,7 @@ mod tests {
         assert_eq!(mock::mock_liter().to_liter(), Volume::Liter(12.2));
         assert_eq!(mock::mock_gallon_us().to_gallon_us(), Volume::GallonUS(5.0));
         assert_eq!(mock::mock_gallon_us().to_liter(), Volume::Liter(18.927057));
+        assert_eq!(mock::mock_gallon_us().liters(), 18.927057);
         assert_eq!(
             Volume::Liter(46.182).to_gallon_us(),
             Volume::GallonUS(12.199993)
@@ -166,6 +179,7 @@ mod tests {
         assert_eq!("5g".parse(), Ok(Volume::GallonUS(5.0)));
         assert_eq!("12.2g".parse(), Ok(Volume::GallonUS(12.2)));
         assert_eq!("5G".parse(), Ok(Volume::GallonUS(5.0)));
+        assert_eq!("20lb".parse(), Ok(Volume::Lb(20.0)));
         //assert_eq!("5 Gallon".parse().is_err(), true);
         //assert_eq!("5 L".parse().is_err(), true);
         //assert_eq!("5l".parse().is_err(), true);
@@ -0,0 +1,157 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::config::{SlurryConfig, YeastConfig};
+use crate::interval::Interval;
+
+/// The slurry that is harvested from a batch in progress, before the planning starts. It can
+/// pitch one of the batches that are planned, while it is viable.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Slurry {
+    pub batch: usize,
+    pub harvested: DateTime<Utc>,
+    pub generation: u32,
+}
+
+impl Slurry {
+    pub fn new(batch: usize, harvested: DateTime<Utc>, generation: u32) -> Self {
+        Self {
+            batch,
+            harvested,
+            generation,
+        }
+    }
+}
+
+impl std::convert::From<&SlurryConfig> for Slurry {
+    fn from(config: &SlurryConfig) -> Self {
+        Slurry::new(config.batch, config.harvested, config.generation)
+    }
+}
+
+/**
+ * A yeast strain that is harvested at the end of the primary fermentation, to pitch the next
+ * batch of a beer with the same strain. The slurry is only viable for so long, and the yeast
+ * only stays healthy for so many generations. Without a viable slurry, the yeast is propagated
+ * from the lab, which takes its time. The slurry is repitched whenever it can be, unless
+ * `propagate_if_sooner` is set: then the yeast is propagated when that finishes all the batches
+ * sooner.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct YeastStrain {
+    pub name: String,
+    pub max_generations: u32,
+    pub viability: Duration,
+    pub propagation: Duration,
+    pub propagate_if_sooner: bool,
+    pub slurries: Vec<Slurry>,
+}
+
+impl YeastStrain {
+    pub fn new(
+        name: String,
+        max_generations: u32,
+        viability: Duration,
+        propagation: Duration,
+    ) -> Self {
+        assert!(max_generations > 0);
+        Self {
+            name,
+            max_generations,
+            viability,
+            propagation,
+            propagate_if_sooner: false,
+            slurries: Vec::new(),
+        }
+    }
+
+    pub fn with_propagate_if_sooner(mut self, propagate_if_sooner: bool) -> Self {
+        self.propagate_if_sooner = propagate_if_sooner;
+        self
+    }
+
+    pub fn with_slurry(mut self, slurry: Slurry) -> Self {
+        self.slurries.push(slurry);
+        self
+    }
+}
+
+impl std::convert::From<&YeastConfig> for YeastStrain {
+    fn from(config: &YeastConfig) -> Self {
+        if config.max_generations == 0 {
+            panic!(
+                "0 does not appear to be a valid number of generations for {}",
+                config.name
+            );
+        }
+        let viability = match Interval::from_str(&config.viability) {
+            Ok(x) => x.duration(),
+            Err(_e) => panic!(
+                "{} does not appear to be a valid viability for {}",
+                config.viability, config.name
+            ),
+        };
+        let propagation = match Interval::from_str(&config.propagation) {
+            Ok(x) => x.duration(),
+            Err(_e) => panic!(
+                "{} does not appear to be a valid propagation time for {}",
+                config.propagation, config.name
+            ),
+        };
+        let mut strain = YeastStrain::new(
+            String::from(&config.name),
+            config.max_generations,
+            viability,
+            propagation,
+        )
+        .with_propagate_if_sooner(config.propagate_if_sooner);
+        for slurry_config in &config.slurry {
+            if slurry_config.generation >= config.max_generations {
+                panic!(
+                    "{} does not appear to be a valid generation of {} to repitch",
+                    slurry_config.generation, config.name
+                );
+            }
+            strain = strain.with_slurry(Slurry::from(slurry_config));
+        }
+        strain
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+
+    pub fn mock_yeast_strain() -> YeastStrain {
+        YeastStrain::new("US-05".to_string(), 3, Duration::days(7), Duration::days(2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_yeast_strain_from_config() {
+        let config: YeastConfig = serde_json::from_str(
+            r#"{"name": "US-05", "max_generations": 3, "viability": "1w", "propagation": "2d"}"#,
+        )
+        .unwrap();
+        assert_eq!(YeastStrain::from(&config), mock::mock_yeast_strain());
+        let config: YeastConfig = serde_json::from_str(
+            r#"{"name": "US-05", "max_generations": 3, "viability": "1w", "propagation": "2d",
+                "propagate_if_sooner": true,
+                "slurry": [{"batch": 7, "harvested": "2021-01-04T08:00:00Z", "generation": 2}]}"#,
+        )
+        .unwrap();
+        let harvested = Utc.with_ymd_and_hms(2021, 1, 4, 8, 0, 0).unwrap();
+        assert_eq!(
+            YeastStrain::from(&config),
+            mock::mock_yeast_strain()
+                .with_propagate_if_sooner(true)
+                .with_slurry(Slurry::new(7, harvested, 2))
+        );
+    }
+}